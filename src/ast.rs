@@ -1,7 +1,7 @@
-use crate::tokens::Token;
+use crate::tokens::{Span, Token};
 
 /// All types of AST nodes.
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Node {
     Program {
         statements: Vec<Node>,
@@ -12,21 +12,73 @@ pub enum Node {
     IntegerLiteral {
         value: i64,
     },
+    FloatLiteral {
+        value: f64,
+    },
     Boolean {
         value: bool,
     },
+    NullLiteral,
+    StringLiteral {
+        value: String,
+    },
+    /// A string literal with expressions interpolated into it, e.g.
+    /// `"a ${b} c"`. Its parts are string literals and expressions.
+    InterpolatedString {
+        parts: Vec<Node>,
+    },
+    ArrayLiteral {
+        elements: Vec<Node>,
+    },
+    /// A hash literal, whose entries are either key-value pairs or spread
+    /// expressions. Later entries override earlier ones.
+    HashLiteral {
+        entries: Vec<Node>,
+    },
+    HashPair {
+        key: Box<Node>,
+        value: Box<Node>,
+    },
+    SpreadExpression {
+        value: Box<Node>,
+        span: Span,
+    },
     FunctionLiteral {
         parameters: Vec<Node>,
         body: Box<Node>,
     },
+    /// A pattern used in place of a variable name, by let statements and
+    /// function parameters, to bind the parts of a value.
+    DestructuringPattern {
+        pattern: Box<Pattern>,
+        span: Span,
+    },
+    DefaultParameter {
+        target: Box<Node>,
+        default: Box<Node>,
+    },
+    RestParameter {
+        name: Box<Node>,
+    },
+    NamedArgument {
+        name: Box<Node>,
+        value: Box<Node>,
+    },
     PrefixExpression {
         operator: String,
         right: Box<Node>,
+        span: Span,
     },
     InfixExpression {
         left: Box<Node>,
         operator: String,
         right: Box<Node>,
+        span: Span,
+    },
+    PostfixExpression {
+        left: Box<Node>,
+        operator: String,
+        span: Span,
     },
     IfExpression {
         condition: Box<Node>,
@@ -36,14 +88,100 @@ pub enum Node {
     CallExpression {
         function: Box<Node>,
         arguments: Vec<Node>,
+        optional: bool,
+        span: Span,
+    },
+    IndexExpression {
+        left: Box<Node>,
+        index: Box<Node>,
+        optional: bool,
+        span: Span,
+    },
+    /// Accesses a field of a struct, or a string key of a hash, e.g. `p.x`.
+    MemberExpression {
+        object: Box<Node>,
+        property: Box<Node>,
+        optional: bool,
+        span: Span,
+    },
+    /// Creates an instance of a struct. Fields given without a value take the
+    /// value of the variable with the same name.
+    StructLiteral {
+        name: Box<Node>,
+        fields: Vec<(Node, Node)>,
+        span: Span,
+    },
+    TryExpression {
+        body: Box<Node>,
+        parameter: Option<Box<Node>>,
+        handler: Option<Box<Node>>,
+        finalizer: Option<Box<Node>>,
+    },
+    MatchExpression {
+        subject: Box<Node>,
+        arms: Vec<MatchArm>,
+        span: Span,
     },
     LetStatement {
         name: Box<Node>,
         value: Option<Box<Node>>,
     },
+    ConstStatement {
+        name: Box<Node>,
+        value: Box<Node>,
+    },
+    StructStatement {
+        name: Box<Node>,
+        fields: Vec<Node>,
+    },
+    /// Declares an enum, whose variants are listed with the names of their
+    /// fields. Variants without fields are declared without parentheses.
+    EnumStatement {
+        name: Box<Node>,
+        variants: Vec<(Node, Option<Vec<Node>>)>,
+    },
+    /// Adds methods to a struct or enum. Each method is a name and a function
+    /// literal; methods whose first parameter is `self` are called on
+    /// instances, the others on the type itself.
+    ImplStatement {
+        name: Box<Node>,
+        methods: Vec<(Node, Node)>,
+    },
+    /// Assigns a new value to a variable, or to an element of the array or
+    /// hash it holds, e.g. `a[0]["b"] = 1`.
+    AssignStatement {
+        target: Box<Node>,
+        value: Box<Node>,
+        span: Span,
+    },
     ReturnStatement {
         value: Option<Box<Node>>,
     },
+    ForStatement {
+        variables: Vec<Node>,
+        iterable: Box<Node>,
+        body: Box<Node>,
+    },
+    ThrowStatement {
+        value: Box<Node>,
+        span: Span,
+    },
+    /// Binds the module loaded from a file to a name. The path is relative to
+    /// the file holding the import statement.
+    ImportStatement {
+        path: String,
+        alias: Box<Node>,
+        span: Span,
+    },
+    /// Makes the names bound by a let, const, struct or enum statement at the
+    /// top level of a file visible to the files importing it.
+    ExportStatement {
+        statement: Box<Node>,
+    },
+    YieldStatement {
+        value: Box<Node>,
+        span: Span,
+    },
     ExpressionStatement {
         expression: Option<Box<Node>>,
     },
@@ -59,16 +197,44 @@ impl Node {
             Node::Program { .. } => "program".to_string(),
             Node::Identifier { value } => value.v.clone(),
             Node::IntegerLiteral { value } => value.to_string(),
+            Node::FloatLiteral { value } => format!("{:?}", value),
             Node::Boolean { value } => value.to_string(),
+            Node::NullLiteral => "null".to_string(),
+            Node::StringLiteral { value } => value.clone(),
+            Node::InterpolatedString { .. } => "\"".to_string(),
+            Node::ArrayLiteral { .. } => "[".to_string(),
+            Node::HashLiteral { .. } => "{".to_string(),
+            Node::HashPair { key, .. } => key.token_literal(),
+            Node::SpreadExpression { .. } => "...".to_string(),
             Node::FunctionLiteral { .. } => "fn".to_string(),
+            Node::DestructuringPattern { pattern, .. } => pattern.as_string(),
+            Node::DefaultParameter { target, .. } => target.token_literal(),
+            Node::RestParameter { .. } => "...".to_string(),
+            Node::NamedArgument { name, .. } => name.token_literal(),
             Node::PrefixExpression { operator, .. } => operator.clone(),
             Node::InfixExpression {
                 left: _, operator, ..
             } => operator.clone(),
+            Node::PostfixExpression { operator, .. } => operator.clone(),
             Node::IfExpression { .. } => "if".to_string(),
             Node::CallExpression { function, .. } => function.as_string(),
+            Node::IndexExpression { .. } => "[".to_string(),
+            Node::MemberExpression { .. } => ".".to_string(),
+            Node::StructLiteral { name, .. } => name.token_literal(),
+            Node::StructStatement { .. } => "struct".to_string(),
+            Node::ImplStatement { .. } => "impl".to_string(),
+            Node::EnumStatement { .. } => "enum".to_string(),
+            Node::TryExpression { .. } => "try".to_string(),
+            Node::MatchExpression { .. } => "match".to_string(),
             Node::LetStatement { .. } => "let".to_string(),
+            Node::ConstStatement { .. } => "const".to_string(),
+            Node::AssignStatement { .. } => "=".to_string(),
             Node::ReturnStatement { .. } => "return".to_string(),
+            Node::ForStatement { .. } => "for".to_string(),
+            Node::ThrowStatement { .. } => "throw".to_string(),
+            Node::ImportStatement { .. } => "import".to_string(),
+            Node::ExportStatement { .. } => "export".to_string(),
+            Node::YieldStatement { .. } => "yield".to_string(),
             Node::ExpressionStatement { expression } => {
                 if let Some(expr) = expression {
                     expr.token_literal()
@@ -90,7 +256,44 @@ impl Node {
                 .join(""),
             Node::Identifier { value } => value.v.clone(),
             Node::IntegerLiteral { value } => value.to_string(),
+            Node::FloatLiteral { value } => format!("{:?}", value),
             Node::Boolean { value } => value.to_string(),
+            Node::NullLiteral => "null".to_string(),
+            Node::StringLiteral { value } => format!("\"{}\"", value),
+            Node::InterpolatedString { parts } => format!(
+                "\"{}\"",
+                parts
+                    .iter()
+                    .map(|part| match part {
+                        Node::StringLiteral { value } => value.clone(),
+                        expression => format!("${{{}}}", expression.as_string()),
+                    })
+                    .collect::<String>()
+            ),
+            Node::ArrayLiteral { elements } => {
+                format!(
+                    "[{}]",
+                    elements
+                        .iter()
+                        .map(|e| e.as_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            }
+            Node::HashLiteral { entries } => {
+                format!(
+                    "{{{}}}",
+                    entries
+                        .iter()
+                        .map(|e| e.as_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            }
+            Node::HashPair { key, value } => {
+                format!("{}: {}", &key.as_string(), &value.as_string())
+            }
+            Node::SpreadExpression { value, .. } => format!("...{}", &value.as_string()),
             Node::FunctionLiteral { parameters, body } => {
                 format!(
                     "fn({}) {}",
@@ -102,13 +305,24 @@ impl Node {
                     &body.as_string()
                 )
             }
-            Node::PrefixExpression { operator, right } => {
+            Node::DestructuringPattern { pattern, .. } => pattern.as_string(),
+            Node::DefaultParameter { target, default } => {
+                format!("{} = {}", &target.as_string(), &default.as_string())
+            }
+            Node::RestParameter { name } => format!("...{}", &name.as_string()),
+            Node::NamedArgument { name, value } => {
+                format!("{}: {}", &name.as_string(), &value.as_string())
+            }
+            Node::PrefixExpression {
+                operator, right, ..
+            } => {
                 format!("({}{})", operator, &*right.as_string())
             }
             Node::InfixExpression {
                 left,
                 operator,
                 right,
+                ..
             } => {
                 format!(
                     "({} {} {})",
@@ -117,6 +331,9 @@ impl Node {
                     &*right.as_string()
                 )
             }
+            Node::PostfixExpression { left, operator, .. } => {
+                format!("({}{})", &*left.as_string(), operator)
+            }
             Node::IfExpression {
                 condition,
                 consequence,
@@ -138,10 +355,13 @@ impl Node {
             Node::CallExpression {
                 function,
                 arguments,
+                optional,
+                ..
             } => {
                 format!(
-                    "{}({})",
+                    "{}{}({})",
                     &function.as_string(),
+                    if *optional { "?." } else { "" },
                     &arguments
                         .iter()
                         .map(|p| p.as_string())
@@ -149,6 +369,120 @@ impl Node {
                         .join(", ")
                 )
             }
+            Node::IndexExpression {
+                left,
+                index,
+                optional,
+                ..
+            } => {
+                format!(
+                    "({}{}[{}])",
+                    &left.as_string(),
+                    if *optional { "?" } else { "" },
+                    &index.as_string()
+                )
+            }
+            Node::MemberExpression {
+                object,
+                property,
+                optional,
+                ..
+            } => {
+                format!(
+                    "({}{}.{})",
+                    &object.as_string(),
+                    if *optional { "?" } else { "" },
+                    &property.as_string()
+                )
+            }
+            Node::StructLiteral { name, fields, .. } => {
+                format!(
+                    "{} {{ {} }}",
+                    &name.as_string(),
+                    fields
+                        .iter()
+                        .map(|(f, v)| format!("{}: {}", f.as_string(), v.as_string()))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            }
+            Node::StructStatement { name, fields } => {
+                format!(
+                    "struct {} {{ {} }}",
+                    &name.as_string(),
+                    fields
+                        .iter()
+                        .map(|f| f.as_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            }
+            Node::EnumStatement { name, variants } => {
+                format!(
+                    "enum {} {{ {} }}",
+                    &name.as_string(),
+                    variants
+                        .iter()
+                        .map(|(variant, fields)| match fields {
+                            Some(fields) => format!(
+                                "{}({})",
+                                variant.as_string(),
+                                fields
+                                    .iter()
+                                    .map(|f| f.as_string())
+                                    .collect::<Vec<String>>()
+                                    .join(", ")
+                            ),
+                            None => variant.as_string(),
+                        })
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            }
+            Node::ImplStatement { name, methods } => {
+                format!(
+                    "impl {} {{ {} }}",
+                    &name.as_string(),
+                    methods
+                        .iter()
+                        .map(|(method, function)| format!(
+                            "fn {}{}",
+                            method.as_string(),
+                            function.as_string().trim_start_matches("fn")
+                        ))
+                        .collect::<Vec<String>>()
+                        .join(" ")
+                )
+            }
+            Node::TryExpression {
+                body,
+                parameter,
+                handler,
+                finalizer,
+            } => {
+                let mut s = format!("try {}", &body.as_string());
+                if let (Some(p), Some(h)) = (parameter, handler) {
+                    s.push_str(&format!(" catch ({}) {}", &p.as_string(), &h.as_string()));
+                }
+
+                if let Some(f) = finalizer {
+                    s.push_str(" finally ");
+                    s.push_str(&f.as_string());
+                }
+
+                s
+            }
+            Node::MatchExpression { subject, arms, .. } => {
+                format!(
+                    "match ({}) {{ {} }}",
+                    &subject.as_string(),
+                    &arms
+                        .iter()
+                        .map(|a| a.as_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            }
             Node::LetStatement { name, value } => {
                 let mut s = format!("let {}", &name.as_string());
                 if let Some(v) = value {
@@ -169,6 +503,34 @@ impl Node {
                 s.push(';');
                 s
             }
+            Node::ForStatement {
+                variables,
+                iterable,
+                body,
+            } => {
+                format!(
+                    "for ({} in {}) {}",
+                    variables
+                        .iter()
+                        .map(|v| v.as_string())
+                        .collect::<Vec<String>>()
+                        .join(", "),
+                    &iterable.as_string(),
+                    &body.as_string()
+                )
+            }
+            Node::ConstStatement { name, value } => {
+                format!("const {} = {};", name.as_string(), value.as_string())
+            }
+            Node::AssignStatement { target, value, .. } => {
+                format!("{} = {};", target.as_string(), value.as_string())
+            }
+            Node::ThrowStatement { value, .. } => format!("throw {};", &value.as_string()),
+            Node::ImportStatement { path, alias, .. } => {
+                format!("import \"{}\" as {};", path, alias.as_string())
+            }
+            Node::ExportStatement { statement } => format!("export {}", statement.as_string()),
+            Node::YieldStatement { value, .. } => format!("yield {};", &value.as_string()),
             Node::ExpressionStatement { expression } => {
                 let mut s = String::new();
                 if let Some(v) = expression {
@@ -187,6 +549,135 @@ impl Node {
     }
 }
 
+/// A single arm of a `match` expression.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Box<Node>>,
+    pub body: Box<Node>,
+}
+
+impl MatchArm {
+    /// Returns the string representation of a match arm.
+    pub fn as_string(&self) -> String {
+        let mut s = self.pattern.as_string();
+        if let Some(g) = &self.guard {
+            s.push_str(" if ");
+            s.push_str(&g.as_string());
+        }
+
+        s.push_str(" => ");
+        s.push_str(&self.body.as_string());
+        s
+    }
+}
+
+/// All types of patterns, which values can be matched against. Literal
+/// patterns hold literal nodes, which negative integers are folded into.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pattern {
+    Wildcard,
+    Literal {
+        value: Node,
+    },
+    Binding {
+        name: String,
+    },
+    /// Matches the remaining elements of an array, optionally binding them.
+    Rest {
+        name: Option<String>,
+    },
+    Array {
+        elements: Vec<Pattern>,
+    },
+    Hash {
+        pairs: Vec<(Node, Pattern)>,
+    },
+    Alternative {
+        patterns: Vec<Pattern>,
+    },
+    /// Matches a variant of an enum, e.g. `Shape.Circle(r)` or `Circle(r)`.
+    /// The fields of the variant aren't looked at if no patterns are given
+    /// for them.
+    Variant {
+        enum_name: Option<String>,
+        variant: String,
+        fields: Option<Vec<Pattern>>,
+    },
+}
+
+impl Pattern {
+    /// Returns true if the pattern matches any value.
+    pub fn is_irrefutable(&self) -> bool {
+        match self {
+            Pattern::Wildcard | Pattern::Binding { .. } => true,
+            Pattern::Alternative { patterns } => patterns.iter().any(|p| p.is_irrefutable()),
+            _ => false,
+        }
+    }
+
+    /// Returns the names of the variables bound by the pattern.
+    pub fn bindings(&self) -> Vec<String> {
+        match self {
+            Pattern::Binding { name } | Pattern::Rest { name: Some(name) } => vec![name.clone()],
+            Pattern::Array { elements: patterns } | Pattern::Alternative { patterns } => {
+                patterns.iter().flat_map(|p| p.bindings()).collect()
+            }
+            Pattern::Hash { pairs } => pairs.iter().flat_map(|(_, p)| p.bindings()).collect(),
+            Pattern::Variant {
+                fields: Some(patterns),
+                ..
+            } => patterns.iter().flat_map(|p| p.bindings()).collect(),
+            Pattern::Variant { fields: None, .. } => vec![],
+            Pattern::Wildcard | Pattern::Literal { .. } | Pattern::Rest { name: None } => vec![],
+        }
+    }
+
+    /// Returns the string representation of a pattern.
+    pub fn as_string(&self) -> String {
+        let join = |patterns: &[Pattern], separator: &str| {
+            patterns
+                .iter()
+                .map(|p| p.as_string())
+                .collect::<Vec<String>>()
+                .join(separator)
+        };
+
+        match self {
+            Pattern::Wildcard => "_".to_string(),
+            Pattern::Literal { value } => value.as_string(),
+            Pattern::Binding { name } => name.clone(),
+            Pattern::Rest { name } => format!("..{}", name.as_deref().unwrap_or("")),
+            Pattern::Array { elements } => format!("[{}]", join(elements, ", ")),
+            Pattern::Hash { pairs } => format!(
+                "{{{}}}",
+                pairs
+                    .iter()
+                    .map(|(k, p)| format!("{}: {}", k.as_string(), p.as_string()))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Pattern::Alternative { patterns } => join(patterns, " | "),
+            Pattern::Variant {
+                enum_name,
+                variant,
+                fields,
+            } => {
+                let mut s = match enum_name {
+                    Some(enum_name) => format!("{}.{}", enum_name, variant),
+                    None => variant.clone(),
+                };
+
+                if let Some(fields) = fields {
+                    s.push_str(&format!("({})", join(fields, ", ")));
+                }
+
+                s
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
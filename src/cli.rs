@@ -0,0 +1,115 @@
+use crate::eval::object::Object;
+use crate::eval::{memory, module, prelude, random, Environment};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::repl;
+use std::{env, fs, process};
+
+/// The options that can be passed on the command line.
+#[derive(Debug, Default)]
+struct Options {
+    path: Option<String>,
+    max_memory: Option<usize>,
+    memory_report: bool,
+    seed: Option<u64>,
+}
+
+/// Parse the command line and either run the given script or start the REPL.
+///
+/// Usage: `interpreter [--max-memory BYTES] [--memory-report] [--seed SEED] [FILE]`
+pub fn run() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    };
+
+    memory::set_limit(options.max_memory);
+    if let Some(seed) = options.seed {
+        random::seed(seed);
+    }
+
+    let code = match &options.path {
+        Some(path) => run_file(path),
+        None => {
+            repl::start();
+            0
+        }
+    };
+
+    if options.memory_report {
+        eprintln!("{}", memory::usage());
+    }
+
+    process::exit(code);
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-memory" => {
+                let value = args.next().ok_or("--max-memory expects a value")?;
+                let bytes = value
+                    .parse()
+                    .map_err(|_| format!("invalid value for --max-memory: {}", value))?;
+                options.max_memory = Some(bytes);
+            }
+            "--memory-report" => options.memory_report = true,
+            "--seed" => {
+                let value = args.next().ok_or("--seed expects a value")?;
+                let seed = value
+                    .parse()
+                    .map_err(|_| format!("invalid value for --seed: {}", value))?;
+                options.seed = Some(seed);
+            }
+            _ => options.path = Some(arg),
+        }
+    }
+
+    Ok(options)
+}
+
+fn run_file(path: &str) -> i32 {
+    let input = match fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("could not read {}: {}", path, e);
+            return 1;
+        }
+    };
+
+    let lexer = Lexer::new(&input);
+    let mut parser = Parser::new(lexer);
+
+    let program = parser.parse_program();
+    parser.warnings.iter().for_each(|w| {
+        eprintln!("{}", w);
+    });
+
+    if !parser.errors.is_empty() {
+        parser.errors.iter().for_each(|e| {
+            eprintln!("{:?}", e);
+        });
+
+        return 1;
+    }
+
+    let mut environment = Environment::new();
+    let loaded = prelude::load(&mut environment);
+    let evaluated = match loaded {
+        Object::Error { .. } => loaded,
+        _ => module::run_file(path, program, &mut environment),
+    };
+
+    match evaluated {
+        Object::Error { value } => {
+            eprintln!("{}", value.traceback());
+            1
+        }
+        _ => 0,
+    }
+}
@@ -1,10 +1,30 @@
-use crate::ast::Node;
+use crate::ast::{MatchArm, Node};
+use crate::tokens::{Span, Token, TokenType};
 
+pub mod bigint;
+pub mod builtins;
 pub mod environment;
+pub mod error;
+pub mod generator;
+pub mod iterator;
+pub mod json;
+pub mod math;
+pub mod memory;
+pub mod module;
 pub mod object;
+pub mod pattern;
+pub mod prelude;
+pub mod random;
 
+use bigint::BigInt;
 pub use environment::Environment;
-use object::Object;
+use error::RuntimeError;
+use error::{ErrorKind, Frame};
+use generator::Generator;
+use iterator::Sequence;
+use object::{HashKey, Object};
+use std::collections::BTreeMap;
+use std::mem;
 
 const NULL: Object = Object::Null;
 const TRUE: Object = Object::Boolean { value: true };
@@ -16,6 +36,7 @@ pub fn eval(node: Node, environment: &mut Environment) -> Object {
     match node {
         Node::Program { statements } => eval_program(statements, environment),
         Node::IntegerLiteral { value: v } => Object::Integer { value: v },
+        Node::FloatLiteral { value: v } => Object::Float { value: v },
         Node::Boolean { value: v } => {
             if v {
                 TRUE
@@ -23,6 +44,21 @@ pub fn eval(node: Node, environment: &mut Environment) -> Object {
                 FALSE
             }
         }
+        Node::NullLiteral => NULL,
+        Node::StringLiteral { value } => Object::String { value },
+        Node::InterpolatedString { parts } => eval_interpolated_string(parts, environment),
+        Node::ArrayLiteral { elements } => {
+            let elements = eval_expressions(elements, environment);
+            if elements.len() == 1 && is_unwinding(&elements[0]) {
+                return elements[0].clone();
+            }
+
+            Object::Array {
+                elements,
+                frozen: false,
+            }
+        }
+        Node::HashLiteral { entries } => eval_hash_literal(entries, environment),
         Node::ExpressionStatement { expression } => {
             if let Some(e) = expression {
                 eval(*e, environment)
@@ -30,30 +66,88 @@ pub fn eval(node: Node, environment: &mut Environment) -> Object {
                 NULL
             }
         }
-        Node::PrefixExpression { operator, right } => {
+        Node::PrefixExpression {
+            operator,
+            right,
+            span,
+        } => {
+            let right = eval(*right, environment);
+            if is_unwinding(&right) {
+                return right;
+            }
+
+            with_span(eval_prefix_expression(operator, right), span)
+        }
+        Node::InfixExpression {
+            left,
+            operator,
+            right,
+            ..
+        } if operator == "??" => {
+            let left = eval(*left, environment);
+            if left != NULL {
+                return left;
+            }
+
+            eval(*right, environment)
+        }
+        Node::InfixExpression {
+            left,
+            operator,
+            right,
+            ..
+        } if operator == "&&" || operator == "||" => {
+            let left = eval(*left, environment);
+            if is_unwinding(&left) {
+                return left;
+            }
+
+            // The right operand is only evaluated if it decides the result.
+            let short_circuit = operator == "||";
+            if is_truthy(left) == short_circuit {
+                return Object::Boolean {
+                    value: short_circuit,
+                };
+            }
+
             let right = eval(*right, environment);
-            if is_error(right.clone()) {
+            if is_unwinding(&right) {
                 return right;
             }
 
-            eval_prefix_expression(operator, right)
+            Object::Boolean {
+                value: is_truthy(right),
+            }
         }
         Node::InfixExpression {
             left,
             operator,
             right,
+            span,
         } => {
             let right = eval(*right, environment);
-            if is_error(right.clone()) {
+            if is_unwinding(&right) {
                 return right;
             }
 
             let left = eval(*left, environment);
-            if is_error(left.clone()) {
+            if is_unwinding(&left) {
                 return left;
             }
 
-            eval_infix_expression(operator, left, right)
+            with_span(eval_infix_expression(operator, left, right), span)
+        }
+        Node::PostfixExpression {
+            left,
+            operator,
+            span,
+        } => {
+            let left = eval(*left, environment);
+            if is_unwinding(&left) {
+                return left;
+            }
+
+            with_span(eval_postfix_expression(operator, left), span)
         }
         Node::BlockStatement { statements } => eval_block_statement(statements, environment),
         Node::IfExpression {
@@ -61,64 +155,325 @@ pub fn eval(node: Node, environment: &mut Environment) -> Object {
             consequence,
             alternative,
         } => eval_if_expression(*condition, *consequence, alternative, environment),
+        Node::ForStatement {
+            variables,
+            iterable,
+            body,
+        } => {
+            let iterable = eval(*iterable, environment);
+            if is_unwinding(&iterable) {
+                return iterable;
+            }
+
+            eval_for_statement(variables, iterable, *body, environment)
+        }
+        Node::TryExpression {
+            body,
+            parameter,
+            handler,
+            finalizer,
+        } => eval_try_expression(*body, parameter, handler, finalizer, environment),
+        Node::MatchExpression {
+            subject,
+            arms,
+            span,
+        } => {
+            let subject = eval(*subject, environment);
+            if is_unwinding(&subject) {
+                return subject;
+            }
+
+            eval_match_expression(subject, arms, span, environment)
+        }
+        Node::ThrowStatement { value, span } => {
+            let value = eval(*value, environment);
+            if is_unwinding(&value) {
+                return value;
+            }
+
+            with_span(
+                Object::Error {
+                    value: Box::new(RuntimeError::thrown(value)),
+                },
+                span,
+            )
+        }
+        // Generator functions run their yield statements on their own, so any
+        // yield reaching this point is outside of a generator.
+        Node::YieldStatement { span, .. } => with_span(
+            Object::error(
+                ErrorKind::InvalidYield,
+                "yield outside of a generator function".to_string(),
+            ),
+            span,
+        ),
         Node::ReturnStatement { value } => match value {
             Some(v) => {
-                let evaluated = eval(*v.clone(), environment);
-                if is_error(evaluated.clone()) {
+                let evaluated = eval(*v, environment);
+                if is_unwinding(&evaluated) {
                     return evaluated;
                 }
 
                 Object::ReturnValue {
-                    value: Box::new(eval(*v, environment)),
+                    value: Box::new(evaluated),
                 }
             }
             None => Object::ReturnValue {
                 value: Box::new(NULL),
             },
         },
-        Node::LetStatement { name, value } => {
-            if let Some(v) = value {
-                let val = eval(*v, environment);
-                if is_error(val.clone()) {
-                    return val;
-                }
-
-                environment.set((*name).as_string(), val);
+        Node::LetStatement { name, value } => match value {
+            Some(v) => eval_binding(*name, *v, false, environment),
+            None => NULL,
+        },
+        Node::ConstStatement { name, value } => eval_binding(*name, *value, true, environment),
+        Node::AssignStatement {
+            target,
+            value,
+            span,
+        } => {
+            let value = eval(*value, environment);
+            if is_unwinding(&value) {
+                return value;
             }
 
-            NULL
+            with_span(eval_assign_statement(*target, value, environment), span)
         }
-        Node::Identifier { value } => eval_identifier(value.v, environment),
+        Node::Identifier { value } => with_span(eval_identifier(value.v, environment), value.span),
+        // These nodes are only ever evaluated as part of the binding, call or
+        // literal they belong to.
+        Node::DestructuringPattern { .. }
+        | Node::DefaultParameter { .. }
+        | Node::RestParameter { .. }
+        | Node::NamedArgument { .. }
+        | Node::HashPair { .. }
+        | Node::SpreadExpression { .. } => NULL,
         Node::FunctionLiteral {
             parameters, body, ..
         } => Object::Function {
+            name: None,
             parameters,
             body: *body,
-            env: environment.clone(),
+            env: Box::new(environment.clone()),
         },
         Node::CallExpression {
             function,
             arguments,
+            optional,
+            span,
         } => {
-            let function = eval(*function, environment);
-            if is_error(function.clone()) {
-                return function;
+            // Calling a member of a value may call a method, which takes the
+            // value as its first argument.
+            let (function, receiver) = match *function {
+                Node::MemberExpression {
+                    object,
+                    property,
+                    optional,
+                    span,
+                } => {
+                    let object = eval(*object, environment);
+                    if is_unwinding(&object) {
+                        return object;
+                    }
+
+                    if optional && object == NULL {
+                        return NULL;
+                    }
+
+                    match resolve_method(object, &property.as_string(), environment) {
+                        Ok(method) => method,
+                        Err(e) => return with_span(e, span),
+                    }
+                }
+                function => {
+                    let function = eval(function, environment);
+                    if is_unwinding(&function) {
+                        return function;
+                    }
+
+                    (function, None)
+                }
+            };
+
+            if optional && function == NULL {
+                return NULL;
             }
 
-            let args = eval_expressions(arguments, environment);
-            if !args.is_empty() && is_error(args[0].clone()) {
+            // Positional arguments are evaluated first, then named ones.
+            let (named_arguments, arguments): (Vec<Node>, Vec<Node>) = arguments
+                .into_iter()
+                .partition(|a| matches!(a, Node::NamedArgument { .. }));
+
+            let mut args = eval_expressions(arguments, environment);
+            if args.len() == 1 && is_unwinding(&args[0]) {
                 return args[0].clone();
             }
 
-            apply_function(function, args)
+            if let Some(receiver) = receiver {
+                args.insert(0, receiver);
+            }
+
+            let mut named = vec![];
+            for argument in named_arguments {
+                if let Node::NamedArgument { name, value } = argument {
+                    let value = eval(*value, environment);
+                    if is_unwinding(&value) {
+                        return value;
+                    }
+
+                    named.push((name.as_string(), value));
+                }
+            }
+
+            let name = match &function {
+                Object::Function { name, .. } => Some(name.clone()),
+                Object::Builtin { name } => Some(Some(name.clone())),
+                _ => None,
+            };
+
+            let builtin = matches!(function, Object::Builtin { .. });
+            let evaluated = apply_function_with_named(function, args, named);
+            match (with_span(evaluated, span), name) {
+                // A builtin such as `next` resumed a generator, whose frame
+                // stands in for the call of the builtin.
+                (Object::Error { mut value }, Some(_))
+                    if builtin && value.frames.last().is_some_and(|f| f.span.is_none()) =>
+                {
+                    if let Some(frame) = value.frames.last_mut() {
+                        frame.span = Some(span);
+                    }
+                    Object::Error { value }
+                }
+                (Object::Error { mut value }, Some(function)) => {
+                    value.frames.push(Frame {
+                        function,
+                        span: Some(span),
+                        file: None,
+                    });
+                    Object::Error { value }
+                }
+                (evaluated, _) => evaluated,
+            }
+        }
+        Node::IndexExpression {
+            left,
+            index,
+            optional,
+            span,
+        } => {
+            let left = eval(*left, environment);
+            if is_unwinding(&left) {
+                return left;
+            }
+
+            if optional && left == NULL {
+                return NULL;
+            }
+
+            let index = eval(*index, environment);
+            if is_unwinding(&index) {
+                return index;
+            }
+
+            with_span(eval_index_expression(left, index), span)
+        }
+        Node::MemberExpression {
+            object,
+            property,
+            optional,
+            span,
+        } => {
+            let object = eval(*object, environment);
+            if is_unwinding(&object) {
+                return object;
+            }
+
+            if optional && object == NULL {
+                return NULL;
+            }
+
+            with_span(eval_member_expression(object, property.as_string()), span)
+        }
+        Node::StructLiteral { name, fields, span } => {
+            with_span(eval_struct_literal(*name, fields, environment), span)
+        }
+        Node::StructStatement { name, fields } => {
+            let declared = Object::StructType {
+                name: name.as_string(),
+                fields: fields.iter().map(|f| f.as_string()).collect(),
+                methods: BTreeMap::new(),
+            };
+
+            match environment.set(name.as_string(), declared) {
+                Ok(_) => NULL,
+                Err(e) => e.into(),
+            }
+        }
+        Node::EnumStatement { name, variants } => {
+            let declared = Object::EnumType {
+                name: name.as_string(),
+                variants: variants
+                    .iter()
+                    .map(|(v, fields)| {
+                        let fields = fields
+                            .as_ref()
+                            .map(|f| f.iter().map(|f| f.as_string()).collect());
+                        (v.as_string(), fields)
+                    })
+                    .collect(),
+                methods: BTreeMap::new(),
+            };
+
+            match environment.set(name.as_string(), declared) {
+                Ok(_) => NULL,
+                Err(e) => e.into(),
+            }
+        }
+        Node::ImplStatement { name, methods } => eval_impl_statement(*name, methods, environment),
+        Node::ImportStatement { path, alias, span } => {
+            let imported = module::import(&path, span);
+            if is_unwinding(&imported) {
+                return with_span(imported, span);
+            }
+
+            match environment.set(alias.as_string(), imported) {
+                Ok(_) => NULL,
+                Err(e) => with_span(e.into(), span),
+            }
+        }
+        Node::ExportStatement { statement } => eval(*statement, environment),
+    }
+}
+
+/// Joins the parts of an interpolated string, showing the values of the
+/// interpolated expressions the way `inspect` does.
+fn eval_interpolated_string(parts: Vec<Node>, environment: &mut Environment) -> Object {
+    let mut value = String::new();
+    for part in parts {
+        let evaluated = eval(part, environment);
+        if is_unwinding(&evaluated) {
+            return evaluated;
         }
+
+        let part = evaluated.inspect();
+        if let Err(e) = reserve_memory(value.len() + part.len()) {
+            return e.into();
+        }
+
+        value.push_str(&part);
     }
+
+    Object::String { value }
 }
 
 fn eval_program(statements: Vec<Node>, environment: &mut Environment) -> Object {
     let mut s = Object::Null;
     for statement in statements {
         s = eval(statement, environment);
+        if let Some(e) = check_memory_limit() {
+            return e;
+        }
+
         match s {
             Object::ReturnValue { value } => {
                 return *value;
@@ -137,6 +492,10 @@ fn eval_block_statement(statements: Vec<Node>, environment: &mut Environment) ->
     let mut s = Object::Null;
     for statement in statements {
         s = eval(statement, environment);
+        if let Some(e) = check_memory_limit() {
+            return e;
+        }
+
         if matches!(s, Object::ReturnValue { .. }) || matches!(s, Object::Error { .. }) {
             break;
         }
@@ -149,9 +508,25 @@ fn eval_prefix_expression(operator: String, right: Object) -> Object {
     match operator.as_str() {
         "!" => eval_bang_operator_expression(right),
         "-" => eval_minus_operator_expression(right),
-        _ => Object::Error {
-            value: format!("unknown operator: {}{}", operator, right.name()),
+        _ => Object::error(
+            ErrorKind::UnknownOperator,
+            format!("unknown operator: {}{}", operator, right.name()),
+        ),
+    }
+}
+
+fn eval_postfix_expression(operator: String, left: Object) -> Object {
+    match (operator.as_str(), left) {
+        // `?` unwraps an ok value, or returns an err from the enclosing
+        // function by unwinding like a return statement.
+        ("?", Object::Result { value: Ok(v) }) => *v,
+        ("?", Object::Result { value: Err(e) }) => Object::ReturnValue {
+            value: Box::new(Object::Result { value: Err(e) }),
         },
+        (_, left) => Object::error(
+            ErrorKind::UnknownOperator,
+            format!("unknown operator: {}{}", left.name(), operator),
+        ),
     }
 }
 
@@ -160,31 +535,75 @@ fn eval_infix_expression(operator: String, left: Object, right: Object) -> Objec
         (Object::Integer { value: v1 }, _, Object::Integer { value: v2 }) => {
             eval_integer_infix_expression(operator, v1, v2)
         }
+        // Integers are promoted to floats when they meet one.
+        (Object::Float { value: v1 }, _, Object::Float { value: v2 }) => {
+            eval_float_infix_expression(operator, v1, v2)
+        }
+        (Object::Integer { value: v1 }, _, Object::Float { value: v2 }) => {
+            eval_float_infix_expression(operator, v1 as f64, v2)
+        }
+        (Object::Float { value: v1 }, _, Object::Integer { value: v2 }) => {
+            eval_float_infix_expression(operator, v1, v2 as f64)
+        }
+        // Integers are promoted to big integers when they meet one, and big
+        // integers to floats.
+        (Object::BigInt { value: v1 }, _, Object::BigInt { value: v2 }) => {
+            eval_bigint_infix_expression(operator, v1, v2)
+        }
+        (Object::Integer { value: v1 }, _, Object::BigInt { value: v2 }) => {
+            eval_bigint_infix_expression(operator, BigInt::from(v1), v2)
+        }
+        (Object::BigInt { value: v1 }, _, Object::Integer { value: v2 }) => {
+            eval_bigint_infix_expression(operator, v1, BigInt::from(v2))
+        }
+        (Object::BigInt { value: v1 }, _, Object::Float { value: v2 }) => {
+            eval_float_infix_expression(operator, v1.to_f64(), v2)
+        }
+        (Object::Float { value: v1 }, _, Object::BigInt { value: v2 }) => {
+            eval_float_infix_expression(operator, v1, v2.to_f64())
+        }
+        (Object::String { value: v1 }, "+", Object::String { value: v2 }) => {
+            match reserve_memory(v1.len() + v2.len()) {
+                Ok(()) => Object::String { value: v1 + &v2 },
+                Err(e) => e.into(),
+            }
+        }
+        // Strings are ordered by their characters' code points.
+        (Object::String { value: v1 }, "<", Object::String { value: v2 }) => {
+            Object::Boolean { value: v1 < v2 }
+        }
+        (Object::String { value: v1 }, ">", Object::String { value: v2 }) => {
+            Object::Boolean { value: v1 > v2 }
+        }
+        // Collections and structs are compared by their contents, and frozen
+        // ones are equal to unfrozen ones with the same contents.
         (_, "==", _) => Object::Boolean {
-            value: left == right,
+            value: left.freeze() == right.freeze(),
         },
         (_, "!=", _) => Object::Boolean {
-            value: left != right,
+            value: left.freeze() != right.freeze(),
         },
         _ => {
             if left.name() != right.name() {
-                Object::Error {
-                    value: format!(
+                Object::error(
+                    ErrorKind::TypeMismatch,
+                    format!(
                         "type mismatch: {} {} {}",
                         left.name(),
                         operator,
                         right.name()
                     ),
-                }
+                )
             } else {
-                Object::Error {
-                    value: format!(
+                Object::error(
+                    ErrorKind::UnknownOperator,
+                    format!(
                         "unknown operator: {} {} {}",
                         left.name(),
                         operator,
                         right.name()
                     ),
-                }
+                )
             }
         }
     }
@@ -197,7 +616,7 @@ fn eval_if_expression(
     environment: &mut Environment,
 ) -> Object {
     let condition = eval(condition, environment);
-    if is_error(condition.clone()) {
+    if is_unwinding(&condition) {
         return condition;
     }
 
@@ -210,142 +629,1252 @@ fn eval_if_expression(
     }
 }
 
-fn eval_bang_operator_expression(right: Object) -> Object {
-    match right {
-        TRUE => FALSE,
-        FALSE => TRUE,
-        NULL => TRUE,
-        _ => FALSE,
-    }
-}
-
-fn eval_minus_operator_expression(right: Object) -> Object {
-    match right {
-        Object::Integer { value } => Object::Integer { value: -value },
-        _ => {
-            return Object::Error {
-                value: format!("unknown operator: -{}", right.name()),
-            }
+fn eval_for_statement(
+    variables: Vec<Node>,
+    iterable: Object,
+    body: Node,
+    environment: &mut Environment,
+) -> Object {
+    let entries = match iterable.iterate() {
+        Some(entries) => entries,
+        None => {
+            return Object::error(
+                ErrorKind::NotIterable,
+                format!("cannot iterate over {}", iterable.name()),
+            )
         }
-    }
-}
+    };
 
-fn eval_integer_infix_expression(operator: String, left: i64, right: i64) -> Object {
-    match operator.as_str() {
-        "+" => Object::Integer {
-            value: left + right,
-        },
-        "-" => Object::Integer {
-            value: left - right,
-        },
-        "*" => Object::Integer {
-            value: left * right,
-        },
-        "/" => Object::Integer {
-            value: left / right,
-        },
-        "<" => Object::Boolean {
-            value: left < right,
-        },
-        ">" => Object::Boolean {
-            value: left > right,
-        },
-        "==" => Object::Boolean {
-            value: left == right,
-        },
-        "!=" => Object::Boolean {
-            value: left != right,
-        },
-        _ => {
-            return Object::Error {
-                value: format!("unknown operator: INTEGER {} INTEGER", operator),
-            }
+    // Loop variables are scoped to the loop: whatever they shadowed is put
+    // back once the loop is done.
+    let names: Vec<String> = variables.iter().map(|v| v.as_string()).collect();
+    let shadowed: Vec<Option<Object>> = names
+        .iter()
+        .map(|n| environment.storage.get(n).cloned())
+        .collect();
+
+    let mut result = NULL;
+    for (key, value) in entries {
+        // Iterators hand back errors raised by their callbacks as items.
+        if is_unwinding(&value) {
+            result = value;
+            break;
         }
-    }
-}
 
-fn eval_identifier(name: String, environment: &mut Environment) -> Object {
-    match environment.get(&name) {
-        Some(v) => v,
-        None => Object::Error {
-            value: format!("identifier not found: {}", name),
-        },
-    }
-}
+        // Every iteration binds the loop variables anew, so closures created
+        // in the body capture the value of the current iteration.
+        let bindings = match (names.as_slice(), &iterable) {
+            ([item], Object::Hash { .. }) => vec![(item.clone(), key)],
+            ([item], _) => vec![(item.clone(), value)],
+            ([k, v], _) => vec![(k.clone(), key), (v.clone(), value)],
+            _ => vec![],
+        };
 
-fn eval_expressions(expressions: Vec<Node>, env: &mut Environment) -> Vec<Object> {
-    let mut result = vec![];
+        if let Err(e) = bindings
+            .into_iter()
+            .try_for_each(|(name, value)| environment.set(name, value).map(drop))
+        {
+            result = e.into();
+            break;
+        }
 
-    for e in expressions {
-        let evaluated = eval(e, env);
-        if is_error(evaluated.clone()) {
-            return vec![evaluated];
+        let evaluated = eval(body.clone(), environment);
+        if is_unwinding(&evaluated) {
+            result = evaluated;
+            break;
         }
+    }
 
-        result.push(evaluated);
+    // Constants can't be used as loop variables, so putting back what the
+    // loop variables shadowed never fails.
+    for (name, previous) in names.into_iter().zip(shadowed) {
+        match previous {
+            Some(p) => environment.set(name, p).ok().flatten(),
+            None => environment.remove(&name),
+        };
     }
 
     result
 }
 
-fn apply_function(function: Object, args: Vec<Object>) -> Object {
-    match function {
+/// Evaluates the value of a let or const statement, and binds it.
+fn eval_binding(name: Node, value: Node, constant: bool, environment: &mut Environment) -> Object {
+    let val = eval(value, environment);
+    if is_unwinding(&val) {
+        return val;
+    }
+
+    // Anonymous functions take the name of the first binding they are
+    // assigned to, so that they can be named in stack traces.
+    let val = match val {
         Object::Function {
+            name: None,
+            parameters,
+            body,
+            env,
+        } if matches!(name, Node::Identifier { .. }) => Object::Function {
+            name: Some(name.as_string()),
             parameters,
             body,
             env,
-        } => {
-            let mut extended_env = create_function_env(parameters, args, env);
-            let evaluated = eval(body, &mut extended_env);
-            unwrap_return_value(evaluated)
-        }
-        _ => Object::Error {
-            value: format!("not a function: {}", function.name()),
         },
-    }
-}
+        _ => val,
+    };
 
-fn create_function_env(parameters: Vec<Node>, args: Vec<Object>, env: Environment) -> Environment {
-    let mut enclosed_env = Environment::new_enclosed(env);
-    parameters.iter().zip(args.iter()).for_each(|(p, a)| {
-        enclosed_env.set(p.as_string(), a.clone());
-    });
+    bind(&name, val, constant, environment).unwrap_or(NULL)
+}
 
-    enclosed_env
+/// A step from a value to one of its parts, on the left of an assignment.
+enum Accessor {
+    Index(Object),
+    Field(String),
 }
 
-fn unwrap_return_value(evaluated: Object) -> Object {
-    match evaluated {
-        Object::ReturnValue { value } => *value,
-        _ => evaluated,
+/// Assigns a value to a variable, or to an element of the array, hash or
+/// struct it holds. Collections are values, so the variable is bound to an
+/// updated copy of its collection, unless the variable is a constant or the
+/// collection is frozen.
+fn eval_assign_statement(target: Node, value: Object, environment: &mut Environment) -> Object {
+    let mut accessors = vec![];
+    let mut root = target;
+    loop {
+        root = match root {
+            Node::IndexExpression { left, index, .. } => {
+                let index = eval(*index, environment);
+                if is_unwinding(&index) {
+                    return index;
+                }
+
+                accessors.push(Accessor::Index(index));
+                *left
+            }
+            Node::MemberExpression {
+                object, property, ..
+            } => {
+                accessors.push(Accessor::Field(property.as_string()));
+                *object
+            }
+            _ => break,
+        };
     }
-}
 
-fn is_truthy(condition: Object) -> bool {
-    match condition {
-        NULL => false,
-        TRUE => true,
-        FALSE => false,
-        _ => true,
+    accessors.reverse();
+    let name = root.as_string();
+    if environment.is_constant(&name) {
+        return Object::error(
+            ErrorKind::ConstantReassignment,
+            format!("cannot reassign constant {}", name),
+        );
+    }
+
+    let current = match environment.get(&name) {
+        Some(current) => current,
+        None => {
+            return Object::error(
+                ErrorKind::UnknownIdentifier,
+                format!("identifier not found: {}", name),
+            )
+        }
+    };
+
+    match assign_part(current, &accessors, value).and_then(|v| environment.assign(name, v)) {
+        Ok(_) => NULL,
+        Err(e) => e.into(),
     }
 }
 
-fn is_error(object: Object) -> bool {
-    matches!(object, Object::Error { .. })
+/// Returns a copy of a collection, whose part at the end of the given chain
+/// of accessors is replaced by a value.
+fn assign_part(
+    target: Object,
+    accessors: &[Accessor],
+    value: Object,
+) -> Result<Object, RuntimeError> {
+    let (accessor, rest) = match accessors.split_first() {
+        Some(split) => split,
+        None => return Ok(value),
+    };
+
+    match (target, accessor) {
+        (
+            target @ Object::Array { frozen: true, .. }
+            | target @ Object::Hash { frozen: true, .. }
+            | target @ Object::Struct { frozen: true, .. }
+            | target @ Object::Module { .. },
+            _,
+        ) => Err(RuntimeError::new(
+            ErrorKind::FrozenMutation,
+            format!("cannot modify frozen {}", target.name()),
+        )),
+        (Object::Array { mut elements, .. }, Accessor::Index(index)) => {
+            let position = match index {
+                Object::Integer { value } if *value >= 0 && (*value as usize) < elements.len() => {
+                    *value as usize
+                }
+                Object::Integer { value } => {
+                    return Err(RuntimeError::new(
+                        ErrorKind::IndexOutOfRange,
+                        format!(
+                            "index out of range: {} for ARRAY of length {}",
+                            value,
+                            elements.len()
+                        ),
+                    ))
+                }
+                _ => {
+                    return Err(RuntimeError::new(
+                        ErrorKind::TypeMismatch,
+                        format!("array index must be INTEGER, got {}", index.name()),
+                    ))
+                }
+            };
+
+            let element = mem::replace(&mut elements[position], NULL);
+            elements[position] = assign_part(element, rest, value)?;
+            Ok(Object::Array {
+                elements,
+                frozen: false,
+            })
+        }
+        (Object::Hash { mut pairs, .. }, accessor) => {
+            let key = match accessor {
+                Accessor::Index(index) => index.hash_key().ok_or_else(|| {
+                    RuntimeError::new(
+                        ErrorKind::UnusableHashKey,
+                        format!("unusable as hash key: {}", index.name()),
+                    )
+                })?,
+                Accessor::Field(field) => HashKey::String {
+                    value: field.clone(),
+                },
+            };
+
+            let element = pairs.remove(&key).unwrap_or(NULL);
+            pairs.insert(key, assign_part(element, rest, value)?);
+            Ok(Object::Hash {
+                pairs,
+                frozen: false,
+            })
+        }
+        (
+            Object::Struct {
+                name, mut fields, ..
+            },
+            Accessor::Field(field),
+        ) => {
+            let position = fields.iter().position(|(f, _)| f == field).ok_or_else(|| {
+                RuntimeError::new(
+                    ErrorKind::UnknownField,
+                    format!("no field {} on {}", field, name),
+                )
+            })?;
+
+            let element = mem::replace(&mut fields[position].1, NULL);
+            fields[position].1 = assign_part(element, rest, value)?;
+            Ok(Object::Struct {
+                name,
+                fields,
+                frozen: false,
+            })
+        }
+        (target, Accessor::Index(_)) => Err(RuntimeError::new(
+            ErrorKind::UnknownOperator,
+            format!("index assignment not supported: {}", target.name()),
+        )),
+        (target, Accessor::Field(field)) => Err(RuntimeError::new(
+            ErrorKind::UnknownField,
+            format!("no field {} on {}", field, target.name()),
+        )),
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::lexer::Lexer;
-    use crate::parser::Parser;
+/// Creates an instance of a struct. Every field of the struct has to be
+/// given a value, exactly once.
+fn eval_struct_literal(
+    name: Node,
+    fields: Vec<(Node, Node)>,
+    environment: &mut Environment,
+) -> Object {
+    let (name, declared) = match eval(name, environment) {
+        Object::StructType { name, fields, .. } => (name, fields),
+        other if is_unwinding(&other) => return other,
+        other => {
+            return Object::error(
+                ErrorKind::TypeMismatch,
+                format!("{} is not a struct", other.name()),
+            )
+        }
+    };
 
-    #[test]
-    fn test_eval_integer_expression() {
-        let table = vec![
-            ("5;".to_string(), 5),
-            ("10;".to_string(), 10),
-            ("-5;".to_string(), -5),
+    let mut values: Vec<Option<Object>> = vec![None; declared.len()];
+    for (field, value) in fields {
+        let field = field.as_string();
+        let slot = match declared.iter().position(|f| *f == field) {
+            Some(position) => &mut values[position],
+            None => {
+                return Object::error(
+                    ErrorKind::UnknownField,
+                    format!("no field {} on {}", field, name),
+                )
+            }
+        };
+
+        if slot.is_some() {
+            return Object::error(
+                ErrorKind::InvalidArgument,
+                format!("field {} given more than once for {}", field, name),
+            );
+        }
+
+        let value = eval(value, environment);
+        if is_unwinding(&value) {
+            return value;
+        }
+
+        *slot = Some(value);
+    }
+
+    let mut instance = vec![];
+    for (field, value) in declared.into_iter().zip(values) {
+        match value {
+            Some(value) => instance.push((field, value)),
+            None => {
+                return Object::error(
+                    ErrorKind::InvalidArgument,
+                    format!("missing field {} for {}", field, name),
+                )
+            }
+        }
+    }
+
+    Object::Struct {
+        name,
+        fields: instance,
+        frozen: false,
+    }
+}
+
+/// Adds the methods of an impl block to a struct or enum, which is bound anew
+/// with its extended set of methods.
+fn eval_impl_statement(
+    name: Node,
+    methods: Vec<(Node, Node)>,
+    environment: &mut Environment,
+) -> Object {
+    let mut declared = eval(name, environment);
+    let (name, table) = match &mut declared {
+        Object::StructType { name, methods, .. } | Object::EnumType { name, methods, .. } => {
+            (name.clone(), methods)
+        }
+        other if is_unwinding(other) => return declared,
+        other => {
+            return Object::error(
+                ErrorKind::TypeMismatch,
+                format!("{} is not a struct or enum", other.name()),
+            )
+        }
+    };
+
+    for (method, function) in methods {
+        let function = match eval(function, environment) {
+            Object::Function {
+                parameters,
+                body,
+                env,
+                ..
+            } => Object::Function {
+                name: Some(format!("{}.{}", name, method.as_string())),
+                parameters,
+                body,
+                env,
+            },
+            other => return other,
+        };
+
+        table.insert(method.as_string(), function);
+    }
+
+    match environment.set(name, declared) {
+        Ok(_) => NULL,
+        Err(e) => e.into(),
+    }
+}
+
+/// Finds the function called by `object.name(...)`, along with the value to
+/// pass as its first argument, if any. Functions held by fields, hash keys and
+/// module exports come first, then the methods of structs and enums, which
+/// take the instance as `self`, and then the methods of built-in types.
+fn resolve_method(
+    object: Object,
+    name: &str,
+    environment: &Environment,
+) -> Result<(Object, Option<Object>), Object> {
+    let member = match &object {
+        Object::Struct { fields, .. } | Object::Variant { fields, .. } => {
+            fields.iter().find(|(f, _)| f == name).map(|(_, v)| v)
+        }
+        Object::Hash { pairs, .. } => pairs.get(&HashKey::String {
+            value: name.to_string(),
+        }),
+        Object::Module { exports, .. } => exports.get(name),
+        _ => None,
+    };
+
+    if let Some(member) = member {
+        return Ok((member.clone(), None));
+    }
+
+    if let Object::StructType { .. } | Object::EnumType { .. } | Object::Module { .. } = object {
+        let method = eval_member_expression(object, name.to_string());
+        if is_unwinding(&method) {
+            return Err(method);
+        }
+
+        return Ok((method, None));
+    }
+
+    if let Object::Struct {
+        name: type_name, ..
+    }
+    | Object::Variant {
+        enum_name: type_name,
+        ..
+    } = &object
+    {
+        if let Some(method) = environment
+            .get(type_name)
+            .and_then(|t| instance_method(t, type_name, name))
+        {
+            return Ok((method, Some(object)));
+        }
+    }
+
+    match builtins::method(&object, name) {
+        Some(builtin) => Ok((builtin, Some(object))),
+        None => Err(Object::error(
+            ErrorKind::UnknownField,
+            format!("no method {} on {}", name, object.name()),
+        )),
+    }
+}
+
+/// Returns the method of a struct or enum type with the given name, if it
+/// takes the instance as `self`. The type is looked up by the name of the
+/// instance's struct or enum, so it has to be the one the instance was
+/// created from.
+fn instance_method(declared: Object, type_name: &str, method: &str) -> Option<Object> {
+    let method = match &declared {
+        Object::StructType { name, methods, .. } | Object::EnumType { name, methods, .. }
+            if name == type_name =>
+        {
+            methods.get(method)?.clone()
+        }
+        _ => return None,
+    };
+
+    match &method {
+        Object::Function { parameters, .. }
+            if parameters.first().is_some_and(|p| p.as_string() == "self") =>
+        {
+            Some(with_own_type(method, declared))
+        }
+        _ => None,
+    }
+}
+
+/// Makes a struct or enum visible to one of its methods under its own name,
+/// with all of its methods. Functions capture the environment they were
+/// created in, which holds the type as it was before the impl block.
+fn with_own_type(method: Object, declared: Object) -> Object {
+    match (method, &declared) {
+        (
+            Object::Function {
+                name,
+                parameters,
+                body,
+                env,
+            },
+            Object::StructType {
+                name: type_name, ..
+            }
+            | Object::EnumType {
+                name: type_name, ..
+            },
+        ) => {
+            let mut env = Environment::new_enclosed(*env);
+            let type_name = type_name.clone();
+            env.set(type_name, declared).ok();
+            Object::Function {
+                name,
+                parameters,
+                body,
+                env: Box::new(env),
+            }
+        }
+        (method, _) => method,
+    }
+}
+
+/// Reads a field of a struct or enum variant, the value of a string key of a
+/// hash, a method of a struct or enum type, or an export of a module. Reading
+/// a variant of an enum type gives its constructor, or the variant itself if
+/// it has no fields.
+fn eval_member_expression(object: Object, property: String) -> Object {
+    if let Object::EnumType { name, variants, .. } = &object {
+        match variants.iter().find(|(v, _)| *v == property) {
+            Some((variant, Some(fields))) => {
+                return Object::Constructor {
+                    enum_name: name.clone(),
+                    variant: variant.clone(),
+                    fields: fields.clone(),
+                }
+            }
+            Some((variant, None)) => {
+                return Object::Variant {
+                    enum_name: name.clone(),
+                    variant: variant.clone(),
+                    fields: vec![],
+                }
+            }
+            None => {}
+        }
+    }
+
+    match object {
+        Object::StructType {
+            ref name,
+            ref methods,
+            ..
+        }
+        | Object::EnumType {
+            ref name,
+            ref methods,
+            ..
+        } => match methods.get(&property) {
+            Some(method) => with_own_type(method.clone(), object.clone()),
+            None => Object::error(
+                ErrorKind::UnknownField,
+                format!("no method {} on {}", property, name),
+            ),
+        },
+        Object::Struct { ref fields, .. } | Object::Variant { ref fields, .. } => {
+            match fields.iter().find(|(f, _)| *f == property) {
+                Some((_, value)) => value.clone(),
+                None => Object::error(
+                    ErrorKind::UnknownField,
+                    format!("no field {} on {}", property, object.name()),
+                ),
+            }
+        }
+        Object::Hash { pairs, .. } => pairs
+            .get(&HashKey::String { value: property })
+            .cloned()
+            .unwrap_or(NULL),
+        Object::Module { path, exports } => match exports.get(&property) {
+            Some(value) => value.clone(),
+            None => Object::error(
+                ErrorKind::UnknownField,
+                format!("no export {} in module {}", property, path),
+            ),
+        },
+        other => Object::error(
+            ErrorKind::UnknownField,
+            format!("no field {} on {}", property, other.name()),
+        ),
+    }
+}
+
+fn eval_try_expression(
+    body: Node,
+    parameter: Option<Box<Node>>,
+    handler: Option<Box<Node>>,
+    finalizer: Option<Box<Node>>,
+    environment: &mut Environment,
+) -> Object {
+    let mut result = eval(body, environment);
+
+    if let (Object::Error { value }, Some(parameter), Some(handler)) = (&result, parameter, handler)
+    {
+        if value.kind.is_catchable() {
+            // The parameter is only bound while the handler runs, like the
+            // variables of a for loop.
+            let name = parameter.as_string();
+            let shadowed = environment.storage.get(&name).cloned();
+
+            result = match environment.set(name.clone(), value.to_object()) {
+                Ok(_) => {
+                    let handled = eval(*handler, environment);
+                    match shadowed {
+                        Some(s) => environment.set(name, s).ok().flatten(),
+                        None => environment.remove(&name),
+                    };
+                    handled
+                }
+                Err(e) => e.into(),
+            };
+        }
+    }
+
+    // The finally block always runs. It only replaces the result of the try
+    // expression if it raises an error or returns on its own.
+    if let Some(finalizer) = finalizer {
+        let finalized = eval(*finalizer, environment);
+        if matches!(finalized, Object::Error { .. } | Object::ReturnValue { .. }) {
+            return finalized;
+        }
+    }
+
+    result
+}
+
+/// Evaluates the first arm whose pattern matches the subject and whose guard,
+/// if any, holds. The variables bound by the pattern are only visible to the
+/// guard and body of the arm.
+fn eval_match_expression(
+    subject: Object,
+    arms: Vec<MatchArm>,
+    span: Span,
+    environment: &mut Environment,
+) -> Object {
+    for arm in arms {
+        let mut bindings = vec![];
+        if !pattern::matches(&arm.pattern, &subject, &mut bindings) {
+            continue;
+        }
+
+        // The arm runs in a scope of its own, which is closed again once it is
+        // done. Assignments to outer variables reach the outer scope, while
+        // the bindings made in the arm go away with it.
+        let mut arm_env = Environment::new_enclosed(mem::take(environment));
+        let result = eval_match_arm(arm, bindings, &mut arm_env);
+        *environment = arm_env.close();
+
+        if let Some(result) = result {
+            return result;
+        }
+    }
+
+    with_span(
+        Object::error(
+            ErrorKind::NoMatch,
+            format!("no match arm for {}", subject.inspect()),
+        ),
+        span,
+    )
+}
+
+/// Evaluates the guard and body of a match arm whose pattern matched. Returns
+/// None if the guard doesn't hold.
+fn eval_match_arm(
+    arm: MatchArm,
+    bindings: Vec<(String, Object)>,
+    environment: &mut Environment,
+) -> Option<Object> {
+    for (name, value) in bindings {
+        if let Err(e) = environment.set(name, value) {
+            return Some(e.into());
+        }
+    }
+
+    if let Some(guard) = arm.guard {
+        let guard = eval(*guard, environment);
+        if is_unwinding(&guard) {
+            return Some(guard);
+        }
+
+        if !is_truthy(guard) {
+            return None;
+        }
+    }
+
+    Some(eval(*arm.body, environment))
+}
+
+fn eval_bang_operator_expression(right: Object) -> Object {
+    match right {
+        TRUE => FALSE,
+        FALSE => TRUE,
+        NULL => TRUE,
+        _ => FALSE,
+    }
+}
+
+fn eval_minus_operator_expression(right: Object) -> Object {
+    match right {
+        Object::Integer { value } => match value.checked_neg() {
+            Some(value) => Object::Integer { value },
+            None => Object::BigInt {
+                value: BigInt::from(value).neg(),
+            },
+        },
+        Object::Float { value } => Object::Float { value: -value },
+        Object::BigInt { value } => Object::BigInt { value: value.neg() },
+        _ => Object::error(
+            ErrorKind::UnknownOperator,
+            format!("unknown operator: -{}", right.name()),
+        ),
+    }
+}
+
+fn eval_integer_infix_expression(operator: String, left: i64, right: i64) -> Object {
+    // Results which don't fit in an integer are promoted to big integers.
+    let integer = |value: Option<i64>| match value {
+        Some(value) => Object::Integer { value },
+        None => {
+            eval_bigint_infix_expression(operator.clone(), BigInt::from(left), BigInt::from(right))
+        }
+    };
+
+    match operator.as_str() {
+        "+" => integer(left.checked_add(right)),
+        "-" => integer(left.checked_sub(right)),
+        "*" => integer(left.checked_mul(right)),
+        "/" if right == 0 => {
+            Object::error(ErrorKind::DivisionByZero, "division by zero".to_string())
+        }
+        "/" => integer(left.checked_div(right)),
+        "<" => Object::Boolean {
+            value: left < right,
+        },
+        ">" => Object::Boolean {
+            value: left > right,
+        },
+        "==" => Object::Boolean {
+            value: left == right,
+        },
+        "!=" => Object::Boolean {
+            value: left != right,
+        },
+        ".." | "..=" => Object::Range {
+            start: left,
+            end: right,
+            inclusive: operator == "..=",
+        },
+        _ => Object::error(
+            ErrorKind::UnknownOperator,
+            format!("unknown operator: INTEGER {} INTEGER", operator),
+        ),
+    }
+}
+
+fn eval_bigint_infix_expression(operator: String, left: BigInt, right: BigInt) -> Object {
+    match operator.as_str() {
+        "+" => Object::BigInt {
+            value: left.add(&right),
+        },
+        "-" => Object::BigInt {
+            value: left.sub(&right),
+        },
+        "*" => Object::BigInt {
+            value: left.mul(&right),
+        },
+        "/" => match left.div_rem(&right) {
+            Some((value, _)) => Object::BigInt { value },
+            None => Object::error(ErrorKind::DivisionByZero, "division by zero".to_string()),
+        },
+        "<" => Object::Boolean {
+            value: left < right,
+        },
+        ">" => Object::Boolean {
+            value: left > right,
+        },
+        "==" => Object::Boolean {
+            value: left == right,
+        },
+        "!=" => Object::Boolean {
+            value: left != right,
+        },
+        _ => Object::error(
+            ErrorKind::UnknownOperator,
+            format!("unknown operator: BIGINT {} BIGINT", operator),
+        ),
+    }
+}
+
+fn eval_float_infix_expression(operator: String, left: f64, right: f64) -> Object {
+    match operator.as_str() {
+        "+" => Object::Float {
+            value: left + right,
+        },
+        "-" => Object::Float {
+            value: left - right,
+        },
+        "*" => Object::Float {
+            value: left * right,
+        },
+        "/" => Object::Float {
+            value: left / right,
+        },
+        "<" => Object::Boolean {
+            value: left < right,
+        },
+        ">" => Object::Boolean {
+            value: left > right,
+        },
+        "==" => Object::Boolean {
+            value: left == right,
+        },
+        "!=" => Object::Boolean {
+            value: left != right,
+        },
+        _ => Object::error(
+            ErrorKind::UnknownOperator,
+            format!("unknown operator: FLOAT {} FLOAT", operator),
+        ),
+    }
+}
+
+fn eval_identifier(name: String, environment: &mut Environment) -> Object {
+    match environment.get(&name).or_else(|| builtins::lookup(&name)) {
+        Some(v) => v,
+        None => Object::error(
+            ErrorKind::UnknownIdentifier,
+            format!("identifier not found: {}", name),
+        ),
+    }
+}
+
+fn eval_hash_literal(entries: Vec<Node>, environment: &mut Environment) -> Object {
+    let mut evaluated = BTreeMap::new();
+
+    for entry in entries {
+        let (k, v) = match entry {
+            Node::HashPair { key, value } => (*key, *value),
+            Node::SpreadExpression { value, span } => {
+                match eval(*value, environment) {
+                    Object::Hash { pairs, .. } => evaluated.extend(pairs),
+                    other if is_unwinding(&other) => return other,
+                    other => {
+                        return with_span(
+                            Object::error(
+                                ErrorKind::TypeMismatch,
+                                format!("cannot spread {} into a hash", other.name()),
+                            ),
+                            span,
+                        )
+                    }
+                }
+
+                continue;
+            }
+            _ => continue,
+        };
+
+        let key = eval(k, environment);
+        if is_unwinding(&key) {
+            return key;
+        }
+
+        let hash_key = match key.hash_key() {
+            Some(hash_key) => hash_key,
+            None => {
+                return Object::error(
+                    ErrorKind::UnusableHashKey,
+                    format!("unusable as hash key: {}", key.name()),
+                )
+            }
+        };
+
+        let value = eval(v, environment);
+        if is_unwinding(&value) {
+            return value;
+        }
+
+        evaluated.insert(hash_key, value);
+    }
+
+    Object::Hash {
+        pairs: evaluated,
+        frozen: false,
+    }
+}
+
+fn eval_index_expression(left: Object, index: Object) -> Object {
+    match &left {
+        Object::Array { elements, .. } => match index {
+            Object::Integer { value } if value >= 0 => {
+                elements.get(value as usize).cloned().unwrap_or(NULL)
+            }
+            Object::Integer { .. } => NULL,
+            _ => Object::error(
+                ErrorKind::TypeMismatch,
+                format!("array index must be INTEGER, got {}", index.name()),
+            ),
+        },
+        Object::Hash { pairs, .. } => match index.hash_key() {
+            Some(key) => pairs.get(&key).cloned().unwrap_or(NULL),
+            None => Object::error(
+                ErrorKind::UnusableHashKey,
+                format!("unusable as hash key: {}", index.name()),
+            ),
+        },
+        _ => Object::error(
+            ErrorKind::UnknownOperator,
+            format!("index operator not supported: {}", left.name()),
+        ),
+    }
+}
+
+/// Evaluates a list of expressions, expanding spread expressions into the
+/// items of the value they spread. If an expression raises an error, the
+/// result only holds that error. The memory held by the items is reserved as
+/// the list grows.
+fn eval_expressions(expressions: Vec<Node>, env: &mut Environment) -> Vec<Object> {
+    let mut result = vec![];
+    let mut bytes = 0;
+
+    for e in expressions {
+        let (spread, span) = match e {
+            Node::SpreadExpression { value, span } => (eval(*value, env), span),
+            other => {
+                let evaluated = eval(other, env);
+                if is_unwinding(&evaluated) {
+                    return vec![evaluated];
+                }
+
+                bytes += evaluated.footprint().bytes;
+                if let Err(e) = reserve_memory(bytes) {
+                    return vec![e.into()];
+                }
+
+                result.push(evaluated);
+                continue;
+            }
+        };
+
+        if is_unwinding(&spread) {
+            return vec![spread];
+        }
+
+        let items = match Sequence::from_object(&spread) {
+            Some(items) => items,
+            None => {
+                return vec![with_span(
+                    Object::error(
+                        ErrorKind::NotIterable,
+                        format!("cannot spread {}", spread.name()),
+                    ),
+                    span,
+                )]
+            }
+        };
+
+        for item in items {
+            if is_unwinding(&item) {
+                return vec![item];
+            }
+
+            bytes += item.footprint().bytes;
+            if let Err(e) = reserve_memory(bytes) {
+                return vec![with_span(e.into(), span)];
+            }
+
+            result.push(item);
+        }
+    }
+
+    result
+}
+
+fn apply_function(function: Object, args: Vec<Object>) -> Object {
+    apply_function_with_named(function, args, vec![])
+}
+
+/// Calls a function with positional and named arguments. Builtins only take
+/// positional arguments, while the constructors of enum variants take their
+/// fields like parameters.
+fn apply_function_with_named(
+    function: Object,
+    args: Vec<Object>,
+    named: Vec<(String, Object)>,
+) -> Object {
+    match function {
+        Object::Function {
+            name,
+            parameters,
+            body,
+            env,
+            ..
+        } => {
+            let mut extended_env = Environment::new_enclosed(*env);
+            if let Some(e) = bind_parameters(&parameters, args, named, &mut extended_env) {
+                return e;
+            }
+
+            if let Some(e) = check_memory_limit() {
+                return e;
+            }
+
+            // Calling a generator function doesn't run its body, but returns
+            // an iterator which runs it up to the next yield on every step.
+            if generator::contains_yield(&body) {
+                return iterator::new_iterator(Sequence::Generator {
+                    generator: Box::new(Generator::new(name, body, extended_env)),
+                });
+            }
+
+            let evaluated = eval(body, &mut extended_env);
+            unwrap_return_value(evaluated)
+        }
+        Object::Constructor {
+            enum_name,
+            variant,
+            fields,
+        } => {
+            let parameters: Vec<Node> = fields
+                .iter()
+                .map(|f| Node::Identifier {
+                    value: Token::new(TokenType::Ident, f.clone()),
+                })
+                .collect();
+
+            let mut env = Environment::new();
+            if let Some(e) = bind_parameters(&parameters, args, named, &mut env) {
+                return e;
+            }
+
+            Object::Variant {
+                enum_name,
+                variant,
+                fields: fields
+                    .into_iter()
+                    .map(|f| {
+                        let value = env.get(&f).unwrap_or(NULL);
+                        (f, value)
+                    })
+                    .collect(),
+            }
+        }
+        Object::Builtin { name } if named.is_empty() => builtins::call(&name, args),
+        Object::Builtin { name } => Object::error(
+            ErrorKind::InvalidArgument,
+            format!("builtin {} doesn't take named arguments", name),
+        ),
+        _ => Object::error(
+            ErrorKind::NotCallable,
+            format!("not a function: {}", function.name()),
+        ),
+    }
+}
+
+/// Binds the arguments of a call to the parameters of a function. Named
+/// arguments can be given for any parameter bound to a plain name, parameters
+/// left without an argument take their default value, and a rest parameter
+/// collects the remaining positional arguments into an array.
+fn bind_parameters(
+    parameters: &[Node],
+    args: Vec<Object>,
+    mut named: Vec<(String, Object)>,
+    environment: &mut Environment,
+) -> Option<Object> {
+    let (rest, parameters) = match parameters.split_last() {
+        Some((Node::RestParameter { name }, init)) => (Some(name), init),
+        _ => (None, parameters),
+    };
+
+    let required = parameters
+        .iter()
+        .filter(|p| !matches!(p, Node::DefaultParameter { .. }))
+        .count();
+
+    let got = args.len() + named.len();
+    if got < required || (rest.is_none() && got > parameters.len()) {
+        let want = match rest {
+            Some(_) => format!(">={}", required),
+            None if required == parameters.len() => format!("={}", required),
+            None => format!("={}..={}", required, parameters.len()),
+        };
+
+        return Some(Object::error(
+            ErrorKind::WrongArgumentCount,
+            format!("wrong number of arguments: want{}, got={}", want, got),
+        ));
+    }
+
+    let mut args = args.into_iter();
+    for parameter in parameters {
+        let (target, default) = match parameter {
+            Node::DefaultParameter { target, default } => (target.as_ref(), Some(default)),
+            other => (other, None),
+        };
+
+        let by_name = match target {
+            Node::Identifier { value } => named
+                .iter()
+                .position(|(name, _)| *name == value.v)
+                .map(|i| named.remove(i).1),
+            _ => None,
+        };
+
+        let value = match (args.next(), by_name) {
+            (Some(_), Some(_)) => {
+                return Some(Object::error(
+                    ErrorKind::InvalidArgument,
+                    format!("multiple values for parameter {}", target.as_string()),
+                ))
+            }
+            (Some(value), None) | (None, Some(value)) => value,
+            (None, None) => match default {
+                Some(default) => {
+                    let value = eval(*default.clone(), environment);
+                    if is_unwinding(&value) {
+                        return Some(value);
+                    }
+
+                    value
+                }
+                None => {
+                    return Some(Object::error(
+                        ErrorKind::WrongArgumentCount,
+                        format!("missing argument for parameter {}", target.as_string()),
+                    ))
+                }
+            },
+        };
+
+        if let Some(e) = bind(target, value, false, environment) {
+            return Some(e);
+        }
+    }
+
+    if let Some((name, _)) = named.first() {
+        return Some(Object::error(
+            ErrorKind::InvalidArgument,
+            format!("unknown parameter: {}", name),
+        ));
+    }
+
+    if let Some(rest) = rest {
+        let elements = args.collect();
+        if let Err(e) = environment.set(
+            rest.as_string(),
+            Object::Array {
+                elements,
+                frozen: false,
+            },
+        ) {
+            return Some(e.into());
+        }
+    }
+
+    None
+}
+
+/// Binds a value to the target of a let or const statement or function
+/// parameter, which is either a name or a destructuring pattern. Returns an
+/// error if the value doesn't fit the pattern, or a constant is in the way.
+fn bind(
+    target: &Node,
+    value: Object,
+    constant: bool,
+    environment: &mut Environment,
+) -> Option<Object> {
+    let declare = |environment: &mut Environment, name: String, value: Object| {
+        let declared = if constant {
+            environment.set_constant(name, value)
+        } else {
+            environment.set(name, value).map(drop)
+        };
+
+        declared.err().map(Object::from)
+    };
+
+    let (pattern, span) = match target {
+        Node::DestructuringPattern { pattern, span } => (pattern, *span),
+        Node::Identifier { value: token } => {
+            return declare(environment, token.v.clone(), value).map(|e| with_span(e, token.span));
+        }
+        _ => return declare(environment, target.as_string(), value),
+    };
+
+    let mut bindings = vec![];
+    if let Err(reason) = pattern::destructure(pattern, &value, &mut bindings) {
+        return Some(with_span(
+            Object::error(
+                ErrorKind::PatternMismatch,
+                format!("cannot destructure {}: {}", value.inspect(), reason),
+            ),
+            span,
+        ));
+    }
+
+    bindings
+        .into_iter()
+        .find_map(|(name, value)| declare(environment, name, value))
+        .map(|e| with_span(e, span))
+}
+
+fn check_memory_limit() -> Option<Object> {
+    reserve_memory(0).err().map(Object::from)
+}
+
+/// Returns an error if holding on to `bytes` more would exceed the memory
+/// limit, so that values which may grow large are refused before they are
+/// built rather than after.
+pub(crate) fn reserve_memory(bytes: usize) -> Result<(), RuntimeError> {
+    if !memory::would_exceed(bytes) {
+        return Ok(());
+    }
+
+    Err(RuntimeError::new(
+        ErrorKind::MemoryLimitExceeded,
+        format!(
+            "memory limit exceeded: {} bytes in use, limit is {} bytes",
+            memory::usage().current.bytes.saturating_add(bytes),
+            memory::limit().unwrap_or_default()
+        ),
+    ))
+}
+
+/// Attaches a source location to an error which doesn't have one yet. Errors
+/// keep the location where they were first raised.
+fn with_span(object: Object, span: Span) -> Object {
+    match object {
+        Object::Error { mut value } => {
+            if value.span.is_none() {
+                value.span = Some(span);
+            }
+
+            Object::Error { value }
+        }
+        _ => object,
+    }
+}
+
+fn unwrap_return_value(evaluated: Object) -> Object {
+    match evaluated {
+        Object::ReturnValue { value } => *value,
+        _ => evaluated,
+    }
+}
+
+fn is_truthy(condition: Object) -> bool {
+    match condition {
+        NULL => false,
+        TRUE => true,
+        FALSE => false,
+        _ => true,
+    }
+}
+
+/// Returns true if an Object interrupts the evaluation of the surrounding
+/// code: errors, and values being returned from a function.
+fn is_unwinding(object: &Object) -> bool {
+    matches!(object, Object::Error { .. } | Object::ReturnValue { .. })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_eval_integer_expression() {
+        let table = [
+            ("5;".to_string(), 5),
+            ("10;".to_string(), 10),
+            ("-5;".to_string(), -5),
             ("-10;".to_string(), -10),
             ("5 + 5 + 5 + 5 - 10;".to_string(), 10),
             ("2 * 2 * 2 * 2 * 2;".to_string(), 32),
@@ -362,158 +1891,1438 @@ mod tests {
 
         table.iter().for_each(|(input, output)| {
             let object = test_eval(input.to_string());
-            match object {
-                Object::Integer { value } => assert_eq!(value, *output),
-                _ => panic!("Unexpected object"),
-            }
+            match object {
+                Object::Integer { value } => assert_eq!(value, *output),
+                _ => panic!("Unexpected object"),
+            }
+        });
+    }
+
+    #[test]
+    fn test_eval_float_expression() {
+        let table = [
+            ("2.5;", "2.5"),
+            ("-0.5 * 3.0;", "-1.5"),
+            ("1 + 0.5;", "1.5"),
+            ("3 / 2.0;", "1.5"),
+            ("1.0 / 0;", "inf"),
+            (
+                "[1.5 < 2, 2 > 2.5, 1 == 1.0, 0.1 + 0.2 != 0.3];",
+                "[true, false, true, true]",
+            ),
+            ("\"${0.25}\";", "0.25"),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            assert_eq!(test_eval(input.to_string()).inspect(), *output, "{}", input);
+        });
+
+        match test_eval("1.5..3;".to_string()) {
+            Object::Error { value } => {
+                assert_eq!(value.message, "unknown operator: FLOAT .. FLOAT")
+            }
+            object => panic!("Unexpected object, {:?}", object),
+        }
+    }
+
+    #[test]
+    fn test_eval_bigint_expression() {
+        let table = [
+            (
+                "bigint(\"9223372036854775807\") + 1;",
+                "9223372036854775808",
+            ),
+            ("-bigint(5) * 3;", "-15"),
+            ("9223372036854775807 + 1;", "9223372036854775808"),
+            ("-9223372036854775807 - 2;", "-9223372036854775809"),
+            ("4611686018427387904 * 2;", "9223372036854775808"),
+            ("(-9223372036854775807 - 1) / -1;", "9223372036854775808"),
+            ("-(-9223372036854775807 - 1);", "9223372036854775808"),
+            (
+                "9223372036854775807 + 1 - 1 == 9223372036854775807;",
+                "true",
+            ),
+            ("bigint(\"-10\") / 3;", "-3"),
+            ("bigint(1) + 0.5;", "1.5"),
+            (
+                "[bigint(2) * 3 == 6, 1 < bigint(2), bigint(1) != 1];",
+                "[true, true, false]",
+            ),
+            ("\"${bigint(\"123\")}\";", "123"),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            assert_eq!(test_eval(input.to_string()).inspect(), *output, "{}", input);
+        });
+
+        let errors = [
+            ("bigint(1) / 0;", "division by zero"),
+            ("bigint(\"1.5\");", "cannot convert \"1.5\" to BIGINT"),
+            ("bigint(1) .. 2;", "unknown operator: BIGINT .. BIGINT"),
+        ];
+
+        errors
+            .iter()
+            .for_each(|(input, message)| match test_eval(input.to_string()) {
+                Object::Error { value } => assert_eq!(value.message, *message, "{}", input),
+                object => panic!("Unexpected object, {:?}", object),
+            });
+    }
+
+    #[test]
+    fn test_eval_boolean_expression() {
+        let table = [
+            ("true;".to_string(), true),
+            ("false;".to_string(), false),
+            ("1 < 2;".to_string(), true),
+            ("1 > 2;".to_string(), false),
+            ("1 < 1;".to_string(), false),
+            ("1 > 1;".to_string(), false),
+            ("1 == 1;".to_string(), true),
+            ("1 != 1;".to_string(), false),
+            ("1 == 2;".to_string(), false),
+            ("1 != 2;".to_string(), true),
+            ("true == true;".to_string(), true),
+            ("false == false;".to_string(), true),
+            ("true == false;".to_string(), false),
+            ("true != false;".to_string(), true),
+            ("false != true;".to_string(), true),
+            ("(1 < 2) == true;".to_string(), true),
+            ("(1 < 2) == false;".to_string(), false),
+            ("(1 > 2) == true;".to_string(), false),
+            ("(1 > 2) == false;".to_string(), true),
+            ("\"a\" < \"b\";".to_string(), true),
+            ("\"ab\" > \"b\";".to_string(), false),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            match object {
+                Object::Boolean { value } => assert_eq!(value, *output),
+                _ => panic!("Unexpected object, {:?}", object),
+            }
+        });
+    }
+
+    #[test]
+    fn test_bang_operator() {
+        let table = [
+            ("!true;".to_string(), false),
+            ("!false;".to_string(), true),
+            ("!5;".to_string(), false),
+            ("!!true;".to_string(), true),
+            ("!!false;".to_string(), false),
+            ("!!5;".to_string(), true),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            match object {
+                Object::Boolean { value } => assert_eq!(value, *output),
+                _ => panic!("Unexpected object"),
+            }
+        });
+    }
+
+    #[test]
+    fn test_if_else_expressions() {
+        let table = [
+            (
+                "if (true) { 10 };".to_string(),
+                Object::Integer { value: 10 },
+            ),
+            ("if (false) { 10 };".to_string(), Object::Null),
+            ("if (1) { 10 };".to_string(), Object::Integer { value: 10 }),
+            (
+                "if (1 < 2) { 10 };".to_string(),
+                Object::Integer { value: 10 },
+            ),
+            ("if (1 > 2) { 10 };".to_string(), Object::Null),
+            (
+                "if (1 > 2) { 10 } else { 20 };".to_string(),
+                Object::Integer { value: 20 },
+            ),
+            (
+                "if (1 < 2) { 10 } else { 20 };".to_string(),
+                Object::Integer { value: 10 },
+            ),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            assert_eq!(object, *output);
+        });
+    }
+
+    #[test]
+    fn test_return_statements() {
+        let table = [
+            ("return 10;".to_string(), 10),
+            ("return 10; 9;".to_string(), 10),
+            ("return 2 * 5; 9;".to_string(), 10),
+            ("9; return 2 * 5; 9;".to_string(), 10),
+            (
+                "if (10 > 1) { if (10 > 1) { return 10; } return 1; };".to_string(),
+                10,
+            ),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            match object {
+                Object::Integer { value } => assert_eq!(value, *output),
+                _ => panic!("Unexpected object"),
+            }
+        });
+    }
+
+    #[test]
+    fn test_error_handling() {
+        let table = [
+            ("5 + true;".to_string(), "type mismatch: INTEGER + BOOLEAN"),
+            (
+                "5 + true; 5;".to_string(),
+                "type mismatch: INTEGER + BOOLEAN",
+            ),
+            ("-true;".to_string(), "unknown operator: -BOOLEAN"),
+            (
+                "true + false;".to_string(),
+                "unknown operator: BOOLEAN + BOOLEAN",
+            ),
+            (
+                "5; true + false; 5;".to_string(),
+                "unknown operator: BOOLEAN + BOOLEAN",
+            ),
+            (
+                "if (10 > 1) { true + false; };".to_string(),
+                "unknown operator: BOOLEAN + BOOLEAN",
+            ),
+            (
+                "if (10 > 1) { if (10 > 1) { return true + false; } return 1; };".to_string(),
+                "unknown operator: BOOLEAN + BOOLEAN",
+            ),
+            ("foobar;".to_string(), "identifier not found: foobar"),
+            ("5 / 0;".to_string(), "division by zero"),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            match object {
+                Object::Error { value } => assert_eq!(value.message, *output),
+                _ => panic!("Unexpected object"),
+            }
+        });
+
+        let caught = test_eval("try { 5 / 0; } catch (e) { e[\"kind\"]; };".to_string());
+        assert_eq!(caught.inspect(), "division_by_zero");
+    }
+
+    #[test]
+    fn test_error_traceback() {
+        let input = "let inner = fn(x) { x + true; };
+let outer = fn(x) {
+    inner(x);
+};
+outer(5);";
+
+        let object = test_eval(input.to_string());
+        match object {
+            Object::Error { value } => {
+                assert_eq!(value.kind, ErrorKind::TypeMismatch);
+                assert_eq!(value.frames.len(), 2);
+                assert_eq!(value.frames[0].function, Some("inner".to_string()));
+                assert_eq!(value.frames[1].function, Some("outer".to_string()));
+                assert_eq!(
+                    value.traceback(),
+                    "Traceback (most recent call last):
+  line 5, column 6, in <script>
+  line 3, column 10, in outer
+  line 1, column 23, in inner
+ERROR: type mismatch: INTEGER + BOOLEAN"
+                );
+            }
+            _ => panic!("Unexpected object, {:?}", object),
+        }
+    }
+
+    #[test]
+    fn test_generator_traceback() {
+        let table = [
+            (
+                "let gen = fn() {\n  yield 1;\n  yield 1 + true;\n};\nlet g = gen();\nnext(g);\nnext(g);",
+                "  line 7, column 5, in <script>\n  line 3, column 11, in gen",
+            ),
+            (
+                "let gen = fn() {\n  yield 1 + true;\n};\nfor (v in gen()) { v; }",
+                "  line 2, column 11, in gen",
+            ),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            match object {
+                Object::Error { value } => assert_eq!(
+                    value.traceback(),
+                    format!(
+                        "Traceback (most recent call last):\n{}\nERROR: type mismatch: INTEGER + BOOLEAN",
+                        output
+                    )
+                ),
+                _ => panic!("Unexpected object, {:?}", object),
+            }
+        });
+    }
+
+    #[test]
+    fn test_string_expressions() {
+        let table = [
+            ("\"Hello World!\";".to_string(), "Hello World!"),
+            (
+                "\"Hello\" + \" \" + \"World!\";".to_string(),
+                "Hello World!",
+            ),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            match object {
+                Object::String { value } => assert_eq!(value, *output),
+                _ => panic!("Unexpected object, {:?}", object),
+            }
+        });
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        let table = [
+            (
+                "let name = \"Ann\"; let count = 2; \"hello ${name}, you have ${count + 1} messages\";",
+                "hello Ann, you have 3 messages",
+            ),
+            ("\"${[1, \"a\"]} ${null} ${1 < 2}\";", "[1, a] null true"),
+            ("let h = {\"k\": 1}; \"${ {\"k\": 2}[\"k\"] + h[\"k\"] }\";", "3"),
+            ("let x = \"in\"; \"out ${\"${x}side\"}\";", "out inside"),
+            ("\"\\${x} costs $5 {}\";", "${x} costs $5 {}"),
+            ("\"${1}${2}\".len();", "2"),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            assert_eq!(object.inspect(), *output, "{}", input);
+        });
+
+        match test_eval("\"a ${b}\";".to_string()) {
+            Object::Error { value } => assert_eq!(value.message, "identifier not found: b"),
+            object => panic!("Unexpected object, {:?}", object),
+        }
+    }
+
+    #[test]
+    fn test_string_library() {
+        let table = [
+            ("split(\"a,b,,c\", \",\");", "[a, b, , c]"),
+            ("\"héllo\".split(\"\");", "[h, é, l, l, o]"),
+            ("join([\"a\", \"b\"], \"-\");", "a-b"),
+            (
+                "\"a b\".split(\" \").map(|w| w.upper()).join(\"_\");",
+                "A_B",
+            ),
+            ("trim(\"  x \\n\");", "x"),
+            (
+                "[starts_with(\"abc\", \"ab\"), \"abc\".ends_with(\"b\")];",
+                "[true, false]",
+            ),
+            (
+                "[contains(\"abc\", \"bc\"), contains([1, [2]], [2]), contains(0..3, 5)];",
+                "[true, true, false]",
+            ),
+            ("replace(\"a-b-c\", \"-\", \"+\");", "a+b+c"),
+            (
+                "[find(\"héllo\", \"l\"), find(\"abc\", \"z\")];",
+                "[2, null]",
+            ),
+            ("find([1, 4, 6], |x| x > 3);", "4"),
+            ("substring(\"héllo\", 1, 3);", "él"),
+            ("\"héllo\".substring(3);", "lo"),
+            ("chars(\"añ\");", "[a, ñ]"),
+            ("bytes(\"añ\");", "[97, 195, 177]"),
+            ("repeat(\"ab\", 3);", "ababab"),
+            ("format(\"{} + {} = {}\", 1, 2, [3]);", "1 + 2 = [3]"),
+            ("\"{{{}}}\".format(\"x\");", "{x}"),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            assert_eq!(object.inspect(), *output, "{}", input);
+        });
+
+        let errors = [
+            (
+                "split(1, \",\");",
+                "argument to `split` must be STRING, got INTEGER",
+            ),
+            (
+                "join([\"a\", 1], \"\");",
+                "argument to `join` must be a sequence of STRING, got INTEGER",
+            ),
+            ("trim(null);", "argument to `trim` must be STRING, got NULL"),
+            (
+                "\"a\".starts_with(1);",
+                "argument to `starts_with` must be STRING, got INTEGER",
+            ),
+            (
+                "contains(\"a\", 1);",
+                "argument to `contains` must be STRING, got INTEGER",
+            ),
+            (
+                "replace(\"a\", \"a\", 1);",
+                "argument to `replace` must be STRING, got INTEGER",
+            ),
+            (
+                "find([1], 2);",
+                "argument to `find` must be FUNCTION, got INTEGER",
+            ),
+            (
+                "substring(\"abc\", \"1\");",
+                "argument to `substring` must be INTEGER, got STRING",
+            ),
+            (
+                "substring(\"abc\", 2, 5);",
+                "substring range 2..5 out of bounds for STRING of length 3",
+            ),
+            (
+                "chars([]);",
+                "argument to `chars` must be STRING, got ARRAY",
+            ),
+            (
+                "repeat(\"a\", -1);",
+                "argument to `repeat` must be a positive INTEGER, got INTEGER",
+            ),
+            (
+                "format(\"{} {}\", 1);",
+                "format string has 2 placeholders, got 1 arguments",
+            ),
+        ];
+
+        errors
+            .iter()
+            .for_each(|(input, message)| match test_eval(input.to_string()) {
+                Object::Error { value } => assert_eq!(value.message, *message),
+                object => panic!("Unexpected object, {:?}", object),
+            });
+    }
+
+    #[test]
+    fn test_json() {
+        let table = [
+            (
+                "json_parse(\"{\\\"a\\\": [1, true, null]}\").unwrap()[\"a\"];",
+                "[1, true, null]",
+            ),
+            ("json_parse(\"[1,\\n 2 x]\").is_err();", "true"),
+            (
+                "let f = json_parse(\"[2.5e-1]\").unwrap(); [f, json_stringify(f)];",
+                "[[0.25], [0.25]]",
+            ),
+            (
+                "json_parse(\"123456789012345678901234567890\").unwrap() + 1;",
+                "123456789012345678901234567891",
+            ),
+            (
+                "json_stringify({\"b\": [1, \"x\"], \"a\": null});",
+                "{\"a\":null,\"b\":[1,\"x\"]}",
+            ),
+            ("json_stringify([1], 1);", "[\n 1\n]"),
+            (
+                "struct P { y, x } json_stringify(P { y: 1, x: {2: false} });",
+                "{\"y\":1,\"x\":{\"2\":false}}",
+            ),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            assert_eq!(test_eval(input.to_string()).inspect(), *output, "{}", input);
+        });
+
+        let errors = [
+            (
+                "json_stringify({\"f\": fn() {}});",
+                "cannot convert FUNCTION to JSON",
+            ),
+            (
+                "json_stringify(1, -1);",
+                "argument to `json_stringify` must be a non-negative INTEGER, got INTEGER",
+            ),
+        ];
+
+        errors
+            .iter()
+            .for_each(|(input, message)| match test_eval(input.to_string()) {
+                Object::Error { value } => assert_eq!(value.message, *message),
+                object => panic!("Unexpected object, {:?}", object),
+            });
+    }
+
+    #[test]
+    fn test_hash_index_expressions() {
+        let table = [
+            (
+                "{\"foo\": 5}[\"foo\"];".to_string(),
+                Object::Integer { value: 5 },
+            ),
+            ("{\"foo\": 5}[\"bar\"];".to_string(), Object::Null),
+            (
+                "let key = \"foo\"; {\"foo\": 5}[key];".to_string(),
+                Object::Integer { value: 5 },
+            ),
+            ("{}[\"foo\"];".to_string(), Object::Null),
+            ("{5: 5}[5];".to_string(), Object::Integer { value: 5 }),
+            ("{true: 5}[true];".to_string(), Object::Integer { value: 5 }),
+            (
+                "{\"one\": 10 - 9, \"two\": 1 + 1}[\"two\"];".to_string(),
+                Object::Integer { value: 2 },
+            ),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            assert_eq!(object, *output);
+        });
+
+        match test_eval("{fn(x) { x }: 5};".to_string()) {
+            Object::Error { value } => assert_eq!(value.message, "unusable as hash key: FUNCTION"),
+            object => panic!("Unexpected object, {:?}", object),
+        }
+    }
+
+    #[test]
+    fn test_try_catch() {
+        let table = [
+            (
+                "try { 1 + true; } catch (e) { e[\"kind\"]; };".to_string(),
+                Object::String {
+                    value: "type_mismatch".to_string(),
+                },
+            ),
+            (
+                "try { foo; } catch (e) { e[\"message\"]; };".to_string(),
+                Object::String {
+                    value: "identifier not found: foo".to_string(),
+                },
+            ),
+            (
+                "try { throw 5; } catch (e) { e[\"value\"] + 1; };".to_string(),
+                Object::Integer { value: 6 },
+            ),
+            (
+                "let f = fn() { throw \"boom\"; 1; }; try { f(); } catch (e) { e[\"message\"]; };"
+                    .to_string(),
+                Object::String {
+                    value: "boom".to_string(),
+                },
+            ),
+            (
+                "try { 1; } catch (e) { 2; };".to_string(),
+                Object::Integer { value: 1 },
+            ),
+            (
+                "let a = 1; try { throw 2; } catch (e) { 3; } finally { let a = 4; }; a;"
+                    .to_string(),
+                Object::Integer { value: 4 },
+            ),
+            (
+                "let f = fn() { try { return 1; } finally { return 2; }; }; f();".to_string(),
+                Object::Integer { value: 2 },
+            ),
+            (
+                "try { try { throw 1; } finally { 2; }; } catch (e) { e[\"value\"]; };".to_string(),
+                Object::Integer { value: 1 },
+            ),
+            (
+                "let e = 7; try { throw \"boom\"; } catch (e) { 1; }; e;".to_string(),
+                Object::Integer { value: 7 },
+            ),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            assert_eq!(object, *output);
+        });
+
+        match test_eval("try { 1; } finally { throw \"boom\"; };".to_string()) {
+            Object::Error { value } => {
+                assert_eq!(value.kind, ErrorKind::Thrown);
+                assert_eq!(value.message, "boom");
+            }
+            object => panic!("Unexpected object, {:?}", object),
+        }
+    }
+
+    #[test]
+    fn test_result_values() {
+        let table = [
+            ("ok(5);".to_string(), "ok(5)"),
+            ("err(\"boom\");".to_string(), "err(boom)"),
+            ("is_ok(ok(1));".to_string(), "true"),
+            ("is_err(ok(1));".to_string(), "false"),
+            ("unwrap(ok(1));".to_string(), "1"),
+            ("unwrap_or(err(1), 2);".to_string(), "2"),
+            ("parse_int(\"42\");".to_string(), "ok(42)"),
+            (
+                "parse_int(\"abc\")?;".to_string(),
+                "err({kind: invalid_argument, message: could not parse \"abc\" as an integer, value: null})",
+            ),
+            (
+                "let half = fn(x) { if (x / 2 * 2 == x) { ok(x / 2) } else { err(x) } };
+                 let quarter = fn(x) { let h = half(x)?; ok(half(h)? + 0); };
+                 quarter(8);"
+                    .to_string(),
+                "ok(2)",
+            ),
+            (
+                "let half = fn(x) { if (x / 2 * 2 == x) { ok(x / 2) } else { err(x) } };
+                 let quarter = fn(x) { let h = half(x)?; ok(half(h)? + 0); };
+                 quarter(6);"
+                    .to_string(),
+                "err(3)",
+            ),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            assert_eq!(object.inspect(), *output);
+        });
+
+        let table = [
+            ("5?;".to_string(), "unknown operator: INTEGER?"),
+            ("unwrap(err(\"boom\"));".to_string(), "boom"),
+            (
+                "is_ok(1, 2);".to_string(),
+                "wrong number of arguments: want=1, got=2",
+            ),
+            (
+                "is_ok(1);".to_string(),
+                "argument to `is_ok` must be RESULT, got INTEGER",
+            ),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            match object {
+                Object::Error { value } => assert_eq!(value.message, *output),
+                _ => panic!("Unexpected object, {:?}", object),
+            }
+        });
+    }
+
+    #[test]
+    fn test_null_handling() {
+        let table = [
+            ("null;".to_string(), Object::Null),
+            ("null == null;".to_string(), TRUE),
+            ("if (false) { 1 } == null;".to_string(), TRUE),
+            ("null ?? 5;".to_string(), Object::Integer { value: 5 }),
+            ("4 ?? 5;".to_string(), Object::Integer { value: 4 }),
+            ("false ?? 5;".to_string(), FALSE),
+            ("4 ?? missing;".to_string(), Object::Integer { value: 4 }),
+            (
+                "let config = {\"db\": {\"port\": 5432}}; config[\"db\"]?[\"port\"];".to_string(),
+                Object::Integer { value: 5432 },
+            ),
+            (
+                "let config = {}; config[\"db\"]?[\"host\"] ?? \"localhost\";".to_string(),
+                Object::String {
+                    value: "localhost".to_string(),
+                },
+            ),
+            ("null?.[missing];".to_string(), Object::Null),
+            ("null?.(missing);".to_string(), Object::Null),
+            (
+                "let f = fn(x) { x + 1 }; f?.(1);".to_string(),
+                Object::Integer { value: 2 },
+            ),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            assert_eq!(object, *output);
+        });
+
+        match test_eval("null[\"a\"];".to_string()) {
+            Object::Error { value } => {
+                assert_eq!(value.message, "index operator not supported: NULL")
+            }
+            object => panic!("Unexpected object, {:?}", object),
+        }
+    }
+
+    #[test]
+    fn test_array_literals() {
+        let table = [
+            ("[1, 2 * 2, 3 + 3];".to_string(), "[1, 4, 6]"),
+            ("[1, 2, 3][0];".to_string(), "1"),
+            ("[1, 2, 3][2];".to_string(), "3"),
+            ("let i = 0; [1][i];".to_string(), "1"),
+            ("[1, 2, 3][3];".to_string(), "null"),
+            ("[1, 2, 3][-1];".to_string(), "null"),
+            ("0..3;".to_string(), "0..3"),
+            ("1..=2 + 3;".to_string(), "1..=5"),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            assert_eq!(object.inspect(), *output);
+        });
+    }
+
+    #[test]
+    fn test_for_statements() {
+        let table = [
+            (
+                "let sum = 0; for (x in [1, 2, 3]) { let sum = sum + x; }; sum;".to_string(),
+                "6",
+            ),
+            (
+                "let sum = 0; for (x in 0..5) { let sum = sum + x; }; sum;".to_string(),
+                "10",
+            ),
+            (
+                "let sum = 0; for (x in 1..=5) { let sum = sum + x; }; sum;".to_string(),
+                "15",
+            ),
+            (
+                "let s = \"\"; for (c in \"héllo\") { let s = c + s; }; s;".to_string(),
+                "olléh",
+            ),
+            (
+                "let s = \"\"; for (k in {\"b\": 1, \"a\": 2}) { let s = s + k; }; s;".to_string(),
+                "ab",
+            ),
+            (
+                "let sum = 0; for (k, v in {\"a\": 1, \"b\": 2}) { let sum = sum + v; }; sum;".to_string(),
+                "3",
+            ),
+            (
+                "let sum = 0; for (i, x in [5, 5, 5]) { let sum = sum + i * x; }; sum;".to_string(),
+                "15",
+            ),
+            ("for (x in 5..0) { throw x; };".to_string(), "null"),
+            (
+                "let f = fn() { for (x in 0..10) { if (x > 3) { return x; } } }; f();".to_string(),
+                "4",
+            ),
+            // Loop variables are scoped to the loop.
+            ("let x = 7; for (x in 0..3) { x; }; x;".to_string(), "7"),
+            (
+                "for (x in 0..3) { x; }; try { x; } catch (e) { e[\"kind\"]; };".to_string(),
+                "unknown_identifier",
+            ),
+            // Closures created in the body capture the current iteration.
+            (
+                "let fs = {}; for (i in 0..3) { let fs = {0: fs[0], 1: fs[1], 2: fs[2], i: fn() { i }}; }; fs[1]();"
+                    .to_string(),
+                "1",
+            ),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            assert_eq!(object.inspect(), *output);
+        });
+
+        match test_eval("for (x in 5) { x; };".to_string()) {
+            Object::Error { value } => assert_eq!(value.message, "cannot iterate over INTEGER"),
+            object => panic!("Unexpected object, {:?}", object),
+        }
+    }
+
+    #[test]
+    fn test_iterators() {
+        let table = [
+            ("collect(map([1, 2, 3], fn(x) { x * 2 }));", "[2, 4, 6]"),
+            (
+                "collect(filter(0..10, fn(x) { x > 6 }));",
+                "[7, 8, 9]",
+            ),
+            ("collect(take(0..1000000000, 3));", "[0, 1, 2]"),
+            ("collect(zip([1, 2, 3], \"ab\"));", "[[1, a], [2, b]]"),
+            ("collect(enumerate({\"a\": 1}));", "[[0, a]]"),
+            ("collect(chain(0..2, [5]));", "[0, 1, 5]"),
+            ("let it = iter([1, 2]); next(it); next(it); next(it);", "null"),
+            // Advancing an iterator is visible through every binding.
+            ("let a = iter(0..5); let b = a; next(a); next(b);", "1"),
+            (
+                "let sum = 0; for (x in take(map(1..=100, fn(x) { x * x }), 3)) { let sum = sum + x; }; sum;",
+                "14",
+            ),
+            (
+                "try { collect(map([1, 0], fn(x) { throw x; })); } catch (e) { e[\"value\"]; };",
+                "1",
+            ),
+            (
+                "try { for (x in map([1], fn(x) { y; })) { x; }; } catch (e) { e[\"kind\"]; };",
+                "unknown_identifier",
+            ),
+            ("sort([3, -1, 2, 0]);", "[-1, 0, 2, 3]"),
+            ("sort(\"bca\");", "[a, b, c]"),
+            ("sort([]);", "[]"),
+            ("sort(collect(0..100)) == collect(0..100);", "true"),
+            // NaN isn't ordered, but doesn't stop the sort.
+            ("len(sort([2, 1.5, 0.0 / 0.0, 1]));", "4"),
+            // Sorting is stable: items with the same key keep their order.
+            (
+                "sort_by([[2, \"a\"], [1, \"b\"], [2, \"c\"]], |p| p[0]);",
+                "[[1, b], [2, a], [2, c]]",
+            ),
+            (
+                "try { sort([\"b\", 1]); } catch (e) { e[\"message\"]; };",
+                "type mismatch: INTEGER < STRING",
+            ),
+            (
+                "try { sort_by([1, 2], fn(x) { throw x; }); } catch (e) { e[\"value\"]; };",
+                "1",
+            ),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            assert_eq!(object.inspect(), *output);
+        });
+
+        match test_eval("map(5, fn(x) { x });".to_string()) {
+            Object::Error { value } => assert_eq!(
+                value.message,
+                "argument to `map` must be iterable, got INTEGER"
+            ),
+            object => panic!("Unexpected object, {:?}", object),
+        }
+    }
+
+    #[test]
+    fn test_generators() {
+        let table = [
+            (
+                "let g = fn() { yield 1; yield 2; }; collect(g());",
+                "[1, 2]",
+            ),
+            (
+                "let naturals = fn() { for (i in 0..1000000000) { yield i; } }; collect(take(naturals(), 3));",
+                "[0, 1, 2]",
+            ),
+            // The body only runs as far as it is asked to.
+            (
+                "let g = fn() { yield 1; throw \"late\"; }; let it = g(); next(it);",
+                "1",
+            ),
+            (
+                "let g = fn(n) { if (n > 0) { yield n; } else { yield -n; }; yield 0; }; collect(g(-3));",
+                "[3, 0]",
+            ),
+            (
+                "let g = fn() { yield 1; return 5; yield 2; }; collect(g());",
+                "[1]",
+            ),
+            (
+                "let g = fn() { let x = 10; for (k, v in {\"a\": 1, \"b\": 2}) { yield [k, v]; }; yield x; }; collect(g());",
+                "[[a, 1], [b, 2], 10]",
+            ),
+            (
+                "let g = fn() { try { yield 1; throw \"boom\"; } catch (e) { yield e[\"message\"]; } finally { yield 3; } }; collect(g());",
+                "[1, boom, 3]",
+            ),
+            (
+                "let e = 7; let g = fn() { try { throw \"boom\"; } catch (e) { yield e[\"message\"]; }; yield e; }; collect(g());",
+                "[boom, 7]",
+            ),
+            (
+                "let g = fn() { yield 1; yield 2; yield 3; }; let it = g(); next(it); collect(it);",
+                "[2, 3]",
+            ),
+            (
+                "let g = fn() { for (x in [1, 2, 3]) { if (x > 1) { yield x * 10; } } }; let sum = 0; for (x in g()) { let sum = sum + x; }; sum;",
+                "50",
+            ),
+            (
+                "let g = fn() { yield 1; undefined; }; try { collect(g()); } catch (e) { e[\"kind\"]; };",
+                "unknown_identifier",
+            ),
+            (
+                "let g = fn() { let x = if (true) { yield 1; }; }; try { collect(g()); } catch (e) { e[\"kind\"]; };",
+                "invalid_yield",
+            ),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            assert_eq!(object.inspect(), *output);
+        });
+
+        match test_eval("yield 1;".to_string()) {
+            Object::Error { value } => assert_eq!(value.kind, ErrorKind::InvalidYield),
+            object => panic!("Unexpected object, {:?}", object),
+        }
+    }
+
+    #[test]
+    fn test_match_expressions() {
+        let table = [
+            (
+                "match (2) { 1 => \"one\", 2 | 3 => \"few\", _ => \"many\" };",
+                "few",
+            ),
+            ("match (-1) { -1 => true, _ => false };", "true"),
+            ("match (\"a\") { \"b\" => 1, s => s + s };", "aa"),
+            ("match (null) { null => 1, _ => 2 };", "1"),
+            ("match ([1, 2, 3]) { [h, ..t] => t };", "[2, 3]"),
+            ("match ([1, 2, 3]) { [.., last] => last };", "3"),
+            (
+                "match ([1, 2, 3]) { [a, b] => 0, [a, b, c] => a + b + c };",
+                "6",
+            ),
+            ("match ([1]) { [a, b, ..] => 0, [..rest] => rest };", "[1]"),
+            (
+                "match ({\"x\": 1, \"y\": [2]}) { {\"x\": 0} => 0, {x, \"y\": [y]} => x + y };",
+                "3",
+            ),
+            (
+                "match (5) { n if n > 10 => \"big\", n => \"small\" };",
+                "small",
+            ),
+            ("match ([0, 4]) { [0, n] | [n, 0] => n, _ => -1 };", "4"),
+            // Bindings are scoped to the arm.
+            ("let n = 1; match (2) { n => n }; n;", "1"),
+            // Assignments to outer variables are kept.
+            ("let x = 0; match (1) { 1 => { x = 5; } }; x;", "5"),
+            (
+                "let x = 0; match (1) { n if n > 5 => 0, n => { x = x + n; } }; x;",
+                "1",
+            ),
+            ("const a = 1; match (2) { a => a * 2 };", "4"),
+            (
+                "const C = 1; let r = match (1) { _ => { let C = 2; C } }; [r, C];",
+                "[2, 1]",
+            ),
+            (
+                "let y = 0; match (1) { _ => { const y = 2; } }; y = 1; y;",
+                "1",
+            ),
+            (
+                "try { match (1) { _ => { let z = 3; } }; z; } catch (e) { e[\"kind\"]; };",
+                "unknown_identifier",
+            ),
+            (
+                "try { match (3) { 1 => 1 }; } catch (e) { e[\"kind\"]; };",
+                "no_match",
+            ),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            assert_eq!(object.inspect(), *output);
         });
     }
 
     #[test]
-    fn test_eval_boolean_expression() {
-        let table = vec![
-            ("true;".to_string(), true),
-            ("false;".to_string(), false),
-            ("1 < 2;".to_string(), true),
-            ("1 > 2;".to_string(), false),
-            ("1 < 1;".to_string(), false),
-            ("1 > 1;".to_string(), false),
-            ("1 == 1;".to_string(), true),
-            ("1 != 1;".to_string(), false),
-            ("1 == 2;".to_string(), false),
-            ("1 != 2;".to_string(), true),
-            ("true == true;".to_string(), true),
-            ("false == false;".to_string(), true),
-            ("true == false;".to_string(), false),
-            ("true != false;".to_string(), true),
-            ("false != true;".to_string(), true),
-            ("(1 < 2) == true;".to_string(), true),
-            ("(1 < 2) == false;".to_string(), false),
-            ("(1 > 2) == true;".to_string(), false),
-            ("(1 > 2) == false;".to_string(), true),
+    fn test_destructuring() {
+        let table = [
+            (
+                "let [a, b, ..rest] = [1, 2, 3, 4]; [a, b, rest];",
+                "[1, 2, [3, 4]]",
+            ),
+            (
+                "let [first, .., last] = [1, 2, 3, 4, 5]; [first, last];",
+                "[1, 5]",
+            ),
+            ("let [x, [y, z]] = [1, [2, 3]]; x + y + z;", "6"),
+            (
+                "let {name, age: years} = {\"name\": \"ann\", \"age\": 30}; [name, years];",
+                "[ann, 30]",
+            ),
+            (
+                "let f = fn([a, b], {c}) { a * b + c }; f([2, 3], {\"c\": 4});",
+                "10",
+            ),
+            ("let swap = fn([a, b]) { [b, a] }; swap([1, 2]);", "[2, 1]"),
         ];
 
         table.iter().for_each(|(input, output)| {
             let object = test_eval(input.to_string());
-            match object {
-                Object::Boolean { value } => assert_eq!(value, *output),
-                _ => panic!("Unexpected object, {:?}", object),
-            }
+            assert_eq!(object.inspect(), *output);
         });
+
+        let errors = [
+            (
+                "let [a, b] = [1];",
+                "cannot destructure [1]: expected 2 elements, got 1",
+            ),
+            (
+                "let [a, b, ..c] = [1];",
+                "cannot destructure [1]: expected at least 2 elements, got 1",
+            ),
+            (
+                "let {name} = {};",
+                "cannot destructure {}: missing key \"name\"",
+            ),
+            (
+                "let [a] = 5;",
+                "cannot destructure 5: expected ARRAY, got INTEGER",
+            ),
+            (
+                "let f = fn({x}) { x }; f([1]);",
+                "cannot destructure [1]: expected HASH, got ARRAY",
+            ),
+        ];
+
+        errors
+            .iter()
+            .for_each(|(input, message)| match test_eval(input.to_string()) {
+                Object::Error { value } => {
+                    assert_eq!(value.kind, ErrorKind::PatternMismatch);
+                    assert_eq!(value.message, *message);
+                }
+                object => panic!("Unexpected object, {:?}", object),
+            });
     }
 
     #[test]
-    fn test_bang_operator() {
-        let table = vec![
-            ("!true;".to_string(), false),
-            ("!false;".to_string(), true),
-            ("!5;".to_string(), false),
-            ("!!true;".to_string(), true),
-            ("!!false;".to_string(), false),
-            ("!!5;".to_string(), true),
+    fn test_function_parameters() {
+        let table = [
+            ("let f = fn(x, y = 10) { x + y }; f(1);", "11"),
+            ("let f = fn(x, y = 10) { x + y }; f(1, 2);", "3"),
+            ("let f = fn(x, y = x * 2) { y }; f(4);", "8"),
+            ("let f = fn(first, ...rest) { rest }; f(1, 2, 3);", "[2, 3]"),
+            ("let f = fn(first, ...rest) { rest }; f(1);", "[]"),
+            ("let f = fn(x, y) { x - y }; f(y: 2, x: 10);", "8"),
+            (
+                "let f = fn(x, y = 1, z = 2) { [x, y, z] }; f(0, z: 5);",
+                "[0, 1, 5]",
+            ),
         ];
 
         table.iter().for_each(|(input, output)| {
             let object = test_eval(input.to_string());
-            match object {
-                Object::Boolean { value } => assert_eq!(value, *output),
-                _ => panic!("Unexpected object"),
-            }
+            assert_eq!(object.inspect(), *output);
         });
+
+        let errors = [
+            (
+                "let add = fn(x, y) { x + y }; add(1);",
+                "wrong number of arguments: want=2, got=1",
+            ),
+            (
+                "let add = fn(x, y) { x + y }; add(1, 2, 3);",
+                "wrong number of arguments: want=2, got=3",
+            ),
+            (
+                "let f = fn(x, y = 1) { x }; f();",
+                "wrong number of arguments: want=1..=2, got=0",
+            ),
+            (
+                "let f = fn(x, ...r) { x }; f();",
+                "wrong number of arguments: want>=1, got=0",
+            ),
+            (
+                "let f = fn(x, y) { x }; f(y: 1, z: 2);",
+                "missing argument for parameter x",
+            ),
+            (
+                "let f = fn(x, y = 1) { x }; f(1, z: 2);",
+                "unknown parameter: z",
+            ),
+            (
+                "let f = fn(x, y) { x }; f(1, x: 2);",
+                "multiple values for parameter x",
+            ),
+            ("ok(value: 1);", "builtin ok doesn't take named arguments"),
+        ];
+
+        errors
+            .iter()
+            .for_each(|(input, message)| match test_eval(input.to_string()) {
+                Object::Error { value } => assert_eq!(value.message, *message),
+                object => panic!("Unexpected object, {:?}", object),
+            });
     }
 
     #[test]
-    fn test_if_else_expressions() {
-        let table = vec![
+    fn test_spread() {
+        let table = [
             (
-                "if (true) { 10 };".to_string(),
-                Object::Integer { value: 10 },
+                "let a = [1, 2]; let b = [3]; [...a, ...b, 4];",
+                "[1, 2, 3, 4]",
             ),
-            ("if (false) { 10 };".to_string(), Object::Null),
-            ("if (1) { 10 };".to_string(), Object::Integer { value: 10 }),
+            ("[...0..3, ...\"ab\"];", "[0, 1, 2, a, b]"),
+            ("[...{\"x\": 1, \"y\": 2}];", "[x, y]"),
+            ("[...map([1, 2], fn(x) { x * 10 })];", "[10, 20]"),
             (
-                "if (1 < 2) { 10 };".to_string(),
-                Object::Integer { value: 10 },
+                "let defaults = {\"a\": 1, \"b\": 2}; {...defaults, \"b\": 3};",
+                "{a: 1, b: 3}",
             ),
-            ("if (1 > 2) { 10 };".to_string(), Object::Null),
+            ("{\"b\": 3, ...{\"a\": 1, \"b\": 2}};", "{a: 1, b: 2}"),
+            ("let f = fn(x, y, z) { x + y * z }; f(...[1, 2], 3);", "7"),
+            ("let f = fn(...r) { r }; f(...1..=3);", "[1, 2, 3]"),
+            ("let f = fn(x, y = 0) { x - y }; f(...[5], y: 1);", "4"),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            assert_eq!(object.inspect(), *output);
+        });
+
+        let errors = [
+            ("[...5];", "cannot spread INTEGER"),
+            ("{...[1]};", "cannot spread ARRAY into a hash"),
+        ];
+
+        errors
+            .iter()
+            .for_each(|(input, message)| match test_eval(input.to_string()) {
+                Object::Error { value } => assert_eq!(value.message, *message),
+                object => panic!("Unexpected object, {:?}", object),
+            });
+    }
+
+    #[test]
+    fn test_lambdas_and_pipelines() {
+        let table = [
+            ("let add = |a, b = 1| a + b; add(2);", "3"),
+            ("let inc = x => x + 1; 5 |> inc;", "6"),
+            ("collect(filter(0..6, n => n > 3));", "[4, 5]"),
+            ("let f = |x| { let y = x * 2; y + 1 }; f(3);", "7"),
+            ("let f = || 42; f();", "42"),
+            ("[1, 2, 3] |> map(|x| x * 2) |> collect;", "[2, 4, 6]"),
+            ("let sub = |a, b| a - b; 10 |> sub(3);", "7"),
+            ("let inc = x => x + 1; 3 |> inc + 1;", "5"),
             (
-                "if (1 > 2) { 10 } else { 20 };".to_string(),
-                Object::Integer { value: 20 },
+                "let flag = true; match (1) { _ if flag => 1, _ => 2 };",
+                "1",
             ),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(input.to_string());
+            assert_eq!(object.inspect(), *output);
+        });
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let table = [
             (
-                "if (1 < 2) { 10 } else { 20 };".to_string(),
-                Object::Integer { value: 10 },
+                "let f = fn(n) { if (n > 2) { 3 } else if (n > 1) { 2 } else { 1 } }; [f(3), f(2), f(0)];",
+                "[3, 2, 1]",
             ),
+            ("let n = 5; n > 3 ? \"big\" : \"small\";", "big"),
+            ("false ? 1 : null ? 2 : 3;", "3"),
+            ("true && 1 < 2;", "true"),
+            ("false || null;", "false"),
+            // The right operand is only evaluated when needed.
+            ("false && undefined;", "false"),
+            ("true || undefined;", "true"),
+            ("let r = ok(2); r? > 1 ? r? * 10 : 0;", "20"),
+            ("let x = 3; true ? -x : x;", "-3"),
+            ("false ? 1 : !true;", "false"),
+            ("let r = ok(2); true ? r? - 1 : 0;", "1"),
         ];
 
         table.iter().for_each(|(input, output)| {
             let object = test_eval(input.to_string());
-            assert_eq!(object, *output);
+            assert_eq!(object.inspect(), *output);
         });
     }
 
     #[test]
-    fn test_return_statements() {
-        let table = vec![
-            ("return 10;".to_string(), 10),
-            ("return 10; 9;".to_string(), 10),
-            ("return 2 * 5; 9;".to_string(), 10),
-            ("9; return 2 * 5; 9;".to_string(), 10),
+    fn test_constants_and_frozen_collections() {
+        let table = [
+            ("const a = 5; a * 2;", "10"),
+            ("const [a, b] = [1, 2]; a + b;", "3"),
+            // Constants can be shadowed in nested scopes.
             (
-                "if (10 > 1) { if (10 > 1) { return 10; } return 1; };".to_string(),
-                10,
+                "const a = 1; let f = fn() { let a = 2; a }; [f(), a];",
+                "[2, 1]",
+            ),
+            ("const a = 1; match (2) { a => a };", "2"),
+            ("let a = [1, [2, 3]]; a[1][0] = 5; a;", "[1, [5, 3]]"),
+            (
+                "let h = {}; h[\"k\"] = 1; h[\"k\"] = h[\"k\"] + 1; h;",
+                "{k: 2}",
+            ),
+            ("let a = 1; a = 2; a;", "2"),
+            // Collections are values, so copies can be modified on their own.
+            ("let a = [1]; let b = a; b[0] = 2; [a, b];", "[[1], [2]]"),
+            (
+                "let a = freeze([1, {\"x\": 2}]); [is_frozen(a), is_frozen(a[1])];",
+                "[true, true]",
             ),
+            ("freeze([1, 2]) == [1, 2];", "true"),
+            ("is_frozen([1]);", "false"),
+            ("let a = freeze([1]); let b = [...a]; b[0] = 2; b;", "[2]"),
         ];
 
         table.iter().for_each(|(input, output)| {
             let object = test_eval(input.to_string());
-            match object {
-                Object::Integer { value } => assert_eq!(value, *output),
-                _ => panic!("Unexpected object"),
-            }
+            assert_eq!(object.inspect(), *output);
         });
+
+        let errors = [
+            ("const a = 1; let a = 2;", "cannot reassign constant a"),
+            ("const a = 1; const a = 2;", "cannot reassign constant a"),
+            (
+                "let a = 1; const a = 2;",
+                "cannot redeclare a as a constant",
+            ),
+            ("const a = 1; a = 2;", "cannot reassign constant a"),
+            ("const a = [1]; a[0] = 2;", "cannot reassign constant a"),
+            (
+                "const a = 1; let f = fn() { a = 2 }; f();",
+                "cannot reassign constant a",
+            ),
+            (
+                "const i = 0; for (i in 0..2) {}",
+                "cannot reassign constant i",
+            ),
+            (
+                "let a = freeze([1]); a[0] = 2;",
+                "cannot modify frozen ARRAY",
+            ),
+            (
+                "let h = freeze({\"a\": {\"b\": 1}}); h[\"a\"][\"b\"] = 2;",
+                "cannot modify frozen HASH",
+            ),
+            (
+                "let a = [1]; a[3] = 2;",
+                "index out of range: 3 for ARRAY of length 1",
+            ),
+            ("b = 1;", "identifier not found: b"),
+        ];
+
+        errors
+            .iter()
+            .for_each(|(input, message)| match test_eval(input.to_string()) {
+                Object::Error { value } => assert_eq!(value.message, *message),
+                object => panic!("Unexpected object, {:?}", object),
+            });
+
+        let caught = test_eval("const a = 1; try { a = 2 } catch (e) { e[\"kind\"] };".to_string());
+        assert_eq!(caught.inspect(), "constant_reassignment");
     }
 
     #[test]
-    fn test_error_handling() {
-        let table = vec![
-            ("5 + true;".to_string(), "type mismatch: INTEGER + BOOLEAN"),
+    fn test_structs() {
+        let table = [
             (
-                "5 + true; 5;".to_string(),
-                "type mismatch: INTEGER + BOOLEAN",
+                "struct Point { x, y } Point { x: 1, y: 2 };",
+                "Point { x: 1, y: 2 }",
             ),
-            ("-true;".to_string(), "unknown operator: -BOOLEAN"),
+            ("struct Point { x, y } Point;", "struct Point { x, y }"),
+            ("struct Unit {} Unit {};", "Unit {}"),
+            // Fields are kept in the order they were declared in.
             (
-                "true + false;".to_string(),
-                "unknown operator: BOOLEAN + BOOLEAN",
+                "struct P { x, y } let x = 3; P { y: 1, x };",
+                "P { x: 3, y: 1 }",
             ),
             (
-                "5; true + false; 5;".to_string(),
-                "unknown operator: BOOLEAN + BOOLEAN",
+                "struct P { x, y } let p = P { x: 1, y: 2 }; p.x + p.y;",
+                "3",
             ),
             (
-                "if (10 > 1) { true + false; };".to_string(),
-                "unknown operator: BOOLEAN + BOOLEAN",
+                "struct P { x, y } let p = P { x: P { x: 1, y: 2 }, y: 3 }; p.x.y;",
+                "2",
             ),
             (
-                "if (10 > 1) { if (10 > 1) { return true + false; } return 1; };".to_string(),
-                "unknown operator: BOOLEAN + BOOLEAN",
+                "struct P { x } let p = P { x: [1] }; p.x[0] = 5; p;",
+                "P { x: [5] }",
             ),
-            ("foobar;".to_string(), "identifier not found: foobar"),
+            (
+                "struct P { x } let a = [P { x: 1 }]; a[0].x = 2; a;",
+                "[P { x: 2 }]",
+            ),
+            (
+                "struct P { x } let p = P { x: 1 }; let q = p; q.x = 2; [p.x, q.x];",
+                "[1, 2]",
+            ),
+            (
+                "struct P { x, y } P { x: 1, y: 2 } == P { x: 1, y: 2 };",
+                "true",
+            ),
+            (
+                "struct P { x, y } P { x: 1, y: 2 } == P { x: 2, y: 1 };",
+                "false",
+            ),
+            (
+                "struct P { x } struct Q { x } P { x: 1 } == Q { x: 1 };",
+                "false",
+            ),
+            ("struct P { x } P { x: 1 } == {\"x\": 1};", "false"),
+            (
+                "let h = {\"a\": {\"b\": 1}}; h.a.b = 2; [h.a.b, h.c];",
+                "[2, null]",
+            ),
+            ("let p = null; p?.x;", "null"),
         ];
 
         table.iter().for_each(|(input, output)| {
             let object = test_eval(input.to_string());
-            match object {
-                Object::Error { value } => assert_eq!(value, *output),
-                _ => panic!("Unexpected object"),
+            assert_eq!(object.inspect(), *output);
+        });
+
+        let errors = [
+            ("struct P { x } P { x: 1, z: 2 };", "no field z on P"),
+            ("struct P { x, y } P { x: 1 };", "missing field y for P"),
+            (
+                "struct P { x } P { x: 1, x: 2 };",
+                "field x given more than once for P",
+            ),
+            ("struct P { x } P { x: 1 }.y;", "no field y on P"),
+            (
+                "struct P { x } let p = P { x: 1 }; p.y = 2;",
+                "no field y on P",
+            ),
+            ("let A = 1; A { x: 1 };", "INTEGER is not a struct"),
+            ("5.x;", "no field x on INTEGER"),
+            (
+                "struct P { x } let p = freeze(P { x: 1 }); p.x = 2;",
+                "cannot modify frozen P",
+            ),
+        ];
+
+        errors
+            .iter()
+            .for_each(|(input, message)| match test_eval(input.to_string()) {
+                Object::Error { value } => assert_eq!(value.message, *message),
+                object => panic!("Unexpected object, {:?}", object),
+            });
+    }
+
+    #[test]
+    fn test_methods() {
+        let point = "struct Point { x, y }
+            impl Point {
+                fn new(x, y) { Point { x, y } }
+                fn sum(self) { self.x + self.y }
+                fn scale(self, by) { Point.new(self.x * by, self.y * by) }
+                fn twice(self) { self.scale(2).sum() }
+            }";
+
+        let table = [
+            ("Point.new(1, 2);", "Point { x: 1, y: 2 }"),
+            ("Point.new(1, 2).sum();", "3"),
+            ("let p = Point { x: 1, y: 2 }; p.scale(3);", "Point { x: 3, y: 6 }"),
+            // Methods can call the methods of their own struct.
+            ("Point.new(1, 2).twice();", "6"),
+            ("Point.sum(Point.new(2, 2));", "4"),
+            ("let f = fn(p) { p.sum() }; f(Point.new(1, 1));", "2"),
+            ("impl Point { fn neg(self) { Point.new(-self.x, -self.y) } } Point.new(1, 2).neg();", "Point { x: -1, y: -2 }"),
+            ("\"abc\".upper();", "ABC"),
+            ("\"aBc\".lower().len();", "3"),
+            ("[1, 2, 3].map(|x| x * 2).filter(|x| x > 2).collect();", "[4, 6]"),
+            ("(0..3).collect();", "[0, 1, 2]"),
+            ("ok(1).unwrap();", "1"),
+            ("{\"f\": |x| x + 1}.f(1);", "2"),
+            ("struct S { f } S { f: |x| x * 3 }.f(2);", "6"),
+            ("null?.len();", "null"),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(format!("{} {}", point, input));
+            assert_eq!(object.inspect(), *output, "{}", input);
+        });
+
+        let errors = [
+            ("Point.new(1, 2).area();", "no method area on Point"),
+            // Functions without self are only called on the struct itself.
+            ("Point.new(1, 2).new(1, 2);", "no method new on Point"),
+            ("Point.area();", "no method area on Point"),
+            ("5.upper();", "no method upper on INTEGER"),
+            ("impl Nope { fn f() {} }", "identifier not found: Nope"),
+            (
+                "let n = 1; impl n { fn f() {} }",
+                "INTEGER is not a struct or enum",
+            ),
+        ];
+
+        errors.iter().for_each(|(input, message)| {
+            match test_eval(format!("{} {}", point, input)) {
+                Object::Error { value } => assert_eq!(value.message, *message),
+                object => panic!("Unexpected object, {:?}", object),
+            }
+        });
+    }
+
+    #[test]
+    fn test_enums() {
+        let shape = "enum Shape { Circle(r), Rect(w, h), Empty }
+            impl Shape {
+                fn area(self) {
+                    match (self) {
+                        Circle(r) => 3 * r * r,
+                        Shape.Rect(w, h) => w * h,
+                        Empty => 0,
+                    }
+                }
+            }";
+
+        let table = [
+            ("Shape;", "enum Shape { Circle(r), Rect(w, h), Empty }"),
+            ("Shape.Circle;", "constructor Shape.Circle"),
+            ("Shape.Circle(2);", "Shape.Circle(2)"),
+            ("Shape.Empty;", "Shape.Empty"),
+            ("Shape.Rect(h: 3, w: 2);", "Shape.Rect(2, 3)"),
+            ("Shape.Rect(2, 3).h;", "3"),
+            ("Shape.Rect(2, 3).area();", "6"),
+            ("Shape.Circle(1).area() + Shape.Empty.area();", "3"),
+            ("Shape.Circle(1) == Shape.Circle(1);", "true"),
+            ("Shape.Circle(1) == Shape.Circle(2);", "false"),
+            (
+                "[1, 2].map(Shape.Circle).collect();",
+                "[Shape.Circle(1), Shape.Circle(2)]",
+            ),
+            (
+                "match (Shape.Rect(1, 2)) { Rect(_, 2) => \"tall\", _ => \"wide\" };",
+                "tall",
+            ),
+            (
+                "match (Shape.Rect(1, 2)) { Circle => \"round\", _ => \"square\" };",
+                "square",
+            ),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let object = test_eval(format!("{} {}", shape, input));
+            assert_eq!(object.inspect(), *output, "{}", input);
+        });
+
+        let errors = [
+            (
+                "Shape.Circle(1, 2);",
+                "wrong number of arguments: want=1, got=2",
+            ),
+            ("Shape.Square;", "no method Square on Shape"),
+            ("Shape.Circle(1).w;", "no field w on Shape.Circle"),
+            (
+                "Shape.Empty.perimeter();",
+                "no method perimeter on Shape.Empty",
+            ),
+            (
+                "match (Shape.Empty) { Circle(r) => r, Rect(w, h) => w };",
+                "no match arm for Shape.Empty",
+            ),
+            (
+                "Shape.Circle(1) + 1;",
+                "type mismatch: Shape.Circle + INTEGER",
+            ),
+        ];
+
+        errors.iter().for_each(|(input, message)| {
+            match test_eval(format!("{} {}", shape, input)) {
+                Object::Error { value } => assert_eq!(value.message, *message),
+                object => panic!("Unexpected object, {:?}", object),
             }
         });
     }
 
     #[test]
     fn test_let_statements() {
-        let table = vec![
+        let table = [
             ("let a = 5; a;".to_string(), 5),
             ("let a = 5 * 5; a;".to_string(), 25),
             ("let a = 5; let b = a; b;".to_string(), 5),
@@ -554,7 +3363,7 @@ mod tests {
 
     #[test]
     fn test_function_application() {
-        let table = vec![
+        let table = [
             ("let identity = fn(x) { x; }; identity(5);".to_string(), 5),
             (
                 "let identity = fn(x) { return x; }; identity(5);".to_string(),
@@ -578,6 +3387,72 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_memory_limit() {
+        // Every call captures the previous closure, so each frame deep-clones
+        // an ever growing chain of environments.
+        let input = "
+            let grow = fn(g, n, acc) {
+                if (n > 1) { g(g, n - 1, fn() { acc; }); } else { 1; }
+            };
+            grow(grow, 200, 1);";
+
+        memory::set_limit(Some(64 * 1024));
+        let object = test_eval(input.to_string());
+        let caught = test_eval(format!("try {{ {} }} catch (e) {{ 0; }};", input));
+        memory::set_limit(None);
+
+        match object {
+            Object::Error { value } => assert_eq!(value.kind, ErrorKind::MemoryLimitExceeded),
+            _ => panic!("Unexpected object, {:?}", object),
+        }
+
+        // Scripts can't recover from exceeding the memory limit.
+        assert!(matches!(caught, Object::Error { .. }));
+
+        assert_eq!(memory::usage().current, memory::Footprint::default());
+
+        // Large values are refused before they are built.
+        let inputs = [
+            "collect(0..3000000);",
+            "repeat(\"ab\", 100000000);",
+            "let a = collect(0..400); let b = [...a, ...a];",
+            "let a = collect(0..400); let b = [a, a, a];",
+            "let a = repeat(\"x\", 30000); len(a + a + a + a);",
+            "let a = repeat(\"x\", 30000); len(\"${a}${a}${a}${a}\");",
+            "let a = repeat(\"x\", 45000); len(join([a, \"\"], a));",
+            "let a = repeat(\"x\", 30000); len(replace(a, \"x\", \"xxxx\"));",
+            "let a = repeat(\"x\", 30000); len(chars(a));",
+            // Fallible builtins don't turn the error into an err value.
+            "json_parse(\"[\" + repeat(\"1,\", 20000) + \"1]\");",
+        ];
+
+        memory::set_limit(Some(100_000));
+        let objects: Vec<Object> = inputs.iter().map(|i| test_eval(i.to_string())).collect();
+        memory::set_limit(None);
+
+        for (input, object) in inputs.iter().zip(objects) {
+            match object {
+                Object::Error { value } => {
+                    assert_eq!(value.kind, ErrorKind::MemoryLimitExceeded, "{}", input)
+                }
+                _ => panic!("Unexpected object for {}, {:?}", input, object),
+            }
+        }
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        memory::reset_peak();
+        let object = test_eval("let a = 5; let f = fn(x) { a + x; }; f(5);".to_string());
+        assert_eq!(object, Object::Integer { value: 10 });
+
+        let usage = memory::usage();
+        assert_eq!(usage.current, memory::Footprint::default());
+        assert!(usage.peak.objects >= 3);
+        assert!(usage.peak.bytes > 0);
+    }
+
     fn test_eval(input: String) -> Object {
         let l = Lexer::new(&input);
         let mut p = Parser::new(l);
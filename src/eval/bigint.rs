@@ -0,0 +1,349 @@
+use super::builtins::{check_arity, invalid_argument};
+use super::error::{ErrorKind, RuntimeError};
+use super::object::Object;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// The base of the digits of a BigInt. A power of ten keeps converting to and
+/// from decimal strings simple.
+const BASE: u64 = 1_000_000_000;
+
+/// An integer of arbitrary size, stored as its sign and its digits in base
+/// 10^9, least significant first. Zero has no digits, and is never negative.
+///
+/// Integers are promoted to big integers when they meet one, or when the
+/// result of arithmetic on them doesn't fit in an integer. Big integers are
+/// promoted to floats in turn, and are never turned back into integers on
+/// their own.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BigInt {
+    negative: bool,
+    digits: Vec<u32>,
+}
+
+impl BigInt {
+    fn new(negative: bool, mut digits: Vec<u32>) -> Self {
+        trim(&mut digits);
+        Self {
+            negative: negative && !digits.is_empty(),
+            digits,
+        }
+    }
+
+    /// Parses a decimal integer, with an optional leading minus sign.
+    pub fn parse(input: &str) -> Option<Self> {
+        let (negative, decimal) = match input.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, input),
+        };
+
+        if decimal.is_empty() || !decimal.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        let mut digits = vec![];
+        let mut end = decimal.len();
+        while end > 0 {
+            let start = end.saturating_sub(9);
+            digits.push(decimal[start..end].parse().ok()?);
+            end = start;
+        }
+
+        Some(Self::new(negative, digits))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    /// Returns an estimate of the memory held by the digits, in bytes.
+    pub fn byte_size(&self) -> usize {
+        self.digits.len() * 4
+    }
+
+    /// Converts to the nearest float, which is infinite if the integer is out
+    /// of range.
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    pub fn abs(&self) -> Self {
+        Self::new(false, self.digits.clone())
+    }
+
+    pub fn neg(&self) -> Self {
+        Self::new(!self.negative, self.digits.clone())
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        if self.negative == other.negative {
+            return Self::new(self.negative, add_digits(&self.digits, &other.digits));
+        }
+
+        match compare_digits(&self.digits, &other.digits) {
+            Ordering::Less => Self::new(other.negative, sub_digits(&other.digits, &self.digits)),
+            _ => Self::new(self.negative, sub_digits(&self.digits, &other.digits)),
+        }
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        let mut product = vec![0; self.digits.len() + other.digits.len()];
+        for (i, &a) in self.digits.iter().enumerate() {
+            let mut carry = 0;
+            for (j, &b) in other.digits.iter().enumerate() {
+                let total = product[i + j] + u64::from(a) * u64::from(b) + carry;
+                product[i + j] = total % BASE;
+                carry = total / BASE;
+            }
+
+            let mut k = i + other.digits.len();
+            while carry > 0 {
+                let total = product[k] + carry;
+                product[k] = total % BASE;
+                carry = total / BASE;
+                k += 1;
+            }
+        }
+
+        let digits = product.into_iter().map(|d| d as u32).collect();
+        Self::new(self.negative != other.negative, digits)
+    }
+
+    /// Divides by another BigInt, rounding toward zero like integer division
+    /// does. The remainder has the sign of the dividend. Returns None when
+    /// dividing by zero.
+    pub fn div_rem(&self, other: &Self) -> Option<(Self, Self)> {
+        if other.is_zero() {
+            return None;
+        }
+
+        let mut quotient = vec![0; self.digits.len()];
+        let mut remainder: Vec<u32> = vec![];
+        for (i, &digit) in self.digits.iter().enumerate().rev() {
+            remainder.insert(0, digit);
+            trim(&mut remainder);
+
+            // Finds the largest digit whose product with the divisor still
+            // fits in the remainder.
+            let (mut low, mut high) = (0, (BASE - 1) as u32);
+            while low < high {
+                let mid = low + (high - low).div_ceil(2);
+                if compare_digits(&mul_digit(&other.digits, mid), &remainder) == Ordering::Greater {
+                    high = mid - 1;
+                } else {
+                    low = mid;
+                }
+            }
+
+            remainder = sub_digits(&remainder, &mul_digit(&other.digits, low));
+            quotient[i] = low;
+        }
+
+        Some((
+            Self::new(self.negative != other.negative, quotient),
+            Self::new(self.negative, remainder),
+        ))
+    }
+
+    pub fn pow(&self, mut exponent: u32) -> Self {
+        let mut result = Self::from(1);
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                result = result.mul(&base);
+            }
+
+            exponent /= 2;
+            if exponent > 0 {
+                base = base.mul(&base);
+            }
+        }
+
+        result
+    }
+
+    /// Returns the greatest common divisor of two BigInts, which is never
+    /// negative.
+    pub fn gcd(&self, other: &Self) -> Self {
+        let (mut a, mut b) = (self.abs(), other.abs());
+        while let Some((_, remainder)) = a.div_rem(&b) {
+            a = b;
+            b = remainder;
+        }
+
+        a
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let mut magnitude = value.unsigned_abs();
+        let mut digits = vec![];
+        while magnitude > 0 {
+            digits.push((magnitude % BASE) as u32);
+            magnitude /= BASE;
+        }
+
+        Self::new(value < 0, digits)
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_digits(&self.digits, &other.digits),
+            (true, true) => compare_digits(&other.digits, &self.digits),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut digits = self.digits.iter().rev();
+        match digits.next() {
+            Some(first) if self.negative => write!(f, "-{}", first)?,
+            Some(first) => write!(f, "{}", first)?,
+            None => return write!(f, "0"),
+        }
+
+        digits.try_for_each(|digit| write!(f, "{:09}", digit))
+    }
+}
+
+/// Drops the leading zeros of a number.
+fn trim(digits: &mut Vec<u32>) {
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+}
+
+fn compare_digits(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_digits(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut sum = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+    for i in 0..a.len().max(b.len()) {
+        let total = u64::from(*a.get(i).unwrap_or(&0)) + u64::from(*b.get(i).unwrap_or(&0)) + carry;
+        sum.push((total % BASE) as u32);
+        carry = total / BASE;
+    }
+
+    if carry > 0 {
+        sum.push(carry as u32);
+    }
+
+    sum
+}
+
+/// Subtracts `b` from `a`, which must not be smaller.
+fn sub_digits(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+    for (i, &digit) in a.iter().enumerate() {
+        let subtracted = i64::from(*b.get(i).unwrap_or(&0)) + borrow;
+        let mut total = i64::from(digit) - subtracted;
+        borrow = 0;
+        if total < 0 {
+            total += BASE as i64;
+            borrow = 1;
+        }
+
+        difference.push(total as u32);
+    }
+
+    trim(&mut difference);
+    difference
+}
+
+fn mul_digit(a: &[u32], digit: u32) -> Vec<u32> {
+    let mut product = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0;
+    for &d in a {
+        let total = u64::from(d) * u64::from(digit) + carry;
+        product.push((total % BASE) as u32);
+        carry = total / BASE;
+    }
+
+    if carry > 0 {
+        product.push(carry as u32);
+    }
+
+    trim(&mut product);
+    product
+}
+
+/// Converts an integer, or a string holding a decimal integer, to a big
+/// integer.
+pub fn bigint(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    let value = match &args[0] {
+        Object::Integer { value } => BigInt::from(*value),
+        Object::BigInt { value } => value.clone(),
+        Object::String { value } => BigInt::parse(value.trim()).ok_or_else(|| {
+            RuntimeError::new(
+                ErrorKind::InvalidArgument,
+                format!("cannot convert {:?} to BIGINT", value),
+            )
+        })?,
+        other => return Err(invalid_argument("bigint", "INTEGER or STRING", other)),
+    };
+
+    Ok(Object::BigInt { value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(input: &str) -> BigInt {
+        BigInt::parse(input).unwrap()
+    }
+
+    #[test]
+    fn test_bigint_arithmetic() {
+        let a = big("123456789012345678901234567890");
+        let b = big("-987654321098765432109876543210");
+
+        assert_eq!(a.add(&b).to_string(), "-864197532086419753208641975320");
+        assert_eq!(a.sub(&b).to_string(), "1111111110111111111011111111100");
+        assert_eq!(
+            a.mul(&b).to_string(),
+            "-121932631137021795226185032733622923332237463801111263526900"
+        );
+        assert_eq!(big("-0").to_string(), "0");
+        assert_eq!(big("000001000000000").to_string(), "1000000000");
+        assert_eq!(BigInt::from(i64::MIN).to_string(), "-9223372036854775808");
+        assert_eq!(
+            big("2").pow(100).to_string(),
+            "1267650600228229401496703205376"
+        );
+
+        let (quotient, remainder) = b.div_rem(&a).unwrap();
+        assert_eq!(
+            (quotient.to_string(), remainder.to_string()),
+            ("-8".to_string(), "-9000000000900000000090".to_string())
+        );
+        assert!(a.div_rem(&BigInt::default()).is_none());
+
+        assert_eq!(big("-12").gcd(&big("18")).to_string(), "6");
+        assert!(b < a && big("-1") > b && big("10") > big("9"));
+        assert_eq!(big("1").to_f64(), 1.0);
+        assert!(BigInt::parse("1-2").is_none() && BigInt::parse("").is_none());
+    }
+}
@@ -0,0 +1,831 @@
+use super::bigint;
+use super::error::{ErrorKind, RuntimeError};
+use super::iterator::{new_iterator, Sequence};
+use super::json;
+use super::math;
+use super::object::Object;
+use super::random;
+use super::{apply_function, eval_infix_expression, is_unwinding, reserve_memory};
+use std::mem;
+
+/// The signature of functions built into the interpreter.
+pub type BuiltinFunction = fn(Vec<Object>) -> Object;
+
+/// Returns the builtin function with the given name, if there is one, or
+/// the `math` namespace.
+pub fn lookup(name: &str) -> Option<Object> {
+    if name == "math" {
+        return Some(math::module());
+    }
+
+    resolve(name).map(|_| Object::Builtin {
+        name: name.to_string(),
+    })
+}
+
+/// Returns the builtin function called by a method of a built-in type, which
+/// takes the value the method is called on as its first argument, e.g.
+/// `[1, 2].map(f)` calls `map([1, 2], f)`.
+pub fn method(object: &Object, name: &str) -> Option<Object> {
+    const SEQUENCE: &[&str] = &[
+        "join",
+        "contains",
+        "find",
+        "iter",
+        "map",
+        "filter",
+        "take",
+        "zip",
+        "enumerate",
+        "chain",
+        "collect",
+    ];
+
+    const STRING: &[&str] = &[
+        "len",
+        "upper",
+        "lower",
+        "parse_int",
+        "split",
+        "trim",
+        "starts_with",
+        "ends_with",
+        "replace",
+        "substring",
+        "chars",
+        "bytes",
+        "repeat",
+        "format",
+    ];
+
+    let found = match object {
+        Object::String { .. } => STRING.contains(&name) || SEQUENCE.contains(&name),
+        Object::Array { .. } => {
+            matches!(name, "len" | "freeze" | "is_frozen" | "shuffle" | "choice")
+                || SEQUENCE.contains(&name)
+        }
+        Object::Hash { .. } => {
+            matches!(name, "len" | "freeze" | "is_frozen") || SEQUENCE.contains(&name)
+        }
+        Object::Range { .. } => SEQUENCE.contains(&name),
+        Object::Iterator { .. } => name == "next" || SEQUENCE.contains(&name),
+        Object::Result { .. } => matches!(name, "is_ok" | "is_err" | "unwrap" | "unwrap_or"),
+        Object::Struct { .. } => matches!(name, "freeze" | "is_frozen"),
+        _ => false,
+    };
+
+    if !found {
+        return None;
+    }
+
+    lookup(name)
+}
+
+/// Calls the builtin function with the given name.
+pub fn call(name: &str, args: Vec<Object>) -> Object {
+    match resolve(name) {
+        Some(function) => function(args),
+        None => Object::error(
+            ErrorKind::UnknownIdentifier,
+            format!("unknown builtin: {}", name),
+        ),
+    }
+}
+
+/// Builtins report failures as a `RuntimeError`. Most of them raise it, which
+/// aborts the script unless it is caught, but a builtin can opt into handing
+/// failures back to the script as an `err(...)` value instead, by being
+/// registered as `fallible`.
+fn resolve(name: &str) -> Option<BuiltinFunction> {
+    let function: BuiltinFunction = match name {
+        "ok" => |args| raise(ok(args)),
+        "err" => |args| raise(err(args)),
+        "is_ok" => |args| raise(is_ok(args)),
+        "is_err" => |args| raise(is_err(args)),
+        "unwrap" => |args| raise(unwrap(args)),
+        "unwrap_or" => |args| raise(unwrap_or(args)),
+        "parse_int" => |args| fallible(parse_int(args)),
+        "json_parse" => |args| fallible(json_parse(args)),
+        "json_stringify" => |args| raise(json_stringify(args)),
+        "len" => |args| raise(len(args)),
+        "upper" => |args| raise(upper(args)),
+        "lower" => |args| raise(lower(args)),
+        "split" => |args| raise(split(args)),
+        "join" => |args| raise(join(args)),
+        "trim" => |args| raise(trim(args)),
+        "starts_with" => |args| raise(starts_with(args)),
+        "ends_with" => |args| raise(ends_with(args)),
+        "contains" => |args| raise(contains(args)),
+        "replace" => |args| raise(replace(args)),
+        "find" => |args| raise(find(args)),
+        "substring" => |args| raise(substring(args)),
+        "chars" => |args| raise(chars(args)),
+        "bytes" => |args| raise(bytes(args)),
+        "repeat" => |args| raise(repeat(args)),
+        "format" => |args| raise(format(args)),
+        "iter" => |args| raise(iter(args)),
+        "next" => |args| raise(next(args)),
+        "map" => |args| raise(map(args)),
+        "filter" => |args| raise(filter(args)),
+        "take" => |args| raise(take(args)),
+        "zip" => |args| raise(zip(args)),
+        "enumerate" => |args| raise(enumerate(args)),
+        "chain" => |args| raise(chain(args)),
+        "collect" => |args| raise(collect(args)),
+        "sort" => |args| raise(sort(args)),
+        "sort_by" => |args| raise(sort_by(args)),
+        "math.abs" => |args| raise(math::abs(args)),
+        "math.min" => |args| raise(math::min(args)),
+        "math.max" => |args| raise(math::max(args)),
+        "math.clamp" => |args| raise(math::clamp(args)),
+        "math.pow" => |args| raise(math::pow(args)),
+        "math.sqrt" => |args| raise(math::sqrt(args)),
+        "math.floor" => |args| raise(math::floor(args)),
+        "math.ceil" => |args| raise(math::ceil(args)),
+        "math.round" => |args| raise(math::round(args)),
+        "math.gcd" => |args| raise(math::gcd(args)),
+        "math.lcm" => |args| raise(math::lcm(args)),
+        "math.sin" => |args| raise(math::sin(args)),
+        "math.cos" => |args| raise(math::cos(args)),
+        "math.tan" => |args| raise(math::tan(args)),
+        "math.asin" => |args| raise(math::asin(args)),
+        "math.acos" => |args| raise(math::acos(args)),
+        "math.atan" => |args| raise(math::atan(args)),
+        "math.atan2" => |args| raise(math::atan2(args)),
+        "math.exp" => |args| raise(math::exp(args)),
+        "math.log" => |args| raise(math::log(args)),
+        "math.log2" => |args| raise(math::log2(args)),
+        "math.log10" => |args| raise(math::log10(args)),
+        "bigint" => |args| raise(bigint::bigint(args)),
+        "random" => |args| raise(random::random(args)),
+        "random_int" => |args| raise(random::random_int(args)),
+        "shuffle" => |args| raise(random::shuffle(args)),
+        "choice" => |args| raise(random::choice(args)),
+        "freeze" => |args| raise(freeze(args)),
+        "is_frozen" => |args| raise(is_frozen(args)),
+        _ => return None,
+    };
+
+    Some(function)
+}
+
+/// Turns the outcome of a builtin into an Object, raising failures as runtime
+/// errors.
+fn raise(result: Result<Object, RuntimeError>) -> Object {
+    match result {
+        Ok(object) => object,
+        Err(e) => Object::Error { value: Box::new(e) },
+    }
+}
+
+/// Turns the outcome of a builtin into a result value, so that failures can be
+/// handled by the script. Errors which can't be caught are still raised.
+fn fallible(result: Result<Object, RuntimeError>) -> Object {
+    match result {
+        Ok(object) => Object::Result {
+            value: Ok(Box::new(object)),
+        },
+        Err(e) if !e.kind.is_catchable() => e.into(),
+        Err(e) => Object::Result {
+            value: Err(Box::new(e.to_object())),
+        },
+    }
+}
+
+pub(crate) fn check_arity(args: &[Object], want: usize) -> Result<(), RuntimeError> {
+    if args.len() != want {
+        return Err(RuntimeError::new(
+            ErrorKind::WrongArgumentCount,
+            format!(
+                "wrong number of arguments: want={}, got={}",
+                want,
+                args.len()
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn invalid_argument(name: &str, want: &str, got: &Object) -> RuntimeError {
+    RuntimeError::new(
+        ErrorKind::InvalidArgument,
+        format!(
+            "argument to `{}` must be {}, got {}",
+            name,
+            want,
+            got.name()
+        ),
+    )
+}
+
+fn ok(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    Ok(Object::Result {
+        value: Ok(Box::new(args[0].clone())),
+    })
+}
+
+fn err(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    Ok(Object::Result {
+        value: Err(Box::new(args[0].clone())),
+    })
+}
+
+fn is_ok(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    match &args[0] {
+        Object::Result { value } => Ok(Object::Boolean {
+            value: value.is_ok(),
+        }),
+        other => Err(invalid_argument("is_ok", "RESULT", other)),
+    }
+}
+
+fn is_err(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    match &args[0] {
+        Object::Result { value } => Ok(Object::Boolean {
+            value: value.is_err(),
+        }),
+        other => Err(invalid_argument("is_err", "RESULT", other)),
+    }
+}
+
+fn unwrap(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    match &args[0] {
+        Object::Result { value: Ok(v) } => Ok(*v.clone()),
+        // Unwrapping an err raises its payload, as if it had been thrown.
+        Object::Result { value: Err(e) } => Err(RuntimeError::thrown(*e.clone())),
+        other => Err(invalid_argument("unwrap", "RESULT", other)),
+    }
+}
+
+fn unwrap_or(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    match &args[0] {
+        Object::Result { value: Ok(v) } => Ok(*v.clone()),
+        Object::Result { value: Err(_) } => Ok(args[1].clone()),
+        other => Err(invalid_argument("unwrap_or", "RESULT", other)),
+    }
+}
+
+fn len(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    let length = match &args[0] {
+        Object::String { value } => value.chars().count(),
+        Object::Array { elements, .. } => elements.len(),
+        Object::Hash { pairs, .. } => pairs.len(),
+        other => return Err(invalid_argument("len", "STRING, ARRAY or HASH", other)),
+    };
+
+    Ok(Object::Integer {
+        value: length as i64,
+    })
+}
+
+fn upper(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    match &args[0] {
+        Object::String { value } => Ok(Object::String {
+            value: value.to_uppercase(),
+        }),
+        other => Err(invalid_argument("upper", "STRING", other)),
+    }
+}
+
+fn lower(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    match &args[0] {
+        Object::String { value } => Ok(Object::String {
+            value: value.to_lowercase(),
+        }),
+        other => Err(invalid_argument("lower", "STRING", other)),
+    }
+}
+
+fn string<'a>(name: &str, object: &'a Object) -> Result<&'a str, RuntimeError> {
+    match object {
+        Object::String { value } => Ok(value),
+        other => Err(invalid_argument(name, "STRING", other)),
+    }
+}
+
+/// Reserves the memory held by an array of `count` elements, whose values
+/// hold `bytes` more between them.
+fn reserve_array(count: usize, bytes: usize) -> Result<(), RuntimeError> {
+    reserve_memory(
+        count
+            .saturating_mul(mem::size_of::<Object>())
+            .saturating_add(bytes),
+    )
+}
+
+fn strings(values: impl IntoIterator<Item = String>) -> Object {
+    Object::Array {
+        elements: values
+            .into_iter()
+            .map(|value| Object::String { value })
+            .collect(),
+        frozen: false,
+    }
+}
+
+/// Splits a string around a separator. An empty separator splits it into its
+/// characters.
+fn split(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    let value = string("split", &args[0])?;
+    match string("split", &args[1])? {
+        "" => {
+            reserve_array(value.chars().count(), value.len())?;
+            Ok(strings(value.chars().map(String::from)))
+        }
+        separator => {
+            reserve_array(value.matches(separator).count() + 1, value.len())?;
+            Ok(strings(value.split(separator).map(String::from)))
+        }
+    }
+}
+
+fn join(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    let separator = string("join", &args[1])?;
+
+    let mut parts = vec![];
+    let mut bytes = 0;
+    for item in sequence("join", &args[0])? {
+        match item {
+            Object::String { value } => {
+                if !parts.is_empty() {
+                    bytes += separator.len();
+                }
+
+                bytes += value.len();
+                reserve_memory(bytes)?;
+                parts.push(value);
+            }
+            Object::Error { value } => return Err(*value),
+            other => return Err(invalid_argument("join", "a sequence of STRING", &other)),
+        }
+    }
+
+    Ok(Object::String {
+        value: parts.join(separator),
+    })
+}
+
+fn trim(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    Ok(Object::String {
+        value: string("trim", &args[0])?.trim().to_string(),
+    })
+}
+
+fn starts_with(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    Ok(Object::Boolean {
+        value: string("starts_with", &args[0])?.starts_with(string("starts_with", &args[1])?),
+    })
+}
+
+fn ends_with(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    Ok(Object::Boolean {
+        value: string("ends_with", &args[0])?.ends_with(string("ends_with", &args[1])?),
+    })
+}
+
+/// Looks for a substring in a string, or for an item in any other sequence.
+/// Items are compared like `==` does.
+fn contains(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    if let Object::String { value } = &args[0] {
+        return Ok(Object::Boolean {
+            value: value.contains(string("contains", &args[1])?),
+        });
+    }
+
+    let needle = args[1].clone().freeze();
+    for item in sequence("contains", &args[0])? {
+        if let Object::Error { value } = item {
+            return Err(*value);
+        }
+
+        if item.freeze() == needle {
+            return Ok(Object::Boolean { value: true });
+        }
+    }
+
+    Ok(Object::Boolean { value: false })
+}
+
+fn replace(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 3)?;
+    let value = string("replace", &args[0])?;
+    let (from, to) = (string("replace", &args[1])?, string("replace", &args[2])?);
+
+    // An empty pattern matches between every character.
+    let matches = match from {
+        "" => value.chars().count() + 1,
+        from => value.matches(from).count(),
+    };
+    reserve_memory(value.len().saturating_add(matches.saturating_mul(to.len())))?;
+
+    Ok(Object::String {
+        value: value.replace(from, to),
+    })
+}
+
+/// Returns the position of the first occurrence of a substring in a string,
+/// counted in characters, or the first item of any other sequence for which
+/// a function holds. Returns null if there is none.
+fn find(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    if let Object::String { value } = &args[0] {
+        let position = value
+            .find(string("find", &args[1])?)
+            .map_or(Object::Null, |i| Object::Integer {
+                value: value[..i].chars().count() as i64,
+            });
+
+        return Ok(position);
+    }
+
+    let mut found = Sequence::Filter {
+        source: Box::new(sequence("find", &args[0])?),
+        function: callable("find", &args[1])?,
+    };
+
+    match found.next() {
+        Some(Object::Error { value }) => Err(*value),
+        Some(item) => Ok(item),
+        None => Ok(Object::Null),
+    }
+}
+
+/// Returns the characters of a string from `start` up to, but not including,
+/// `end`, which defaults to the end of the string.
+fn substring(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    if !(2..=3).contains(&args.len()) {
+        return Err(RuntimeError::new(
+            ErrorKind::WrongArgumentCount,
+            format!("wrong number of arguments: want=2..=3, got={}", args.len()),
+        ));
+    }
+
+    let value = string("substring", &args[0])?;
+    let length = value.chars().count() as i64;
+    let bound = |object: &Object| match object {
+        Object::Integer { value } => Ok(*value),
+        other => Err(invalid_argument("substring", "INTEGER", other)),
+    };
+
+    let start = bound(&args[1])?;
+    let end = args.get(2).map_or(Ok(length), bound)?;
+    if start < 0 || end < start || end > length {
+        return Err(RuntimeError::new(
+            ErrorKind::IndexOutOfRange,
+            format!(
+                "substring range {}..{} out of bounds for STRING of length {}",
+                start, end, length
+            ),
+        ));
+    }
+
+    Ok(Object::String {
+        value: value
+            .chars()
+            .skip(start as usize)
+            .take((end - start) as usize)
+            .collect(),
+    })
+}
+
+fn chars(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    let value = string("chars", &args[0])?;
+    reserve_array(value.chars().count(), value.len())?;
+    Ok(strings(value.chars().map(String::from)))
+}
+
+fn bytes(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    let value = string("bytes", &args[0])?;
+    reserve_array(value.len(), 0)?;
+    Ok(Object::Array {
+        elements: value
+            .bytes()
+            .map(|b| Object::Integer { value: b as i64 })
+            .collect(),
+        frozen: false,
+    })
+}
+
+fn repeat(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    let value = string("repeat", &args[0])?;
+    match &args[1] {
+        Object::Integer { value: count } if *count >= 0 => {
+            reserve_memory(value.len().saturating_mul(*count as usize))?;
+            Ok(Object::String {
+                value: value.repeat(*count as usize),
+            })
+        }
+        other => Err(invalid_argument("repeat", "a positive INTEGER", other)),
+    }
+}
+
+/// Replaces every `{}` in a string with the next argument. `{{` and `}}`
+/// stand for literal braces.
+fn format(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    let template = match args.first() {
+        Some(template) => string("format", template)?,
+        None => {
+            return Err(RuntimeError::new(
+                ErrorKind::WrongArgumentCount,
+                "wrong number of arguments: want>=1, got=0".to_string(),
+            ))
+        }
+    };
+
+    let mut values = args[1..].iter();
+    let mut formatted = String::new();
+    let mut chars = template.chars().peekable();
+    let mut placeholders = 0;
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                formatted.push(c);
+                chars.next();
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                placeholders += 1;
+                if let Some(value) = values.next() {
+                    let value = value.inspect();
+                    reserve_memory(formatted.len() + value.len())?;
+                    formatted.push_str(&value);
+                }
+            }
+            _ => formatted.push(c),
+        }
+    }
+
+    if placeholders != args.len() - 1 {
+        return Err(RuntimeError::new(
+            ErrorKind::WrongArgumentCount,
+            format!(
+                "format string has {} placeholders, got {} arguments",
+                placeholders,
+                args.len() - 1
+            ),
+        ));
+    }
+
+    Ok(Object::String { value: formatted })
+}
+
+fn freeze(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    Ok(args.into_iter().next().map_or(Object::Null, Object::freeze))
+}
+
+fn is_frozen(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    Ok(Object::Boolean {
+        value: args[0].is_frozen(),
+    })
+}
+
+fn parse_int(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    match &args[0] {
+        Object::String { value } => match value.trim().parse() {
+            Ok(value) => Ok(Object::Integer { value }),
+            Err(_) => Err(RuntimeError::new(
+                ErrorKind::InvalidArgument,
+                format!("could not parse \"{}\" as an integer", value),
+            )),
+        },
+        other => Err(invalid_argument("parse_int", "STRING", other)),
+    }
+}
+
+fn json_parse(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    json::parse(string("json_parse", &args[0])?)
+}
+
+/// Serializes a value to JSON, on a single line unless it is given how many
+/// spaces to indent nested values by.
+fn json_stringify(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    if !(1..=2).contains(&args.len()) {
+        return Err(RuntimeError::new(
+            ErrorKind::WrongArgumentCount,
+            format!("wrong number of arguments: want=1..=2, got={}", args.len()),
+        ));
+    }
+
+    let indent = match args.get(1) {
+        None => 0,
+        Some(Object::Integer { value }) if *value >= 0 => *value as usize,
+        Some(other) => {
+            return Err(invalid_argument(
+                "json_stringify",
+                "a non-negative INTEGER",
+                other,
+            ))
+        }
+    };
+
+    Ok(Object::String {
+        value: json::stringify(&args[0], indent)?,
+    })
+}
+
+fn sequence(name: &str, object: &Object) -> Result<Sequence, RuntimeError> {
+    Sequence::from_object(object).ok_or_else(|| invalid_argument(name, "iterable", object))
+}
+
+fn callable(name: &str, object: &Object) -> Result<Box<Object>, RuntimeError> {
+    match object {
+        Object::Function { .. } | Object::Builtin { .. } | Object::Constructor { .. } => {
+            Ok(Box::new(object.clone()))
+        }
+        other => Err(invalid_argument(name, "FUNCTION", other)),
+    }
+}
+
+fn iter(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    match &args[0] {
+        Object::Iterator { .. } => Ok(args[0].clone()),
+        other => Ok(new_iterator(sequence("iter", other)?)),
+    }
+}
+
+/// Advances an iterator, returning null once it is exhausted.
+fn next(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    match &args[0] {
+        Object::Iterator { .. } => match sequence("next", &args[0])?.next() {
+            Some(Object::Error { value }) => Err(*value),
+            Some(item) => Ok(item),
+            None => Ok(Object::Null),
+        },
+        other => Err(invalid_argument("next", "ITERATOR", other)),
+    }
+}
+
+fn map(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    Ok(new_iterator(Sequence::Map {
+        source: Box::new(sequence("map", &args[0])?),
+        function: callable("map", &args[1])?,
+    }))
+}
+
+fn filter(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    Ok(new_iterator(Sequence::Filter {
+        source: Box::new(sequence("filter", &args[0])?),
+        function: callable("filter", &args[1])?,
+    }))
+}
+
+fn take(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    let remaining = match &args[1] {
+        Object::Integer { value } if *value >= 0 => *value as usize,
+        other => return Err(invalid_argument("take", "a positive INTEGER", other)),
+    };
+
+    Ok(new_iterator(Sequence::Take {
+        source: Box::new(sequence("take", &args[0])?),
+        remaining,
+    }))
+}
+
+fn zip(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    Ok(new_iterator(Sequence::Zip {
+        left: Box::new(sequence("zip", &args[0])?),
+        right: Box::new(sequence("zip", &args[1])?),
+    }))
+}
+
+fn enumerate(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    Ok(new_iterator(Sequence::Enumerate {
+        source: Box::new(sequence("enumerate", &args[0])?),
+        index: 0,
+    }))
+}
+
+fn chain(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    Ok(new_iterator(Sequence::Chain {
+        first: Box::new(sequence("chain", &args[0])?),
+        second: Box::new(sequence("chain", &args[1])?),
+    }))
+}
+
+fn collect(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    let mut elements = vec![];
+    let mut bytes = 0;
+    for item in sequence("collect", &args[0])? {
+        if is_unwinding(&item) {
+            return Ok(item);
+        }
+
+        bytes += item.footprint().bytes;
+        reserve_memory(bytes)?;
+        elements.push(item);
+    }
+
+    Ok(Object::Array {
+        elements,
+        frozen: false,
+    })
+}
+
+/// Sorts the items of a sequence in ascending order.
+fn sort(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    sorted("sort", &args[0], None)
+}
+
+/// Sorts the items of a sequence by the keys a function returns for them.
+fn sort_by(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    sorted("sort_by", &args[0], Some(callable("sort_by", &args[1])?))
+}
+
+/// Collects the items of a sequence into an array sorted by their keys, or
+/// by the items themselves if there is no key function.
+fn sorted(name: &str, items: &Object, key: Option<Box<Object>>) -> Result<Object, RuntimeError> {
+    let mut pairs = vec![];
+    let mut bytes = 0;
+    for item in sequence(name, items)? {
+        if is_unwinding(&item) {
+            return Ok(item);
+        }
+
+        let k = match &key {
+            Some(key) => apply_function(*key.clone(), vec![item.clone()]),
+            None => item.clone(),
+        };
+
+        if is_unwinding(&k) {
+            return Ok(k);
+        }
+
+        bytes += item.footprint().bytes + k.footprint().bytes;
+        reserve_memory(bytes)?;
+        pairs.push((k, item));
+    }
+
+    Ok(Object::Array {
+        elements: merge_sort(pairs)?
+            .into_iter()
+            .map(|(_, item)| item)
+            .collect(),
+        frozen: false,
+    })
+}
+
+/// Sorts pairs of keys and items by their keys, which are compared with `<`.
+/// Items with equal keys keep their order. Unlike `slice::sort_by`, this can
+/// stop at the first comparison which raises an error, and doesn't rely on
+/// `<` being a total order, which it isn't with NaN.
+fn merge_sort(mut pairs: Vec<(Object, Object)>) -> Result<Vec<(Object, Object)>, RuntimeError> {
+    if pairs.len() <= 1 {
+        return Ok(pairs);
+    }
+
+    let right = merge_sort(pairs.split_off(pairs.len() / 2))?;
+    let left = merge_sort(pairs)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        // Taking from the left unless the right is smaller keeps the order of
+        // equal keys.
+        let next = match eval_infix_expression("<".to_string(), r.0.clone(), l.0.clone()) {
+            Object::Error { value } => return Err(*value),
+            Object::Boolean { value: true } => right.next(),
+            _ => left.next(),
+        };
+
+        merged.extend(next);
+    }
+
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
@@ -1,14 +1,25 @@
+use super::error::{ErrorKind, RuntimeError};
+use super::memory::{self, Footprint};
 use super::Object;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// The language environment which keeps hold of variable bindings. The evaluator
 /// may use this to store or fetch any bindings it needs. Additionally, an
 /// Environment can embed another Environment, which gives us the capability to
 /// add closures to our programming language.
-#[derive(Clone, Debug, PartialEq, Eq)]
+///
+/// Every Environment reports the memory held by its own bindings to the memory
+/// tracker when it is created, cloned, updated or dropped, so that the usage
+/// of a script can be capped.
+///
+/// Bindings made with `const` are remembered, and can't be replaced in the
+/// Environment which holds them.
+#[derive(Debug, PartialEq)]
 pub struct Environment {
     pub storage: HashMap<String, Object>,
     pub outer: Option<Box<Environment>>,
+    constants: HashSet<String>,
+    footprint: Footprint,
 }
 
 impl Default for Environment {
@@ -17,12 +28,32 @@ impl Default for Environment {
     }
 }
 
+impl Clone for Environment {
+    fn clone(&self) -> Self {
+        memory::allocate(self.footprint);
+        Self {
+            storage: self.storage.clone(),
+            outer: self.outer.clone(),
+            constants: self.constants.clone(),
+            footprint: self.footprint,
+        }
+    }
+}
+
+impl Drop for Environment {
+    fn drop(&mut self) {
+        memory::release(self.footprint);
+    }
+}
+
 impl Environment {
     /// Creates a new, blank Environment.
     pub fn new() -> Self {
         Self {
             storage: HashMap::new(),
             outer: None,
+            constants: HashSet::new(),
+            footprint: Footprint::default(),
         }
     }
 
@@ -32,16 +63,24 @@ impl Environment {
         Self {
             storage: HashMap::new(),
             outer: Some(Box::new(env)),
+            constants: HashSet::new(),
+            footprint: Footprint::default(),
         }
     }
 
+    /// Ends the scope of an Environment created with `new_enclosed`, and
+    /// returns the Environment it embeds. Bindings made in the scope go away.
+    pub fn close(mut self) -> Environment {
+        *self.outer.take().unwrap_or_default()
+    }
+
     /// Fetch an Object from the Environment.
     pub fn get(&self, k: &str) -> Option<Object> {
         match self.storage.get(k) {
             Some(v) => Some(v.clone()),
             None => {
-                if self.outer.is_some() {
-                    return self.outer.as_ref().unwrap().get(k);
+                if let Some(outer) = &self.outer {
+                    return outer.get(k);
                 }
 
                 None
@@ -49,8 +88,99 @@ impl Environment {
         }
     }
 
-    /// Insert an Object into the Environment.
-    pub fn set(&mut self, k: String, v: Object) -> Option<Object> {
-        self.storage.insert(k, v)
+    /// Insert an Object into the Environment. Returns an error if the name is
+    /// bound to a constant in this Environment.
+    pub fn set(&mut self, k: String, v: Object) -> Result<Option<Object>, RuntimeError> {
+        if self.constants.contains(&k) {
+            return Err(RuntimeError::new(
+                ErrorKind::ConstantReassignment,
+                format!("cannot reassign constant {}", k),
+            ));
+        }
+
+        Ok(self.insert(k, v))
+    }
+
+    /// Replace the Object bound to a name in whichever Environment holds it,
+    /// or insert it into this Environment if none does. Returns an error if
+    /// the name is bound to a constant.
+    pub fn assign(&mut self, k: String, v: Object) -> Result<Option<Object>, RuntimeError> {
+        if !self.storage.contains_key(&k) {
+            if let Some(outer) = self.outer.as_mut().filter(|o| o.holds(&k)) {
+                return outer.assign(k, v);
+            }
+        }
+
+        self.set(k, v)
     }
+
+    /// Insert an Object into the Environment as a constant, which can't be
+    /// reassigned or shadowed in this Environment afterwards.
+    pub fn set_constant(&mut self, k: String, v: Object) -> Result<(), RuntimeError> {
+        if self.storage.contains_key(&k) {
+            let message = if self.constants.contains(&k) {
+                format!("cannot reassign constant {}", k)
+            } else {
+                format!("cannot redeclare {} as a constant", k)
+            };
+
+            return Err(RuntimeError::new(ErrorKind::ConstantReassignment, message));
+        }
+
+        self.constants.insert(k.clone());
+        self.insert(k, v);
+        Ok(())
+    }
+
+    /// Returns true if the name is bound to a constant in this Environment or
+    /// any of the environments it embeds.
+    pub fn is_constant(&self, k: &str) -> bool {
+        if self.storage.contains_key(k) {
+            return self.constants.contains(k);
+        }
+
+        self.outer.as_ref().is_some_and(|o| o.is_constant(k))
+    }
+
+    fn holds(&self, k: &str) -> bool {
+        self.storage.contains_key(k) || self.outer.as_ref().is_some_and(|o| o.holds(k))
+    }
+
+    fn insert(&mut self, k: String, v: Object) -> Option<Object> {
+        let added = binding_footprint(&k, &v);
+        memory::allocate(added);
+        self.footprint += added;
+
+        let previous = self.storage.insert(k.clone(), v);
+        if let Some(p) = &previous {
+            let removed = binding_footprint(&k, p);
+            memory::release(removed);
+            self.footprint.objects -= removed.objects;
+            self.footprint.bytes -= removed.bytes;
+        }
+
+        previous
+    }
+
+    /// Remove an Object from the Environment. Bindings held by outer
+    /// environments and constants are left untouched.
+    pub fn remove(&mut self, k: &str) -> Option<Object> {
+        if self.constants.contains(k) {
+            return None;
+        }
+
+        let previous = self.storage.remove(k);
+        if let Some(p) = &previous {
+            let removed = binding_footprint(k, p);
+            memory::release(removed);
+            self.footprint.objects -= removed.objects;
+            self.footprint.bytes -= removed.bytes;
+        }
+
+        previous
+    }
+}
+
+fn binding_footprint(k: &str, v: &Object) -> Footprint {
+    Footprint::new(0, k.len()) + v.footprint()
 }
@@ -0,0 +1,213 @@
+use super::object::{HashKey, Object};
+use crate::tokens::Span;
+use std::collections::BTreeMap;
+
+/// All the kinds of errors that can be raised while evaluating a program.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ErrorKind {
+    TypeMismatch,
+    UnknownOperator,
+    UnknownIdentifier,
+    NotCallable,
+    WrongArgumentCount,
+    InvalidArgument,
+    UnusableHashKey,
+    NotIterable,
+    Thrown,
+    InvalidYield,
+    NoMatch,
+    PatternMismatch,
+    ConstantReassignment,
+    FrozenMutation,
+    IndexOutOfRange,
+    DomainError,
+    Overflow,
+    DivisionByZero,
+    UnknownField,
+    ImportError,
+    CircularImport,
+    MemoryLimitExceeded,
+}
+
+impl ErrorKind {
+    /// Returns the name of an ErrorKind, as shown to scripts.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::TypeMismatch => "type_mismatch",
+            ErrorKind::UnknownOperator => "unknown_operator",
+            ErrorKind::UnknownIdentifier => "unknown_identifier",
+            ErrorKind::NotCallable => "not_callable",
+            ErrorKind::WrongArgumentCount => "wrong_argument_count",
+            ErrorKind::InvalidArgument => "invalid_argument",
+            ErrorKind::UnusableHashKey => "unusable_hash_key",
+            ErrorKind::NotIterable => "not_iterable",
+            ErrorKind::Thrown => "thrown",
+            ErrorKind::InvalidYield => "invalid_yield",
+            ErrorKind::NoMatch => "no_match",
+            ErrorKind::PatternMismatch => "pattern_mismatch",
+            ErrorKind::ConstantReassignment => "constant_reassignment",
+            ErrorKind::FrozenMutation => "frozen_mutation",
+            ErrorKind::IndexOutOfRange => "index_out_of_range",
+            ErrorKind::DomainError => "domain_error",
+            ErrorKind::Overflow => "overflow",
+            ErrorKind::DivisionByZero => "division_by_zero",
+            ErrorKind::UnknownField => "unknown_field",
+            ErrorKind::ImportError => "import_error",
+            ErrorKind::CircularImport => "circular_import",
+            ErrorKind::MemoryLimitExceeded => "memory_limit_exceeded",
+        }
+    }
+
+    /// Returns true if scripts may recover from this kind of error with a
+    /// `try`/`catch` expression. Exceeding the memory limit always aborts.
+    pub fn is_catchable(&self) -> bool {
+        !matches!(self, ErrorKind::MemoryLimitExceeded)
+    }
+}
+
+/// A function call which was in progress when an error was raised. The span
+/// points at the call site, in the given file if it is in an imported module
+/// rather than in the script itself. Generators push a frame without a span
+/// when an error leaves them, which the builtin that resumed them fills in.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Frame {
+    pub function: Option<String>,
+    pub span: Option<Span>,
+    pub file: Option<String>,
+}
+
+/// An error raised while evaluating a program. Frames are pushed while the
+/// error unwinds, so the innermost call comes first. Like the spans of
+/// frames, the span of the error may point into an imported file. Errors
+/// raised with `throw` keep hold of the thrown value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuntimeError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub span: Option<Span>,
+    pub file: Option<String>,
+    pub frames: Vec<Frame>,
+    pub value: Option<Box<Object>>,
+}
+
+impl RuntimeError {
+    /// Creates a new RuntimeError, without any location information.
+    pub fn new(kind: ErrorKind, message: String) -> Self {
+        Self {
+            kind,
+            message,
+            span: None,
+            file: None,
+            frames: vec![],
+            value: None,
+        }
+    }
+
+    /// Creates a new RuntimeError for a value raised with `throw`.
+    pub fn thrown(value: Object) -> Self {
+        let message = match &value {
+            Object::String { value } => value.clone(),
+            _ => value.inspect(),
+        };
+
+        Self {
+            value: Some(Box::new(value)),
+            ..Self::new(ErrorKind::Thrown, message)
+        }
+    }
+
+    /// Returns the Object exposed to scripts when an error is caught: a hash
+    /// holding the error's kind and message, and the thrown value if any.
+    pub fn to_object(&self) -> Object {
+        let mut pairs = BTreeMap::new();
+        let mut insert = |k: &str, v: Object| {
+            pairs.insert(
+                HashKey::String {
+                    value: k.to_string(),
+                },
+                v,
+            );
+        };
+
+        insert(
+            "kind",
+            Object::String {
+                value: self.kind.name().to_string(),
+            },
+        );
+        insert(
+            "message",
+            Object::String {
+                value: self.message.clone(),
+            },
+        );
+        insert(
+            "value",
+            self.value.as_ref().map_or(Object::Null, |v| *v.clone()),
+        );
+
+        Object::Hash {
+            pairs,
+            frozen: false,
+        }
+    }
+
+    /// Records that the error left an imported file, which the spans that
+    /// don't have a file yet point into. The import is pushed as a frame of
+    /// its own, whose span points at the import statement.
+    pub fn leave_module(&mut self, path: &str, span: Span) {
+        if self.span.is_some() && self.file.is_none() {
+            self.file = Some(path.to_string());
+        }
+
+        for frame in self.frames.iter_mut().filter(|f| f.file.is_none()) {
+            frame.file = Some(path.to_string());
+        }
+
+        self.frames.push(Frame {
+            function: Some(String::from("<module>")),
+            span: Some(span),
+            file: None,
+        });
+    }
+
+    /// Renders the error as a traceback, with the outermost call first.
+    pub fn traceback(&self) -> String {
+        let mut lines = vec![];
+        let mut caller = String::from("<script>");
+        let location = |span: &Span, file: &Option<String>| match file {
+            Some(file) => format!("{}, {}", file, span),
+            None => span.to_string(),
+        };
+
+        for frame in self.frames.iter().rev() {
+            if let Some(span) = &frame.span {
+                lines.push(format!("  {}, in {}", location(span, &frame.file), caller));
+            }
+
+            caller = frame
+                .function
+                .clone()
+                .unwrap_or_else(|| String::from("<anonymous>"));
+        }
+
+        if let Some(span) = self.span {
+            lines.push(format!("  {}, in {}", location(&span, &self.file), caller));
+        }
+
+        if !lines.is_empty() {
+            lines.insert(0, String::from("Traceback (most recent call last):"));
+        }
+
+        lines.push(format!("ERROR: {}", self.message));
+        lines.join("\n")
+    }
+}
+
+impl From<RuntimeError> for Object {
+    fn from(error: RuntimeError) -> Self {
+        Object::Error {
+            value: Box::new(error),
+        }
+    }
+}
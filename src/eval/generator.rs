@@ -0,0 +1,477 @@
+use super::error::{ErrorKind, Frame};
+use super::iterator::Sequence;
+use super::object::Object;
+use super::{check_memory_limit, eval, is_truthy, is_unwinding, Environment};
+use crate::ast::Node;
+
+/// The state of a call to a generator function, i.e. a function whose body
+/// contains `yield`. Generators are run one statement at a time, so that they
+/// can be suspended at every `yield` and resumed later on.
+///
+/// `eval` keeps its progress on the Rust stack and can't be suspended, so a
+/// generator keeps its own stack of the blocks, loops and `try` expressions it
+/// is in, and hands every other statement over to `eval`. As a result, `yield`
+/// can only be used as a statement, possibly nested in blocks, `if`, `for` and
+/// `try`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Generator {
+    name: Option<String>,
+    env: Environment,
+    frames: Vec<Resume>,
+}
+
+/// A construct the generator is in the middle of.
+#[derive(Clone, Debug, PartialEq)]
+enum Resume {
+    Block {
+        statements: Vec<Node>,
+        index: usize,
+    },
+    Loop {
+        names: Vec<String>,
+        shadowed: Vec<Option<Object>>,
+        iterable: Object,
+        sequence: Sequence,
+        position: i64,
+        body: Node,
+    },
+    Try {
+        parameter: Option<Box<Node>>,
+        handler: Option<Box<Node>>,
+        finalizer: Option<Box<Node>>,
+    },
+    /// Runs once the handler of a try expression is done, and puts back
+    /// whatever its parameter shadowed.
+    Catch {
+        name: String,
+        shadowed: Option<Object>,
+    },
+    /// Runs once a finally block is done. The pending error or return value,
+    /// if any, carries on unwinding from there.
+    Finally {
+        pending: Option<Object>,
+    },
+}
+
+impl Generator {
+    /// Creates a Generator which runs the body of the named function in the
+    /// given environment. Nothing is evaluated until the Generator is resumed.
+    pub fn new(name: Option<String>, body: Node, env: Environment) -> Self {
+        let mut generator = Self {
+            name,
+            env,
+            frames: vec![],
+        };
+
+        generator.enter(body);
+        generator
+    }
+
+    /// Runs the Generator until it yields a value, and returns that value.
+    /// Returns None once the Generator has returned. An error is returned as
+    /// a value, after which the Generator is finished.
+    pub fn resume(&mut self) -> Option<Object> {
+        while let Some(frame) = self.frames.last_mut() {
+            let outcome = match frame {
+                Resume::Block { statements, index } => match statements.get(*index).cloned() {
+                    Some(statement) => {
+                        *index += 1;
+                        self.execute(statement)
+                    }
+                    None => {
+                        self.frames.pop();
+                        None
+                    }
+                },
+                Resume::Loop {
+                    names,
+                    iterable,
+                    sequence,
+                    position,
+                    body,
+                    ..
+                } => match sequence.next() {
+                    Some(item) if is_unwinding(&item) => Some(item),
+                    Some(item) => {
+                        let bindings = loop_bindings(names, iterable, *position, item);
+                        let body = body.clone();
+                        *position += 1;
+
+                        // Every iteration binds the loop variables anew, like
+                        // in regular for loops.
+                        match bindings
+                            .into_iter()
+                            .try_for_each(|(name, value)| self.env.set(name, value).map(drop))
+                        {
+                            Ok(()) => {
+                                self.enter(body);
+                                None
+                            }
+                            Err(e) => Some(e.into()),
+                        }
+                    }
+                    None => {
+                        if let Some(Resume::Loop {
+                            names, shadowed, ..
+                        }) = self.frames.pop()
+                        {
+                            self.restore(names, shadowed);
+                        }
+
+                        None
+                    }
+                },
+                // The body of the try expression is done without raising an
+                // error, so only the finally block is left to run.
+                Resume::Try { finalizer, .. } => {
+                    let finalizer = finalizer.take();
+                    self.frames.pop();
+
+                    if let Some(finalizer) = finalizer {
+                        self.frames.push(Resume::Finally { pending: None });
+                        self.enter(*finalizer);
+                    }
+
+                    None
+                }
+                Resume::Finally { pending } => {
+                    let pending = pending.take();
+                    self.frames.pop();
+                    pending
+                }
+                Resume::Catch { .. } => {
+                    if let Some(Resume::Catch { name, shadowed }) = self.frames.pop() {
+                        self.restore(vec![name], vec![shadowed]);
+                    }
+
+                    None
+                }
+            };
+
+            match outcome {
+                Some(object) if is_unwinding(&object) => {
+                    if let Some(e) = self.unwind(object) {
+                        return Some(self.leave(e));
+                    }
+                }
+                Some(object) => return Some(object),
+                None => {}
+            }
+        }
+
+        None
+    }
+
+    /// Executes a single statement. Returns the yielded value, or an error or
+    /// return value which has to unwind the Generator, if any.
+    fn execute(&mut self, statement: Node) -> Option<Object> {
+        if !contains_yield(&statement) {
+            let evaluated = eval(statement, &mut self.env);
+            if is_unwinding(&evaluated) {
+                return Some(evaluated);
+            }
+
+            return check_memory_limit();
+        }
+
+        match statement {
+            Node::YieldStatement { value, .. } => Some(eval(*value, &mut self.env)),
+            Node::ExpressionStatement {
+                expression: Some(expression),
+            } => self.execute(*expression),
+            Node::BlockStatement { .. } => {
+                self.enter(statement);
+                None
+            }
+            Node::IfExpression {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                let condition = eval(*condition, &mut self.env);
+                if is_unwinding(&condition) {
+                    return Some(condition);
+                }
+
+                if is_truthy(condition) {
+                    self.enter(*consequence);
+                } else if let Some(alternative) = alternative {
+                    self.enter(*alternative);
+                }
+
+                None
+            }
+            Node::ForStatement {
+                variables,
+                iterable,
+                body,
+            } => {
+                let iterable = eval(*iterable, &mut self.env);
+                if is_unwinding(&iterable) {
+                    return Some(iterable);
+                }
+
+                let sequence = match Sequence::from_object(&iterable) {
+                    Some(sequence) => sequence,
+                    None => {
+                        return Some(Object::error(
+                            ErrorKind::NotIterable,
+                            format!("cannot iterate over {}", iterable.name()),
+                        ))
+                    }
+                };
+
+                let names: Vec<String> = variables.iter().map(|v| v.as_string()).collect();
+                let shadowed = names
+                    .iter()
+                    .map(|n| self.env.storage.get(n).cloned())
+                    .collect();
+
+                self.frames.push(Resume::Loop {
+                    names,
+                    shadowed,
+                    iterable,
+                    sequence,
+                    position: 0,
+                    body: *body,
+                });
+                None
+            }
+            Node::TryExpression {
+                body,
+                parameter,
+                handler,
+                finalizer,
+            } => {
+                self.frames.push(Resume::Try {
+                    parameter,
+                    handler,
+                    finalizer,
+                });
+                self.enter(*body);
+                None
+            }
+            _ => Some(Object::error(
+                ErrorKind::InvalidYield,
+                "yield can only be used as a statement".to_string(),
+            )),
+        }
+    }
+
+    /// Pushes the frame of the generator function onto an error leaving it.
+    /// Its span is left to the builtin which resumed the Generator.
+    fn leave(&self, object: Object) -> Object {
+        match object {
+            Object::Error { mut value } => {
+                value.frames.push(Frame {
+                    function: self.name.clone(),
+                    span: None,
+                    file: None,
+                });
+                Object::Error { value }
+            }
+            object => object,
+        }
+    }
+
+    /// Pops frames off the Generator until an error is caught, or a finally
+    /// block has to run first. Returns the error if nothing stops it, in which
+    /// case the Generator is finished.
+    fn unwind(&mut self, mut object: Object) -> Option<Object> {
+        while let Some(frame) = self.frames.pop() {
+            match frame {
+                Resume::Block { .. } | Resume::Finally { .. } => {}
+                Resume::Loop {
+                    names, shadowed, ..
+                } => self.restore(names, shadowed),
+                Resume::Catch { name, shadowed } => self.restore(vec![name], vec![shadowed]),
+                Resume::Try {
+                    parameter,
+                    handler,
+                    finalizer,
+                } => {
+                    // An error raised while binding the parameter of the
+                    // handler carries on unwinding in place of the original.
+                    let mut raised = None;
+                    let mut catch = None;
+                    let handler = match (&object, parameter, handler) {
+                        (Object::Error { value }, Some(parameter), Some(handler))
+                            if value.kind.is_catchable() =>
+                        {
+                            let name = parameter.as_string();
+                            let shadowed = self.env.storage.get(&name).cloned();
+
+                            match self.env.set(name.clone(), value.to_object()) {
+                                Ok(_) => {
+                                    catch = Some(Resume::Catch { name, shadowed });
+                                    Some(handler)
+                                }
+                                Err(e) => {
+                                    raised = Some(e);
+                                    None
+                                }
+                            }
+                        }
+                        _ => None,
+                    };
+
+                    if let Some(e) = raised {
+                        object = e.into();
+                    }
+
+                    if handler.is_none() && finalizer.is_none() {
+                        continue;
+                    }
+
+                    if let Some(finalizer) = finalizer {
+                        let pending = match handler {
+                            Some(_) => None,
+                            None => Some(object),
+                        };
+
+                        self.frames.push(Resume::Finally { pending });
+                        self.enter(*finalizer);
+                    }
+
+                    if let Some(catch) = catch {
+                        self.frames.push(catch);
+                    }
+
+                    if let Some(handler) = handler {
+                        self.enter(*handler);
+                    }
+
+                    return None;
+                }
+            }
+        }
+
+        match object {
+            Object::Error { .. } => Some(object),
+            _ => None,
+        }
+    }
+
+    fn enter(&mut self, node: Node) {
+        let statements = match node {
+            Node::BlockStatement { statements } => statements,
+            other => vec![other],
+        };
+
+        self.frames.push(Resume::Block {
+            statements,
+            index: 0,
+        });
+    }
+
+    /// Puts back whatever the variables of a loop shadowed.
+    fn restore(&mut self, names: Vec<String>, shadowed: Vec<Option<Object>>) {
+        for (name, previous) in names.into_iter().zip(shadowed) {
+            match previous {
+                Some(p) => self.env.set(name, p).ok().flatten(),
+                None => self.env.remove(&name),
+            };
+        }
+    }
+}
+
+/// Returns the variables bound by an iteration of a for loop, matching the
+/// entries of `Object::iterate`.
+fn loop_bindings(
+    names: &[String],
+    iterable: &Object,
+    position: i64,
+    item: Object,
+) -> Vec<(String, Object)> {
+    let (key, value) = match iterable {
+        Object::Hash { pairs, .. } => {
+            let value = item
+                .hash_key()
+                .and_then(|k| pairs.get(&k).cloned())
+                .unwrap_or(Object::Null);
+            (item, value)
+        }
+        _ => (Object::Integer { value: position }, item),
+    };
+
+    match (names, iterable) {
+        ([item], Object::Hash { .. }) => vec![(item.clone(), key)],
+        ([item], _) => vec![(item.clone(), value)],
+        ([k, v], _) => vec![(k.clone(), key), (v.clone(), value)],
+        _ => vec![],
+    }
+}
+
+/// Returns true if a node contains a `yield` statement, without looking into
+/// nested function literals. Functions whose body contains `yield` are
+/// generator functions.
+pub fn contains_yield(node: &Node) -> bool {
+    let any = |nodes: &[Node]| nodes.iter().any(contains_yield);
+    let optional = |node: &Option<Box<Node>>| node.as_deref().is_some_and(contains_yield);
+
+    match node {
+        Node::YieldStatement { .. } => true,
+        Node::Program { statements } | Node::BlockStatement { statements } => any(statements),
+        Node::ArrayLiteral { elements } | Node::InterpolatedString { parts: elements } => {
+            any(elements)
+        }
+        Node::HashLiteral { entries } => any(entries),
+        Node::HashPair { key, value } => contains_yield(key) || contains_yield(value),
+        Node::PrefixExpression { right, .. } => contains_yield(right),
+        Node::InfixExpression { left, right, .. } => contains_yield(left) || contains_yield(right),
+        Node::PostfixExpression { left, .. } => contains_yield(left),
+        Node::IfExpression {
+            condition,
+            consequence,
+            alternative,
+        } => contains_yield(condition) || contains_yield(consequence) || optional(alternative),
+        Node::CallExpression {
+            function,
+            arguments,
+            ..
+        } => contains_yield(function) || any(arguments),
+        Node::IndexExpression { left, index, .. } => contains_yield(left) || contains_yield(index),
+        Node::MemberExpression { object, .. } => contains_yield(object),
+        Node::StructLiteral { fields, .. } => fields.iter().any(|(_, v)| contains_yield(v)),
+        Node::TryExpression {
+            body,
+            handler,
+            finalizer,
+            ..
+        } => contains_yield(body) || optional(handler) || optional(finalizer),
+        Node::LetStatement { value, .. }
+        | Node::ReturnStatement { value }
+        | Node::ExpressionStatement { expression: value } => optional(value),
+        Node::ForStatement { iterable, body, .. } => {
+            contains_yield(iterable) || contains_yield(body)
+        }
+        Node::AssignStatement { target, value, .. } => {
+            contains_yield(target) || contains_yield(value)
+        }
+        Node::ExportStatement { statement } => contains_yield(statement),
+        Node::ThrowStatement { value, .. }
+        | Node::ConstStatement { value, .. }
+        | Node::NamedArgument { value, .. }
+        | Node::SpreadExpression { value, .. } => contains_yield(value),
+        Node::MatchExpression { subject, arms, .. } => {
+            contains_yield(subject)
+                || arms
+                    .iter()
+                    .any(|a| optional(&a.guard) || contains_yield(&a.body))
+        }
+        Node::FunctionLiteral { .. }
+        | Node::StructStatement { .. }
+        | Node::ImplStatement { .. }
+        | Node::EnumStatement { .. }
+        | Node::ImportStatement { .. }
+        | Node::DestructuringPattern { .. }
+        | Node::DefaultParameter { .. }
+        | Node::RestParameter { .. }
+        | Node::Identifier { .. }
+        | Node::IntegerLiteral { .. }
+        | Node::FloatLiteral { .. }
+        | Node::Boolean { .. }
+        | Node::NullLiteral
+        | Node::StringLiteral { .. } => false,
+    }
+}
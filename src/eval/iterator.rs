@@ -0,0 +1,207 @@
+use super::error::ErrorKind;
+use super::generator::Generator;
+use super::object::Object;
+use super::{apply_function, is_unwinding};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A lazy sequence of Objects. Sequences are built from collections, ranges
+/// and generator functions, and can be stacked on top of each other to
+/// transform their items one at a time, without materializing intermediate
+/// arrays.
+///
+/// Iterator objects share their Sequence, so that advancing an iterator is
+/// visible through every binding that refers to it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Sequence {
+    Array {
+        elements: Vec<Object>,
+        position: usize,
+    },
+    String {
+        value: String,
+        offset: usize,
+    },
+    Range {
+        next: i64,
+        end: i64,
+        inclusive: bool,
+    },
+    Shared {
+        sequence: Rc<RefCell<Sequence>>,
+    },
+    Map {
+        source: Box<Sequence>,
+        function: Box<Object>,
+    },
+    Filter {
+        source: Box<Sequence>,
+        function: Box<Object>,
+    },
+    Take {
+        source: Box<Sequence>,
+        remaining: usize,
+    },
+    Zip {
+        left: Box<Sequence>,
+        right: Box<Sequence>,
+    },
+    Enumerate {
+        source: Box<Sequence>,
+        index: i64,
+    },
+    Chain {
+        first: Box<Sequence>,
+        second: Box<Sequence>,
+    },
+    Generator {
+        generator: Box<Generator>,
+    },
+}
+
+impl Sequence {
+    /// Creates a Sequence over the items of an Object, or returns None if the
+    /// Object can't be iterated over. Like in for loops, hashes yield their
+    /// keys.
+    pub fn from_object(object: &Object) -> Option<Sequence> {
+        match object {
+            Object::Array { elements, .. } => Some(Sequence::Array {
+                elements: elements.clone(),
+                position: 0,
+            }),
+            Object::Hash { pairs, .. } => Some(Sequence::Array {
+                elements: pairs.keys().map(|k| k.to_object()).collect(),
+                position: 0,
+            }),
+            Object::String { value } => Some(Sequence::String {
+                value: value.clone(),
+                offset: 0,
+            }),
+            Object::Range {
+                start,
+                end,
+                inclusive,
+            } => Some(Sequence::Range {
+                next: *start,
+                end: *end,
+                inclusive: *inclusive,
+            }),
+            Object::Iterator { sequence } => Some(Sequence::Shared {
+                sequence: Rc::clone(sequence),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Advancing a Sequence returns its next item, or None once it is exhausted.
+/// Errors raised by callbacks are returned as items, and must be checked for
+/// by the caller.
+impl Iterator for Sequence {
+    type Item = Object;
+
+    fn next(&mut self) -> Option<Object> {
+        match self {
+            Sequence::Array { elements, position } => {
+                let item = elements.get(*position).cloned();
+                *position += 1;
+                item
+            }
+            Sequence::String { value, offset } => {
+                let c = value[*offset..].chars().next()?;
+                *offset += c.len_utf8();
+                Some(Object::String {
+                    value: c.to_string(),
+                })
+            }
+            Sequence::Range {
+                next,
+                end,
+                inclusive,
+            } => {
+                let current = *next;
+                if current < *end {
+                    *next += 1;
+                } else if current == *end && *inclusive {
+                    *inclusive = false;
+                } else {
+                    return None;
+                }
+
+                Some(Object::Integer { value: current })
+            }
+            Sequence::Shared { sequence } => match sequence.try_borrow_mut() {
+                Ok(mut sequence) => sequence.next(),
+                Err(_) => Some(Object::error(
+                    ErrorKind::InvalidArgument,
+                    "iterator is already being advanced".to_string(),
+                )),
+            },
+            Sequence::Map { source, function } => {
+                let item = source.next()?;
+                if is_unwinding(&item) {
+                    return Some(item);
+                }
+
+                Some(apply_function(*function.clone(), vec![item]))
+            }
+            Sequence::Filter { source, function } => loop {
+                let item = source.next()?;
+                if is_unwinding(&item) {
+                    return Some(item);
+                }
+
+                match apply_function(*function.clone(), vec![item.clone()]) {
+                    Object::Error { value } => return Some(Object::Error { value }),
+                    Object::Boolean { value: false } | Object::Null => continue,
+                    _ => return Some(item),
+                }
+            },
+            Sequence::Take { source, remaining } => {
+                if *remaining == 0 {
+                    return None;
+                }
+
+                *remaining -= 1;
+                source.next()
+            }
+            Sequence::Zip { left, right } => {
+                let l = left.next()?;
+                if is_unwinding(&l) {
+                    return Some(l);
+                }
+
+                let r = right.next()?;
+                if is_unwinding(&r) {
+                    return Some(r);
+                }
+
+                Some(Object::Array {
+                    elements: vec![l, r],
+                    frozen: false,
+                })
+            }
+            Sequence::Enumerate { source, index } => {
+                let item = source.next()?;
+                if is_unwinding(&item) {
+                    return Some(item);
+                }
+
+                *index += 1;
+                Some(Object::Array {
+                    elements: vec![Object::Integer { value: *index - 1 }, item],
+                    frozen: false,
+                })
+            }
+            Sequence::Chain { first, second } => first.next().or_else(|| second.next()),
+            Sequence::Generator { generator } => generator.resume(),
+        }
+    }
+}
+
+/// Wraps a Sequence into an iterator Object.
+pub fn new_iterator(sequence: Sequence) -> Object {
+    Object::Iterator {
+        sequence: Rc::new(RefCell::new(sequence)),
+    }
+}
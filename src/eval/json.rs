@@ -0,0 +1,556 @@
+use super::bigint::BigInt;
+use super::error::{ErrorKind, RuntimeError};
+use super::object::{HashKey, Object};
+use super::reserve_memory;
+use crate::tokens::Span;
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::mem;
+use std::str::Chars;
+
+/// How deeply arrays and objects may be nested, which keeps deeply nested
+/// input from overflowing the stack.
+const MAX_DEPTH: usize = 256;
+
+/// Parses a JSON document. Objects become hashes with string keys, and
+/// numbers become integers unless they have a fraction or an exponent.
+pub fn parse(input: &str) -> Result<Object, RuntimeError> {
+    let mut parser = JsonParser {
+        input: input.chars().peekable(),
+        line: 1,
+        column: 1,
+        depth: 0,
+        bytes: 0,
+    };
+
+    parser.skip_whitespace();
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+
+    match parser.peek() {
+        None => Ok(value),
+        Some(c) => Err(parser.error(format!("unexpected '{}' after the document", c))),
+    }
+}
+
+/// Serializes an Object to JSON. Hash keys are written in sorted order, and
+/// struct fields in the order they were declared in. A positive indent puts
+/// every entry on its own line, indented by that many spaces per level.
+pub fn stringify(object: &Object, indent: usize) -> Result<String, RuntimeError> {
+    let mut output = String::new();
+    write_value(object, indent, 0, &mut output)?;
+    Ok(output)
+}
+
+fn error_at(span: Span, message: String) -> RuntimeError {
+    RuntimeError::new(
+        ErrorKind::InvalidArgument,
+        format!("invalid JSON at {}: {}", span, message),
+    )
+}
+
+struct JsonParser<'a> {
+    input: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+    depth: usize,
+    /// The memory held by the values parsed so far, which is reserved as
+    /// they are built.
+    bytes: usize,
+}
+
+impl JsonParser<'_> {
+    fn error(&self, message: String) -> RuntimeError {
+        error_at(Span::new(self.line, self.column), message)
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.input.peek().copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.input.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), RuntimeError> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.next();
+                Ok(())
+            }
+            Some(c) => Err(self.error(format!("expected '{}', got '{}'", expected, c))),
+            None => Err(self.error(format!("expected '{}', got the end of input", expected))),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Object, RuntimeError> {
+        let value = match self.peek() {
+            Some('{') => self.nested(Self::parse_object),
+            Some('[') => self.nested(Self::parse_array),
+            Some('"') => Ok(Object::String {
+                value: self.parse_string()?,
+            }),
+            Some('-' | '0'..='9') => self.parse_number(),
+            Some('t') => self.parse_keyword("true", Object::Boolean { value: true }),
+            Some('f') => self.parse_keyword("false", Object::Boolean { value: false }),
+            Some('n') => self.parse_keyword("null", Object::Null),
+            Some(c) => Err(self.error(format!("unexpected '{}'", c))),
+            None => Err(self.error("unexpected end of input".to_string())),
+        }?;
+
+        // The values nested in arrays and objects were counted as they were
+        // parsed.
+        self.bytes += match &value {
+            Object::String { value } => mem::size_of::<Object>() + value.len(),
+            _ => mem::size_of::<Object>(),
+        };
+
+        reserve_memory(self.bytes)?;
+        Ok(value)
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Object, RuntimeError>,
+    ) -> Result<Object, RuntimeError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(format!("nested more than {} levels deep", MAX_DEPTH)));
+        }
+
+        self.depth += 1;
+        let parsed = parse(self);
+        self.depth -= 1;
+        parsed
+    }
+
+    fn parse_object(&mut self) -> Result<Object, RuntimeError> {
+        self.expect('{')?;
+        self.skip_whitespace();
+
+        let mut pairs = BTreeMap::new();
+        if self.peek() == Some('}') {
+            self.next();
+            return Ok(Object::Hash {
+                pairs,
+                frozen: false,
+            });
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a string key".to_string()));
+            }
+
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            self.skip_whitespace();
+            pairs.insert(HashKey::String { value: key }, self.parse_value()?);
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(',') => {}
+                Some('}') => break,
+                _ => return Err(self.error("expected ',' or '}' in object".to_string())),
+            }
+
+            self.next();
+        }
+
+        self.next();
+        Ok(Object::Hash {
+            pairs,
+            frozen: false,
+        })
+    }
+
+    fn parse_array(&mut self) -> Result<Object, RuntimeError> {
+        self.expect('[')?;
+        self.skip_whitespace();
+
+        let mut elements = vec![];
+        if self.peek() == Some(']') {
+            self.next();
+            return Ok(Object::Array {
+                elements,
+                frozen: false,
+            });
+        }
+
+        loop {
+            self.skip_whitespace();
+            elements.push(self.parse_value()?);
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(',') => {}
+                Some(']') => break,
+                _ => return Err(self.error("expected ',' or ']' in array".to_string())),
+            }
+
+            self.next();
+        }
+
+        self.next();
+        Ok(Object::Array {
+            elements,
+            frozen: false,
+        })
+    }
+
+    fn parse_string(&mut self) -> Result<String, RuntimeError> {
+        self.expect('"')?;
+
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(value),
+                Some('\\') => {
+                    let escaped = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.parse_unicode_escape()?,
+                        _ => return Err(self.error("invalid escape sequence".to_string())),
+                    };
+
+                    value.push(escaped);
+                }
+                Some(c) if c < ' ' => {
+                    return Err(self.error("control character in string".to_string()))
+                }
+                Some(c) => value.push(c),
+                None => return Err(self.error("unterminated string".to_string())),
+            }
+        }
+    }
+
+    /// Parses the digits of a `\u` escape. Characters outside the basic
+    /// multilingual plane are written as a surrogate pair of escapes.
+    fn parse_unicode_escape(&mut self) -> Result<char, RuntimeError> {
+        let high = self.parse_hex()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high)
+                .ok_or_else(|| self.error("invalid \\u escape".to_string()));
+        }
+
+        if self.next() != Some('\\') || self.next() != Some('u') {
+            return Err(self.error("unpaired surrogate in \\u escape".to_string()));
+        }
+
+        let low = self.parse_hex()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error("unpaired surrogate in \\u escape".to_string()));
+        }
+
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+            .ok_or_else(|| self.error("invalid \\u escape".to_string()))
+    }
+
+    fn parse_hex(&mut self) -> Result<u32, RuntimeError> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = self
+                .next()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error("invalid \\u escape".to_string()))?;
+            value = value * 16 + digit;
+        }
+
+        Ok(value)
+    }
+
+    /// Errors in numbers point at where the number starts. Integers which
+    /// don't fit in an INTEGER are parsed as big integers.
+    fn parse_number(&mut self) -> Result<Object, RuntimeError> {
+        let start = Span::new(self.line, self.column);
+        let mut number = String::new();
+        while let Some(c) = self.peek() {
+            match c {
+                '-' | '+' | '.' | 'e' | 'E' | '0'..='9' => {
+                    number.push(c);
+                    self.next();
+                }
+                _ => break,
+            }
+        }
+
+        if !is_number(&number) {
+            return Err(error_at(start, format!("invalid number {}", number)));
+        }
+
+        let out_of_range = || error_at(start, format!("{} is out of range", number));
+        if number.contains(['.', 'e', 'E']) {
+            return match number.parse::<f64>() {
+                Ok(value) if value.is_finite() => Ok(Object::Float { value }),
+                _ => Err(out_of_range()),
+            };
+        }
+
+        match number.parse() {
+            Ok(value) => Ok(Object::Integer { value }),
+            Err(_) => BigInt::parse(&number)
+                .map(|value| Object::BigInt { value })
+                .ok_or_else(out_of_range),
+        }
+    }
+
+    fn parse_keyword(&mut self, keyword: &str, value: Object) -> Result<Object, RuntimeError> {
+        for expected in keyword.chars() {
+            if self.peek() != Some(expected) {
+                return Err(self.error(format!("expected {}", keyword)));
+            }
+
+            self.next();
+        }
+
+        Ok(value)
+    }
+}
+
+/// Returns true if a number is written the way JSON allows, e.g. without
+/// leading zeros or a leading `+`.
+fn is_number(number: &str) -> bool {
+    let digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+
+    let unsigned = number.strip_prefix('-').unwrap_or(number);
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(i) => (&unsigned[..i], Some(&unsigned[i + 1..])),
+        None => (unsigned, None),
+    };
+    let (integer, fraction) = match mantissa.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (mantissa, None),
+    };
+
+    digits(integer)
+        && (integer == "0" || !integer.starts_with('0'))
+        && fraction.is_none_or(digits)
+        && exponent.is_none_or(|e| digits(e.strip_prefix(['+', '-']).unwrap_or(e)))
+}
+
+fn write_value(
+    object: &Object,
+    indent: usize,
+    level: usize,
+    output: &mut String,
+) -> Result<(), RuntimeError> {
+    match object {
+        Object::Null => output.push_str("null"),
+        Object::Boolean { value } => output.push_str(&value.to_string()),
+        Object::Integer { value } => output.push_str(&value.to_string()),
+        Object::BigInt { value } => output.push_str(&value.to_string()),
+        Object::Float { value } if value.is_finite() => output.push_str(&format!("{:?}", value)),
+        Object::Float { value } => {
+            return Err(RuntimeError::new(
+                ErrorKind::InvalidArgument,
+                format!("cannot convert {:?} to JSON", value),
+            ))
+        }
+        Object::String { value } => write_string(value, output),
+        Object::Array { elements, .. } => {
+            let entries: Vec<(Option<String>, &Object)> =
+                elements.iter().map(|e| (None, e)).collect();
+            write_entries(('[', ']'), &entries, indent, level, output)?;
+        }
+        Object::Hash { pairs, .. } => {
+            let mut entries: Vec<(Option<String>, &Object)> = pairs
+                .iter()
+                .map(|(k, v)| (Some(k.to_object().inspect()), v))
+                .collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            write_entries(('{', '}'), &entries, indent, level, output)?;
+        }
+        Object::Struct { fields, .. } => {
+            let entries: Vec<(Option<String>, &Object)> =
+                fields.iter().map(|(f, v)| (Some(f.clone()), v)).collect();
+            write_entries(('{', '}'), &entries, indent, level, output)?;
+        }
+        other => {
+            return Err(RuntimeError::new(
+                ErrorKind::InvalidArgument,
+                format!("cannot convert {} to JSON", other.name()),
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the entries of an array or object between the given delimiters.
+/// Entries of objects come with their key.
+fn write_entries(
+    (open, close): (char, char),
+    entries: &[(Option<String>, &Object)],
+    indent: usize,
+    level: usize,
+    output: &mut String,
+) -> Result<(), RuntimeError> {
+    output.push(open);
+    if entries.is_empty() {
+        output.push(close);
+        return Ok(());
+    }
+
+    for (i, (key, value)) in entries.iter().enumerate() {
+        if i > 0 {
+            output.push(',');
+        }
+
+        if indent > 0 {
+            output.push('\n');
+            output.push_str(&" ".repeat(indent * (level + 1)));
+        }
+
+        if let Some(key) = key {
+            write_string(key, output);
+            output.push(':');
+            if indent > 0 {
+                output.push(' ');
+            }
+        }
+
+        write_value(value, indent, level + 1, output)?;
+    }
+
+    if indent > 0 {
+        output.push('\n');
+        output.push_str(&" ".repeat(indent * level));
+    }
+
+    output.push(close);
+    Ok(())
+}
+
+fn write_string(value: &str, output: &mut String) {
+    output.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if c < ' ' => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+
+    output.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let table = [
+            ("null", "null"),
+            (" true ", "true"),
+            ("-42", "-42"),
+            (
+                "[123456789012345678901234567890, -9223372036854775809]",
+                "[123456789012345678901234567890, -9223372036854775809]",
+            ),
+            ("[0.5, -1e3, 2.5E-1]", "[0.5, -1000.0, 0.25]"),
+            ("\"a\\\"b\\u00e9\\ud83d\\ude00\"", "a\"bé😀"),
+            ("[1, [], {}, \"x\"]", "[1, [], {}, x]"),
+            (
+                "{\"b\": {\"c\": [true, null]}, \"a\": 1}",
+                "{a: 1, b: {c: [true, null]}}",
+            ),
+        ];
+
+        table.iter().for_each(|(input, output)| match parse(input) {
+            Ok(object) => assert_eq!(object.inspect(), *output, "{}", input),
+            Err(e) => panic!("Unexpected error for {}: {}", input, e.message),
+        });
+
+        let errors = [
+            ("", "line 1, column 1: unexpected end of input"),
+            ("{\"a\" 1}", "line 1, column 6: expected ':', got '1'"),
+            ("[1,\n  2,\n  x]", "line 3, column 3: unexpected 'x'"),
+            ("[1 2]", "line 1, column 4: expected ',' or ']' in array"),
+            ("{1: 2}", "line 1, column 2: expected a string key"),
+            ("\"abc", "line 1, column 5: unterminated string"),
+            ("[01]", "line 1, column 2: invalid number 01"),
+            ("1.", "line 1, column 1: invalid number 1."),
+            ("-e5", "line 1, column 1: invalid number -e5"),
+            ("1e999", "line 1, column 1: 1e999 is out of range"),
+            ("nul", "line 1, column 4: expected null"),
+            (
+                "{} {}",
+                "line 1, column 4: unexpected '{' after the document",
+            ),
+        ];
+
+        errors
+            .iter()
+            .for_each(|(input, message)| match parse(input) {
+                Err(e) => assert_eq!(e.message, format!("invalid JSON at {}", message)),
+                Ok(object) => panic!("Unexpected object, {:?}", object),
+            });
+
+        let deep = "[".repeat(MAX_DEPTH + 1);
+        assert!(parse(&deep).is_err());
+    }
+
+    #[test]
+    fn test_stringify() {
+        let value = parse("{\"b\": [1, {\"c\": null}], \"a\": \"x\\ny\", \"e\": []}").unwrap();
+        assert_eq!(
+            stringify(&value, 0).unwrap(),
+            "{\"a\":\"x\\ny\",\"b\":[1,{\"c\":null}],\"e\":[]}"
+        );
+        assert_eq!(
+            stringify(&value, 2).unwrap(),
+            "{\n  \"a\": \"x\\ny\",\n  \"b\": [\n    1,\n    {\n      \"c\": null\n    }\n  ],\n  \"e\": []\n}"
+        );
+
+        // Parsing what was written gives back the same value.
+        let written = stringify(&value, 4).unwrap();
+        assert_eq!(parse(&written).unwrap(), value);
+
+        let function = Object::Builtin {
+            name: "len".to_string(),
+        };
+        let nested = Object::Array {
+            elements: vec![function],
+            frozen: false,
+        };
+        assert_eq!(
+            stringify(&nested, 0).unwrap_err().message,
+            "cannot convert BUILTIN to JSON"
+        );
+
+        let floats = parse("[0.1, 1e21, -2.0]").unwrap();
+        assert_eq!(stringify(&floats, 0).unwrap(), "[0.1,1e21,-2.0]");
+        assert_eq!(
+            stringify(&Object::Float { value: f64::NAN }, 0)
+                .unwrap_err()
+                .message,
+            "cannot convert NaN to JSON"
+        );
+    }
+}
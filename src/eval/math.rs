@@ -0,0 +1,646 @@
+use super::bigint::BigInt;
+use super::builtins::{check_arity, invalid_argument};
+use super::error::{ErrorKind, RuntimeError};
+use super::object::Object;
+use super::reserve_memory;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::f64::consts;
+
+/// The functions of the `math` namespace. Each is a builtin whose name is
+/// prefixed with `math.`, e.g. `math.sqrt`.
+const FUNCTIONS: &[&str] = &[
+    "abs", "min", "max", "clamp", "pow", "sqrt", "floor", "ceil", "round", "gcd", "lcm", "sin",
+    "cos", "tan", "asin", "acos", "atan", "atan2", "exp", "log", "log2", "log10",
+];
+
+/// Returns the `math` namespace, a module holding the math functions along
+/// with the constants `PI` and `E`.
+pub fn module() -> Object {
+    let mut exports: BTreeMap<String, Object> = FUNCTIONS
+        .iter()
+        .map(|name| {
+            let builtin = Object::Builtin {
+                name: format!("math.{}", name),
+            };
+            (name.to_string(), builtin)
+        })
+        .collect();
+
+    exports.insert("PI".to_string(), Object::Float { value: consts::PI });
+    exports.insert("E".to_string(), Object::Float { value: consts::E });
+
+    Object::Module {
+        path: "math".to_string(),
+        exports,
+    }
+}
+
+/// A numeric argument. Integers are promoted to big integers when they meet
+/// one, and both are promoted to floats when they meet one, or when a
+/// function is only defined for floats.
+#[derive(Clone)]
+enum Number {
+    Integer(i64),
+    Big(BigInt),
+    Float(f64),
+}
+
+impl Number {
+    fn to_f64(&self) -> f64 {
+        match self {
+            Number::Integer(value) => *value as f64,
+            Number::Big(value) => value.to_f64(),
+            Number::Float(value) => *value,
+        }
+    }
+
+    /// Returns the number as a big integer, unless it is a float.
+    fn to_big(&self) -> Option<BigInt> {
+        match self {
+            Number::Integer(value) => Some(BigInt::from(*value)),
+            Number::Big(value) => Some(value.clone()),
+            Number::Float(_) => None,
+        }
+    }
+
+    fn to_object(&self) -> Object {
+        match self {
+            Number::Integer(value) => Object::Integer { value: *value },
+            Number::Big(value) => Object::BigInt {
+                value: value.clone(),
+            },
+            Number::Float(value) => Object::Float { value: *value },
+        }
+    }
+}
+
+fn number(name: &str, object: &Object) -> Result<Number, RuntimeError> {
+    match object {
+        Object::Integer { value } => Ok(Number::Integer(*value)),
+        Object::BigInt { value } => Ok(Number::Big(value.clone())),
+        Object::Float { value } => Ok(Number::Float(*value)),
+        other => Err(invalid_argument(name, "INTEGER, BIGINT or FLOAT", other)),
+    }
+}
+
+/// Returns an integer argument, which may be a big integer.
+fn integer(name: &str, object: &Object) -> Result<Number, RuntimeError> {
+    match object {
+        Object::Integer { .. } | Object::BigInt { .. } => number(name, object),
+        other => Err(invalid_argument(name, "INTEGER or BIGINT", other)),
+    }
+}
+
+fn domain_error(name: &str, argument: &Number) -> RuntimeError {
+    RuntimeError::new(
+        ErrorKind::DomainError,
+        format!(
+            "math.{}({}) is undefined",
+            name,
+            argument.to_object().inspect()
+        ),
+    )
+}
+
+fn overflow(name: &str) -> RuntimeError {
+    RuntimeError::new(
+        ErrorKind::Overflow,
+        format!("integer overflow in math.{}", name),
+    )
+}
+
+pub fn abs(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    match number("math.abs", &args[0])? {
+        Number::Integer(value) => Ok(match value.checked_abs() {
+            Some(value) => Object::Integer { value },
+            None => Object::BigInt {
+                value: BigInt::from(value).abs(),
+            },
+        }),
+        Number::Big(value) => Ok(Object::BigInt { value: value.abs() }),
+        Number::Float(value) => Ok(Object::Float { value: value.abs() }),
+    }
+}
+
+pub fn min(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    extreme("min", args, Ordering::Less)
+}
+
+pub fn max(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    extreme("max", args, Ordering::Greater)
+}
+
+/// Returns the smallest or largest of one or more numbers, depending on the
+/// ordering wanted. NaN wins over every other number.
+fn extreme(name: &str, args: Vec<Object>, wanted: Ordering) -> Result<Object, RuntimeError> {
+    if args.is_empty() {
+        return Err(RuntimeError::new(
+            ErrorKind::WrongArgumentCount,
+            "wrong number of arguments: want=1.., got=0".to_string(),
+        ));
+    }
+
+    let name = format!("math.{}", name);
+    let numbers = args
+        .iter()
+        .map(|arg| number(&name, arg))
+        .collect::<Result<Vec<Number>, RuntimeError>>()?;
+
+    let mut best = &numbers[0];
+    for candidate in &numbers[1..] {
+        let better = match compare(candidate, best) {
+            Some(ordering) => ordering == wanted,
+            None => candidate.to_f64().is_nan(),
+        };
+
+        if better {
+            best = candidate;
+        }
+    }
+
+    Ok(promote(best, &numbers).to_object())
+}
+
+pub fn clamp(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 3)?;
+    let numbers = args
+        .iter()
+        .map(|arg| number("math.clamp", arg))
+        .collect::<Result<Vec<Number>, RuntimeError>>()?;
+    let (value, low, high) = (&numbers[0], &numbers[1], &numbers[2]);
+
+    if !matches!(compare(low, high), Some(Ordering::Less | Ordering::Equal)) {
+        return Err(RuntimeError::new(
+            ErrorKind::InvalidArgument,
+            format!(
+                "bounds of `math.clamp` must be ordered, got {} and {}",
+                args[1].inspect(),
+                args[2].inspect()
+            ),
+        ));
+    }
+
+    let clamped = if compare(value, low) == Some(Ordering::Less) {
+        low
+    } else if compare(value, high) == Some(Ordering::Greater) {
+        high
+    } else {
+        value
+    };
+
+    Ok(promote(clamped, &numbers).to_object())
+}
+
+/// Compares two numbers. Integers and big integers are compared exactly,
+/// and are otherwise promoted to floats. Returns None if either number is
+/// NaN.
+fn compare(a: &Number, b: &Number) -> Option<Ordering> {
+    match (a, b) {
+        (Number::Integer(a), Number::Integer(b)) => Some(a.cmp(b)),
+        (a, b) => match (a.to_big(), b.to_big()) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ => a.to_f64().partial_cmp(&b.to_f64()),
+        },
+    }
+}
+
+/// Promotes a number picked out of several to a float if any of them is a
+/// float, or else to a big integer if any of them is one, as arithmetic on
+/// them would.
+fn promote(number: &Number, numbers: &[Number]) -> Number {
+    if numbers.iter().any(|n| matches!(n, Number::Float(_))) {
+        Number::Float(number.to_f64())
+    } else if numbers.iter().any(|n| matches!(n, Number::Big(_))) {
+        number.to_big().map_or_else(|| number.clone(), Number::Big)
+    } else {
+        number.clone()
+    }
+}
+
+/// Raises an integer to a non-negative integer power exactly, promoting the
+/// result to a big integer if it doesn't fit in an integer. Negative powers
+/// and float operands give floats, though zero integers have no negative
+/// powers.
+pub fn pow(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    let base = number("math.pow", &args[0])?;
+    let exponent = number("math.pow", &args[1])?;
+
+    match (base, exponent) {
+        (Number::Integer(base), Number::Integer(exponent)) if exponent >= 0 => {
+            let exponent = u32::try_from(exponent).map_err(|_| overflow("pow"))?;
+            match base.checked_pow(exponent) {
+                Some(value) => Ok(Object::Integer { value }),
+                None => big_pow(BigInt::from(base), exponent),
+            }
+        }
+        (Number::Big(base), Number::Integer(exponent)) if exponent >= 0 => {
+            let exponent = u32::try_from(exponent).map_err(|_| overflow("pow"))?;
+            big_pow(base, exponent)
+        }
+        (base, exponent)
+            if base.to_big().is_some_and(|base| base.is_zero()) && exponent.to_f64() < 0.0 =>
+        {
+            Err(RuntimeError::new(
+                ErrorKind::DomainError,
+                format!(
+                    "math.pow({}, {}) is undefined",
+                    base.to_object().inspect(),
+                    exponent.to_object().inspect()
+                ),
+            ))
+        }
+        (base, exponent) => Ok(Object::Float {
+            value: base.to_f64().powf(exponent.to_f64()),
+        }),
+    }
+}
+
+fn big_pow(base: BigInt, exponent: u32) -> Result<Object, RuntimeError> {
+    // The result holds about as many digits as the base does, times the
+    // exponent.
+    reserve_memory(base.byte_size().saturating_mul(exponent as usize))?;
+    Ok(Object::BigInt {
+        value: base.pow(exponent),
+    })
+}
+
+pub fn sqrt(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    float_function("sqrt", args, |x| x >= 0.0, f64::sqrt)
+}
+
+pub fn floor(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    rounding("floor", args, f64::floor)
+}
+
+pub fn ceil(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    rounding("ceil", args, f64::ceil)
+}
+
+/// Rounds halfway cases away from zero.
+pub fn round(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    rounding("round", args, f64::round)
+}
+
+/// Rounds a float to an integer, or to a big integer if it doesn't fit in
+/// one. Integers are returned as they are.
+fn rounding(
+    name: &str,
+    args: Vec<Object>,
+    function: fn(f64) -> f64,
+) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    match number(&format!("math.{}", name), &args[0])? {
+        Number::Integer(value) => Ok(Object::Integer { value }),
+        Number::Big(value) => Ok(Object::BigInt { value }),
+        Number::Float(value) => {
+            let rounded = function(value);
+            if !rounded.is_finite() {
+                return Err(RuntimeError::new(
+                    ErrorKind::Overflow,
+                    format!("{:?} can't be represented as an INTEGER", value),
+                ));
+            }
+
+            // i64::MAX isn't exactly representable as a float, and rounds up
+            // to 2^63, which is out of range.
+            if rounded >= i64::MIN as f64 && rounded < i64::MAX as f64 {
+                return Ok(Object::Integer {
+                    value: rounded as i64,
+                });
+            }
+
+            // Floats this large are whole numbers, which print exactly.
+            let value = BigInt::parse(&format!("{:.0}", rounded)).unwrap_or_default();
+            Ok(Object::BigInt { value })
+        }
+    }
+}
+
+/// Returns the greatest common divisor of two integers, which is never
+/// negative.
+pub fn gcd(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    let (a, b) = match (
+        integer("math.gcd", &args[0])?,
+        integer("math.gcd", &args[1])?,
+    ) {
+        (Number::Integer(a), Number::Integer(b)) => (a, b),
+        (a, b) => return Ok(big_integers(a, b, |a, b| a.gcd(&b))),
+    };
+
+    let gcd = i64::try_from(unsigned_gcd(a.unsigned_abs(), b.unsigned_abs()));
+    Ok(match gcd {
+        Ok(value) => Object::Integer { value },
+        Err(_) => big_integers(Number::Integer(a), Number::Integer(b), |a, b| a.gcd(&b)),
+    })
+}
+
+/// Returns the least common multiple of two integers, which is never
+/// negative. It is 0 if either integer is.
+pub fn lcm(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    let (a, b) = match (
+        integer("math.lcm", &args[0])?,
+        integer("math.lcm", &args[1])?,
+    ) {
+        (Number::Integer(a), Number::Integer(b)) => (a, b),
+        (a, b) => return Ok(big_integers(a, b, big_lcm)),
+    };
+
+    if a == 0 || b == 0 {
+        return Ok(Object::Integer { value: 0 });
+    }
+
+    let (x, y) = (a.unsigned_abs(), b.unsigned_abs());
+    let lcm = (x / unsigned_gcd(x, y))
+        .checked_mul(y)
+        .and_then(|value| i64::try_from(value).ok());
+
+    Ok(match lcm {
+        Some(value) => Object::Integer { value },
+        None => big_integers(Number::Integer(a), Number::Integer(b), big_lcm),
+    })
+}
+
+fn big_lcm(a: BigInt, b: BigInt) -> BigInt {
+    if a.is_zero() || b.is_zero() {
+        return BigInt::default();
+    }
+
+    match a.div_rem(&a.gcd(&b)) {
+        Some((quotient, _)) => quotient.mul(&b).abs(),
+        None => BigInt::default(),
+    }
+}
+
+/// Applies a function of two big integers to integer arguments, when either
+/// is a big integer or the result doesn't fit in an integer.
+fn big_integers(a: Number, b: Number, function: fn(BigInt, BigInt) -> BigInt) -> Object {
+    let a = a.to_big().unwrap_or_default();
+    let b = b.to_big().unwrap_or_default();
+    Object::BigInt {
+        value: function(a, b),
+    }
+}
+
+fn unsigned_gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    a
+}
+
+pub fn sin(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    float_function("sin", args, |_| true, f64::sin)
+}
+
+pub fn cos(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    float_function("cos", args, |_| true, f64::cos)
+}
+
+pub fn tan(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    float_function("tan", args, |_| true, f64::tan)
+}
+
+pub fn asin(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    float_function("asin", args, |x| (-1.0..=1.0).contains(&x), f64::asin)
+}
+
+pub fn acos(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    float_function("acos", args, |x| (-1.0..=1.0).contains(&x), f64::acos)
+}
+
+pub fn atan(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    float_function("atan", args, |_| true, f64::atan)
+}
+
+/// Returns the angle of the point `(x, y)`, given as `atan2(y, x)`.
+pub fn atan2(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    let y = number("math.atan2", &args[0])?.to_f64();
+    let x = number("math.atan2", &args[1])?.to_f64();
+    Ok(Object::Float { value: y.atan2(x) })
+}
+
+pub fn exp(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    float_function("exp", args, |_| true, f64::exp)
+}
+
+/// Returns the natural logarithm of a number, or its logarithm in the given
+/// base.
+pub fn log(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    if !(1..=2).contains(&args.len()) {
+        return Err(RuntimeError::new(
+            ErrorKind::WrongArgumentCount,
+            format!("wrong number of arguments: want=1..=2, got={}", args.len()),
+        ));
+    }
+
+    if args.len() == 1 {
+        return float_function("log", args, |x| x > 0.0, f64::ln);
+    }
+
+    let x = number("math.log", &args[0])?;
+    let base = number("math.log", &args[1])?;
+    if x.to_f64() <= 0.0 {
+        return Err(domain_error("log", &x));
+    }
+
+    if base.to_f64() <= 0.0 || base.to_f64() == 1.0 {
+        return Err(RuntimeError::new(
+            ErrorKind::DomainError,
+            format!(
+                "logarithms in base {} are undefined",
+                base.to_object().inspect()
+            ),
+        ));
+    }
+
+    Ok(Object::Float {
+        value: x.to_f64().log(base.to_f64()),
+    })
+}
+
+pub fn log2(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    float_function("log2", args, |x| x > 0.0, f64::log2)
+}
+
+pub fn log10(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    float_function("log10", args, |x| x > 0.0, f64::log10)
+}
+
+/// Applies a function of one float to a number, which must be in the
+/// function's domain.
+fn float_function(
+    name: &str,
+    args: Vec<Object>,
+    domain: fn(f64) -> bool,
+    function: fn(f64) -> f64,
+) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    let x = number(&format!("math.{}", name), &args[0])?;
+    if !domain(x.to_f64()) {
+        return Err(domain_error(name, &x));
+    }
+
+    Ok(Object::Float {
+        value: function(x.to_f64()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::{eval, Environment};
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn test_eval(input: &str) -> Object {
+        let program = Parser::new(Lexer::new(input)).parse_program();
+        eval(program, &mut Environment::new())
+    }
+
+    #[test]
+    fn test_math() {
+        let table = [
+            ("[math.abs(-3), math.abs(-2.5)];", "[3, 2.5]"),
+            ("[math.min(3, 1, 2), math.max(4, 2.5)];", "[1, 4.0]"),
+            ("[math.clamp(5, 0, 3), math.clamp(-1, 0.5, 3)];", "[3, 0.5]"),
+            (
+                "[math.pow(2, 10), math.pow(2, -1), math.pow(4, 0.5)];",
+                "[1024, 0.5, 2.0]",
+            ),
+            ("[math.sqrt(16), math.sqrt(2.25)];", "[4.0, 1.5]"),
+            (
+                "[math.floor(2.7), math.ceil(-2.7), math.round(-2.5), math.round(7)];",
+                "[2, -2, -3, 7]",
+            ),
+            (
+                "[math.gcd(12, -18), math.gcd(0, 0), math.lcm(4, -6), math.lcm(0, 3)];",
+                "[6, 0, 12, 0]",
+            ),
+            (
+                "[math.sin(0), math.cos(math.PI), math.atan2(1, 1) * 4];",
+                "[0.0, -1.0, 3.141592653589793]",
+            ),
+            (
+                "[math.asin(1) * 2, math.acos(1), math.atan(0), math.tan(0)];",
+                "[3.141592653589793, 0.0, 0.0, 0.0]",
+            ),
+            (
+                "[math.exp(0), math.log(math.E), math.log(8, 2), math.log2(8), math.log10(1000)];",
+                "[1.0, 1.0, 3.0, 3.0, 3.0]",
+            ),
+            (
+                "[math.abs(bigint(-5)), math.max(1, bigint(3)), math.min(bigint(3), 2.5)];",
+                "[5, 3, 2.5]",
+            ),
+            (
+                "[math.pow(bigint(2), 100), math.sqrt(bigint(16)), math.floor(bigint(7))];",
+                "[1267650600228229401496703205376, 4.0, 7]",
+            ),
+            (
+                "[math.gcd(bigint(\"123456789012345678901234567890\"), 30), math.lcm(bigint(4), -6)];",
+                "[30, 12]",
+            ),
+            (
+                "[math.abs(-9223372036854775807 - 1), math.pow(10, 19), math.pow(2, 100)];",
+                "[9223372036854775808, 10000000000000000000, 1267650600228229401496703205376]",
+            ),
+            (
+                "[math.lcm(9223372036854775807, 2), math.gcd(-9223372036854775807 - 1, 0)];",
+                "[18446744073709551614, 9223372036854775808]",
+            ),
+            (
+                "[math.floor(math.pow(10.0, 20)), math.pow(0.0, -1)];",
+                "[100000000000000000000, inf]",
+            ),
+            ("math.E;", "2.718281828459045"),
+            ("math;", "module math"),
+            // The namespace can be shadowed like any other builtin.
+            ("let math = 1; math;", "1"),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            assert_eq!(test_eval(input).inspect(), *output, "{}", input);
+        });
+
+        let errors = [
+            (
+                "math.sqrt(-1);",
+                ErrorKind::DomainError,
+                "math.sqrt(-1) is undefined",
+            ),
+            (
+                "math.log(0.0);",
+                ErrorKind::DomainError,
+                "math.log(0.0) is undefined",
+            ),
+            (
+                "math.asin(2);",
+                ErrorKind::DomainError,
+                "math.asin(2) is undefined",
+            ),
+            (
+                "math.log(2, 1);",
+                ErrorKind::DomainError,
+                "logarithms in base 1 are undefined",
+            ),
+            (
+                "math.pow(0, -1);",
+                ErrorKind::DomainError,
+                "math.pow(0, -1) is undefined",
+            ),
+            (
+                "math.pow(bigint(0), -0.5);",
+                ErrorKind::DomainError,
+                "math.pow(0, -0.5) is undefined",
+            ),
+            (
+                "math.floor(math.exp(1000));",
+                ErrorKind::Overflow,
+                "inf can't be represented as an INTEGER",
+            ),
+            (
+                "math.gcd(1.5, 2);",
+                ErrorKind::InvalidArgument,
+                "argument to `math.gcd` must be INTEGER or BIGINT, got FLOAT",
+            ),
+            (
+                "math.sin(\"1\");",
+                ErrorKind::InvalidArgument,
+                "argument to `math.sin` must be INTEGER, BIGINT or FLOAT, got STRING",
+            ),
+            (
+                "math.clamp(1, 3, 2);",
+                ErrorKind::InvalidArgument,
+                "bounds of `math.clamp` must be ordered, got 3 and 2",
+            ),
+            (
+                "math.max();",
+                ErrorKind::WrongArgumentCount,
+                "wrong number of arguments: want=1.., got=0",
+            ),
+            (
+                "math.tau;",
+                ErrorKind::UnknownField,
+                "no export tau in module math",
+            ),
+        ];
+
+        errors
+            .iter()
+            .for_each(|(input, kind, message)| match test_eval(input) {
+                Object::Error { value } => {
+                    assert_eq!(value.kind, *kind, "{}", input);
+                    assert_eq!(value.message, *message);
+                }
+                object => panic!("Unexpected object, {:?}", object),
+            });
+    }
+}
@@ -0,0 +1,122 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::{Add, AddAssign};
+
+/// The amount of memory held by a value or a set of bindings, counted both in
+/// objects and in (estimated) bytes.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Footprint {
+    pub objects: usize,
+    pub bytes: usize,
+}
+
+impl Footprint {
+    /// Creates a new Footprint for the given amount of objects and bytes.
+    pub fn new(objects: usize, bytes: usize) -> Self {
+        Self { objects, bytes }
+    }
+}
+
+impl Add for Footprint {
+    type Output = Footprint;
+
+    fn add(self, other: Footprint) -> Footprint {
+        Footprint {
+            objects: self.objects + other.objects,
+            bytes: self.bytes + other.bytes,
+        }
+    }
+}
+
+impl AddAssign for Footprint {
+    fn add_assign(&mut self, other: Footprint) {
+        *self = *self + other;
+    }
+}
+
+/// A snapshot of the memory attributable to the script running on the current
+/// thread, along with the highest usage seen since the last reset.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MemoryUsage {
+    pub current: Footprint,
+    pub peak: Footprint,
+}
+
+impl fmt::Display for MemoryUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "memory: {} objects / {} bytes live, peak {} objects / {} bytes",
+            self.current.objects, self.current.bytes, self.peak.objects, self.peak.bytes
+        )
+    }
+}
+
+#[derive(Default)]
+struct Tracker {
+    usage: MemoryUsage,
+    limit: Option<usize>,
+}
+
+thread_local! {
+    static TRACKER: RefCell<Tracker> = RefCell::new(Tracker::default());
+}
+
+/// Sets the maximum amount of bytes a script may hold on to. Once the limit is
+/// exceeded, evaluation aborts with an error. `None` removes the limit.
+pub fn set_limit(limit: Option<usize>) {
+    TRACKER.with(|t| t.borrow_mut().limit = limit);
+}
+
+/// Returns the currently configured memory limit, in bytes.
+pub fn limit() -> Option<usize> {
+    TRACKER.with(|t| t.borrow().limit)
+}
+
+/// Returns the current and peak memory usage.
+pub fn usage() -> MemoryUsage {
+    TRACKER.with(|t| t.borrow().usage)
+}
+
+/// Resets the peak usage to the current usage. The limit is left untouched.
+pub fn reset_peak() {
+    TRACKER.with(|t| {
+        let mut t = t.borrow_mut();
+        t.usage.peak = t.usage.current;
+    });
+}
+
+/// Returns true if the current usage is above the configured limit.
+pub fn limit_exceeded() -> bool {
+    would_exceed(0)
+}
+
+/// Returns true if holding on to `bytes` more than the current usage would
+/// take it above the configured limit.
+pub fn would_exceed(bytes: usize) -> bool {
+    TRACKER.with(|t| {
+        let t = t.borrow();
+        matches!(t.limit, Some(limit) if t.usage.current.bytes.saturating_add(bytes) > limit)
+    })
+}
+
+pub(crate) fn allocate(footprint: Footprint) {
+    TRACKER.with(|t| {
+        let mut t = t.borrow_mut();
+        let usage = &mut t.usage;
+        usage.current += footprint;
+        usage.peak.objects = usage.peak.objects.max(usage.current.objects);
+        usage.peak.bytes = usage.peak.bytes.max(usage.current.bytes);
+    });
+}
+
+pub(crate) fn release(footprint: Footprint) {
+    // The tracker may already be gone if an Environment is dropped while the
+    // thread is shutting down, in which case there is nothing left to update.
+    let _ = TRACKER.try_with(|t| {
+        let mut t = t.borrow_mut();
+        let current = &mut t.usage.current;
+        current.objects = current.objects.saturating_sub(footprint.objects);
+        current.bytes = current.bytes.saturating_sub(footprint.bytes);
+    });
+}
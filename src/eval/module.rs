@@ -0,0 +1,345 @@
+use super::error::ErrorKind;
+use super::object::Object;
+use super::{eval, prelude, Environment};
+use crate::ast::Node;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::tokens::Span;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The modules imported so far, and the files being evaluated. Files are
+/// identified by their canonical path, and shown by the path they were given
+/// as, relative to the file importing them.
+#[derive(Default)]
+struct Loader {
+    modules: HashMap<PathBuf, Object>,
+    running: Vec<(PathBuf, String)>,
+}
+
+thread_local! {
+    static LOADER: RefCell<Loader> = RefCell::new(Loader::default());
+}
+
+/// Evaluates the program of a file in the given environment. Imports in the
+/// program are resolved relative to the file, which can't be imported again
+/// until it is done.
+pub fn run_file(path: &str, program: Node, environment: &mut Environment) -> Object {
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+    LOADER.with(|l| l.borrow_mut().running.push((canonical, path.to_string())));
+
+    let evaluated = eval(program, environment);
+    LOADER.with(|l| l.borrow_mut().running.pop());
+    evaluated
+}
+
+/// Returns the module held by a file, evaluating the file in its own
+/// environment the first time it is imported. Later imports of the same file
+/// get the same module, even through a different path. Errors raised while
+/// evaluating the file point into it, and back at the import statement at
+/// the given span.
+pub fn import(path: &str, span: Span) -> Object {
+    let path = resolve(path);
+    let canonical = match fs::canonicalize(&path) {
+        Ok(canonical) => canonical,
+        Err(e) => {
+            return Object::error(
+                ErrorKind::ImportError,
+                format!("cannot import {}: {}", path, e),
+            )
+        }
+    };
+
+    if let Some(module) = LOADER.with(|l| l.borrow().modules.get(&canonical).cloned()) {
+        return module;
+    }
+
+    if let Some(chain) = import_chain(&canonical, &path) {
+        return Object::error(
+            ErrorKind::CircularImport,
+            format!("circular import: {}", chain),
+        );
+    }
+
+    let input = match fs::read_to_string(&canonical) {
+        Ok(input) => input,
+        Err(e) => {
+            return Object::error(
+                ErrorKind::ImportError,
+                format!("cannot import {}: {}", path, e),
+            )
+        }
+    };
+
+    let mut parser = Parser::new(Lexer::new(&input));
+    let program = parser.parse_program();
+    if let Some(e) = parser.errors.first() {
+        return Object::error(
+            ErrorKind::ImportError,
+            format!("cannot import {}: {:?}", path, e),
+        );
+    }
+
+    let names = exported_names(&program);
+    let mut environment = Environment::new();
+    let mut evaluated = prelude::load(&mut environment);
+    if !matches!(evaluated, Object::Error { .. }) {
+        evaluated = run_file(&path, program, &mut environment);
+    }
+
+    if let Object::Error { mut value } = evaluated {
+        value.leave_module(&path, span);
+        return Object::Error { value };
+    }
+
+    let exports: BTreeMap<String, Object> = names
+        .into_iter()
+        .filter_map(|name| environment.get(&name).map(|value| (name, value)))
+        .collect();
+
+    let module = Object::Module { path, exports };
+    LOADER.with(|l| l.borrow_mut().modules.insert(canonical, module.clone()));
+
+    module
+}
+
+/// Resolves an imported path relative to the directory of the file being
+/// evaluated. Outside of any file, e.g. in the REPL, paths are left as they
+/// are and resolved relative to the working directory.
+fn resolve(path: &str) -> String {
+    let importer = LOADER.with(|l| l.borrow().running.last().map(|(_, p)| p.clone()));
+    match importer.as_deref().map(Path::new).and_then(Path::parent) {
+        Some(directory) if Path::new(path).is_relative() => {
+            directory.join(path).display().to_string()
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// Returns the chain of imports leading back to a file, if the file is still
+/// being evaluated, e.g. `a.mk -> b.mk -> a.mk`.
+fn import_chain(canonical: &Path, path: &str) -> Option<String> {
+    LOADER.with(|l| {
+        let loader = l.borrow();
+        let start = loader.running.iter().position(|(c, _)| c == canonical)?;
+
+        let mut chain: Vec<&str> = loader.running[start..]
+            .iter()
+            .map(|(_, p)| p.as_str())
+            .collect();
+        chain.push(path);
+        Some(chain.join(" -> "))
+    })
+}
+
+/// Returns the names exported by the top level of a program.
+fn exported_names(program: &Node) -> Vec<String> {
+    let statements = match program {
+        Node::Program { statements } => statements,
+        _ => return vec![],
+    };
+
+    statements
+        .iter()
+        .flat_map(|statement| match statement {
+            Node::ExportStatement { statement } => match statement.as_ref() {
+                Node::LetStatement { name, .. } | Node::ConstStatement { name, .. } => {
+                    match name.as_ref() {
+                        Node::DestructuringPattern { pattern, .. } => pattern.bindings(),
+                        name => vec![name.as_string()],
+                    }
+                }
+                Node::StructStatement { name, .. } | Node::EnumStatement { name, .. } => {
+                    vec![name.as_string()]
+                }
+                _ => vec![],
+            },
+            _ => vec![],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::object::Object;
+    use std::env;
+    use std::ops::Deref;
+
+    /// A directory written by a test, which is removed once the test is done.
+    struct TestDirectory(PathBuf);
+
+    impl Deref for TestDirectory {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TestDirectory {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Writes the given files to a fresh directory.
+    fn write_files(test: &str, files: &[(&str, &str)]) -> TestDirectory {
+        let directory = env::temp_dir().join(format!("modules-{}-{}", test, std::process::id()));
+        for (name, contents) in files {
+            let path = directory.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        TestDirectory(directory)
+    }
+
+    fn run(directory: &Path, main: &str) -> Object {
+        let path = directory.join("main.mk").display().to_string();
+        let program = Parser::new(Lexer::new(main)).parse_program();
+        run_file(&path, program, &mut Environment::new())
+    }
+
+    #[test]
+    fn test_imports() {
+        let directory = write_files(
+            "imports",
+            &[
+                (
+                    "lib/shapes.mk",
+                    "import \"math.mk\" as math;
+                    export struct Square { side }
+                    export let area = fn(s) { math.square(s.side) };
+                    let secret = 1;",
+                ),
+                (
+                    "lib/math.mk",
+                    "export let square = |x| x * x; export const [one, two] = [1, 2];",
+                ),
+            ],
+        );
+
+        let table = [
+            (
+                "import \"lib/shapes.mk\" as s; let Square = s.Square; s.area(Square { side: 3 });",
+                "9",
+            ),
+            (
+                "import \"lib/math.mk\" as m; import \"lib/../lib/math.mk\" as n; [m.one + m.two, m == n];",
+                "[3, true]",
+            ),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            assert_eq!(run(&directory, input).inspect(), *output, "{}", input);
+        });
+
+        // Both paths lead to the same file, which is only evaluated once.
+        let canonical = fs::canonicalize(directory.join("lib/math.mk")).unwrap();
+        assert!(LOADER.with(|l| l.borrow().modules.contains_key(&canonical)));
+        assert_eq!(LOADER.with(|l| l.borrow().modules.len()), 2);
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let directory = write_files(
+            "import-errors",
+            &[
+                ("a.mk", "import \"b.mk\" as b; export let x = 1;"),
+                ("b.mk", "import \"a.mk\" as a; export let y = 2;"),
+                ("broken.mk", "let = 1;"),
+                ("lib.mk", "let secret = 1; export let f = fn() { secret };"),
+            ],
+        );
+
+        let path = |name: &str| directory.join(name).display().to_string();
+        let errors = [
+            (
+                "import \"a.mk\" as a;",
+                ErrorKind::CircularImport,
+                format!(
+                    "circular import: {} -> {} -> {}",
+                    path("a.mk"),
+                    path("b.mk"),
+                    path("a.mk")
+                ),
+            ),
+            (
+                "import \"main.mk\" as m;",
+                ErrorKind::CircularImport,
+                format!(
+                    "circular import: {} -> {}",
+                    path("main.mk"),
+                    path("main.mk")
+                ),
+            ),
+            (
+                "import \"broken.mk\" as b;",
+                ErrorKind::ImportError,
+                format!("cannot import {}: IdentExpected", path("broken.mk")),
+            ),
+            (
+                "import \"lib.mk\" as lib; lib.secret;",
+                ErrorKind::UnknownField,
+                format!("no export secret in module {}", path("lib.mk")),
+            ),
+            (
+                "import \"lib.mk\" as lib; lib.f = 1;",
+                ErrorKind::FrozenMutation,
+                "cannot modify frozen MODULE".to_string(),
+            ),
+        ];
+
+        fs::write(directory.join("main.mk"), "").unwrap();
+        errors
+            .iter()
+            .for_each(|(input, kind, message)| match run(&directory, input) {
+                Object::Error { value } => {
+                    assert_eq!(value.kind, *kind);
+                    assert_eq!(value.message, *message);
+                }
+                object => panic!("Unexpected object, {:?}", object),
+            });
+
+        assert_eq!(
+            run(&directory, "import \"lib.mk\" as lib; lib.f();").inspect(),
+            "1"
+        );
+
+        match run(&directory, "import \"missing.mk\" as m;") {
+            Object::Error { value } => assert_eq!(value.kind, ErrorKind::ImportError),
+            object => panic!("Unexpected object, {:?}", object),
+        }
+    }
+
+    #[test]
+    fn test_import_traceback() {
+        let directory = write_files(
+            "import-traceback",
+            &[
+                ("lib/a.mk", "import \"b.mk\" as b;"),
+                ("lib/b.mk", "let f = fn(x) { x + true; };\nf(1);"),
+            ],
+        );
+
+        match run(&directory, "let x = 1;\nimport \"lib/a.mk\" as a;") {
+            Object::Error { value } => assert_eq!(
+                value.traceback(),
+                format!(
+                    "Traceback (most recent call last):
+  line 2, column 1, in <script>
+  {a}, line 1, column 1, in <module>
+  {b}, line 2, column 2, in <module>
+  {b}, line 1, column 19, in f
+ERROR: type mismatch: INTEGER + BOOLEAN",
+                    a = directory.join("lib/a.mk").display(),
+                    b = directory.join("lib/b.mk").display(),
+                )
+            ),
+            object => panic!("Unexpected object, {:?}", object),
+        }
+    }
+}
@@ -1,39 +1,205 @@
+use super::bigint::BigInt;
+use super::error::{ErrorKind, RuntimeError};
+use super::iterator::Sequence;
+use super::memory::Footprint;
 use super::Environment;
 use crate::ast::Node;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::mem;
+use std::rc::Rc;
 
 /// All types of language objects. These are resolved from AST nodes, and represent
 /// any kind of evaluated input.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Object {
     Integer {
         value: i64,
     },
+    Float {
+        value: f64,
+    },
+    BigInt {
+        value: BigInt,
+    },
     Boolean {
         value: bool,
     },
+    String {
+        value: String,
+    },
+    /// Frozen arrays, hashes and structs can't be modified, and neither can
+    /// the collections nested in them.
+    Array {
+        elements: Vec<Object>,
+        frozen: bool,
+    },
+    Hash {
+        pairs: BTreeMap<HashKey, Object>,
+        frozen: bool,
+    },
+    /// The type declared by a struct statement, which lists its fields and
+    /// the methods added by impl blocks.
+    StructType {
+        name: String,
+        fields: Vec<String>,
+        methods: BTreeMap<String, Object>,
+    },
+    /// An instance of a struct, which holds its fields in the order they were
+    /// declared in.
+    Struct {
+        name: String,
+        fields: Vec<(String, Object)>,
+        frozen: bool,
+    },
+    /// The type declared by an enum statement, which lists its variants along
+    /// with their fields, and the methods added by impl blocks. Variants
+    /// without fields have None in place of their fields.
+    EnumType {
+        name: String,
+        variants: Vec<(String, Option<Vec<String>>)>,
+        methods: BTreeMap<String, Object>,
+    },
+    /// A callable value which creates a variant of an enum from its fields.
+    Constructor {
+        enum_name: String,
+        variant: String,
+        fields: Vec<String>,
+    },
+    /// A variant of an enum, which holds its fields in the order they were
+    /// declared in.
+    Variant {
+        enum_name: String,
+        variant: String,
+        fields: Vec<(String, Object)>,
+    },
+    /// A file loaded by an import statement, holding the values it exports.
+    Module {
+        path: String,
+        exports: BTreeMap<String, Object>,
+    },
+    Range {
+        start: i64,
+        end: i64,
+        inclusive: bool,
+    },
+    Iterator {
+        sequence: Rc<RefCell<Sequence>>,
+    },
+    Result {
+        value: Result<Box<Object>, Box<Object>>,
+    },
     ReturnValue {
         value: Box<Object>,
     },
     Error {
-        value: String,
+        value: Box<RuntimeError>,
     },
     Function {
+        name: Option<String>,
         parameters: Vec<Node>,
         body: Node,
-        env: Environment,
+        env: Box<Environment>,
+    },
+    Builtin {
+        name: String,
     },
     Null,
 }
 
 impl Object {
+    /// Creates a new Error object of the given kind.
+    pub fn error(kind: ErrorKind, message: String) -> Self {
+        Object::Error {
+            value: Box::new(RuntimeError::new(kind, message)),
+        }
+    }
+
     /// Returns the string representation of an Object.
     pub fn inspect(&self) -> String {
         match self {
             Object::Integer { value } => format!("{}", value),
+            Object::Float { value } => format!("{:?}", value),
+            Object::BigInt { value } => value.to_string(),
             Object::Boolean { value } => format!("{}", value),
+            Object::String { value } => value.clone(),
+            Object::Array { elements, .. } => format!(
+                "[{}]",
+                elements
+                    .iter()
+                    .map(|e| e.inspect())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Object::Range {
+                start,
+                end,
+                inclusive,
+            } => format!("{}{}{}", start, if *inclusive { "..=" } else { ".." }, end),
+            Object::Iterator { .. } => String::from("<iterator>"),
+            Object::Hash { pairs, .. } => format!(
+                "{{{}}}",
+                pairs
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", k.to_object().inspect(), v.inspect()))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Object::StructType { name, fields, .. } => {
+                format!("struct {} {{ {} }}", name, fields.join(", "))
+            }
+            Object::Struct { name, fields, .. } if fields.is_empty() => format!("{} {{}}", name),
+            Object::Struct { name, fields, .. } => format!(
+                "{} {{ {} }}",
+                name,
+                fields
+                    .iter()
+                    .map(|(f, v)| format!("{}: {}", f, v.inspect()))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Object::EnumType { name, variants, .. } => format!(
+                "enum {} {{ {} }}",
+                name,
+                variants
+                    .iter()
+                    .map(|(v, fields)| match fields {
+                        Some(fields) => format!("{}({})", v, fields.join(", ")),
+                        None => v.clone(),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Object::Constructor {
+                enum_name, variant, ..
+            } => format!("constructor {}.{}", enum_name, variant),
+            Object::Variant {
+                enum_name,
+                variant,
+                fields,
+            } if fields.is_empty() => format!("{}.{}", enum_name, variant),
+            Object::Variant {
+                enum_name,
+                variant,
+                fields,
+            } => format!(
+                "{}.{}({})",
+                enum_name,
+                variant,
+                fields
+                    .iter()
+                    .map(|(_, v)| v.inspect())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Object::Module { path, .. } => format!("module {}", path),
+            Object::Result { value } => match value {
+                Ok(v) => format!("ok({})", v.inspect()),
+                Err(e) => format!("err({})", e.inspect()),
+            },
             Object::ReturnValue { value } => (*value.inspect()).to_string(),
             Object::Error { value } => {
-                format!("ERROR: {}", value)
+                format!("ERROR: {}", value.message)
             }
             Object::Function {
                 parameters, body, ..
@@ -48,6 +214,7 @@ impl Object {
                     &body.as_string()
                 )
             }
+            Object::Builtin { name } => format!("builtin {}", name),
             Object::Null => String::from("null"),
         }
     }
@@ -56,11 +223,210 @@ impl Object {
     pub fn name(&self) -> String {
         match self {
             Object::Integer { .. } => "INTEGER".to_string(),
+            Object::Float { .. } => "FLOAT".to_string(),
+            Object::BigInt { .. } => "BIGINT".to_string(),
             Object::Boolean { .. } => "BOOLEAN".to_string(),
+            Object::String { .. } => "STRING".to_string(),
+            Object::Array { .. } => "ARRAY".to_string(),
+            Object::Hash { .. } => "HASH".to_string(),
+            Object::StructType { .. } => "STRUCT".to_string(),
+            Object::Struct { name, .. } => name.clone(),
+            Object::EnumType { .. } => "ENUM".to_string(),
+            Object::Module { .. } => "MODULE".to_string(),
+            Object::Constructor { .. } => "CONSTRUCTOR".to_string(),
+            Object::Variant {
+                enum_name, variant, ..
+            } => format!("{}.{}", enum_name, variant),
+            Object::Range { .. } => "RANGE".to_string(),
+            Object::Iterator { .. } => "ITERATOR".to_string(),
+            Object::Result { .. } => "RESULT".to_string(),
             Object::ReturnValue { .. } => "RETURN_VALUE".to_string(),
             Object::Error { .. } => "ERROR".to_string(),
             Object::Function { .. } => "FUNCTION".to_string(),
-            Object::Null { .. } => "NULL".to_string(),
+            Object::Builtin { .. } => "BUILTIN".to_string(),
+            Object::Null => "NULL".to_string(),
+        }
+    }
+
+    /// Returns a copy of an Object in which arrays, hashes and structs are
+    /// frozen, including the ones nested in other collections.
+    pub fn freeze(self) -> Object {
+        match self {
+            Object::Array { elements, .. } => Object::Array {
+                elements: elements.into_iter().map(Object::freeze).collect(),
+                frozen: true,
+            },
+            Object::Hash { pairs, .. } => Object::Hash {
+                pairs: pairs.into_iter().map(|(k, v)| (k, v.freeze())).collect(),
+                frozen: true,
+            },
+            Object::Struct { name, fields, .. } => Object::Struct {
+                name,
+                fields: fields.into_iter().map(|(f, v)| (f, v.freeze())).collect(),
+                frozen: true,
+            },
+            Object::Variant {
+                enum_name,
+                variant,
+                fields,
+            } => Object::Variant {
+                enum_name,
+                variant,
+                fields: fields.into_iter().map(|(f, v)| (f, v.freeze())).collect(),
+            },
+            Object::Result { value } => Object::Result {
+                value: match value {
+                    Ok(v) => Ok(Box::new(v.freeze())),
+                    Err(e) => Err(Box::new(e.freeze())),
+                },
+            },
+            other => other,
+        }
+    }
+
+    /// Returns true if an Object can't be modified. Only arrays, hashes and
+    /// structs can be, unless they are frozen.
+    pub fn is_frozen(&self) -> bool {
+        !matches!(
+            self,
+            Object::Array { frozen: false, .. }
+                | Object::Hash { frozen: false, .. }
+                | Object::Struct { frozen: false, .. }
+        )
+    }
+
+    /// Returns the key used to store an Object in a hash, or None if the
+    /// Object can't be used as a hash key.
+    pub fn hash_key(&self) -> Option<HashKey> {
+        match self {
+            Object::Integer { value } => Some(HashKey::Integer { value: *value }),
+            Object::Boolean { value } => Some(HashKey::Boolean { value: *value }),
+            Object::String { value } => Some(HashKey::String {
+                value: value.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over the entries of an Object, or None if the
+    /// Object can't be iterated over. Every entry is a (key, value) pair:
+    /// hashes yield their keys and values, while arrays, strings, ranges and
+    /// iterators yield the position of each item along with the item itself.
+    /// Ranges and iterators are iterated lazily.
+    pub fn iterate(&self) -> Option<Box<dyn Iterator<Item = (Object, Object)>>> {
+        let position = |(i, item): (usize, Object)| (Object::Integer { value: i as i64 }, item);
+
+        match self {
+            Object::Array { elements, .. } => Some(Box::new(
+                elements.clone().into_iter().enumerate().map(position),
+            )),
+            Object::Hash { pairs, .. } => Some(Box::new(
+                pairs.clone().into_iter().map(|(k, v)| (k.to_object(), v)),
+            )),
+            Object::String { value } => Some(Box::new(
+                value
+                    .chars()
+                    .collect::<Vec<char>>()
+                    .into_iter()
+                    .map(|c| Object::String {
+                        value: c.to_string(),
+                    })
+                    .enumerate()
+                    .map(position),
+            )),
+            Object::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let values: Box<dyn Iterator<Item = i64>> = if *inclusive {
+                    Box::new(*start..=*end)
+                } else {
+                    Box::new(*start..*end)
+                };
+
+                Some(Box::new(
+                    values
+                        .map(|value| Object::Integer { value })
+                        .enumerate()
+                        .map(position),
+                ))
+            }
+            Object::Iterator { sequence } => {
+                let shared = Sequence::Shared {
+                    sequence: Rc::clone(sequence),
+                };
+
+                Some(Box::new(shared.enumerate().map(position)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns an estimate of the memory held by an Object. A function's
+    /// captured Environment is not included, since environments account for
+    /// their own bindings.
+    pub fn footprint(&self) -> Footprint {
+        let shallow = mem::size_of::<Object>();
+        match self {
+            Object::String { value } => Footprint::new(1, shallow + value.len()),
+            Object::BigInt { value } => Footprint::new(1, shallow + value.byte_size()),
+            Object::Array { elements, .. } => elements
+                .iter()
+                .fold(Footprint::new(1, shallow), |total, e| total + e.footprint()),
+            Object::Hash { pairs, .. } => pairs
+                .iter()
+                .fold(Footprint::new(1, shallow), |total, (k, v)| {
+                    total + k.to_object().footprint() + v.footprint()
+                }),
+            Object::StructType { methods, .. }
+            | Object::EnumType { methods, .. }
+            | Object::Module {
+                exports: methods, ..
+            } => methods
+                .iter()
+                .fold(Footprint::new(1, shallow), |total, (name, v)| {
+                    total + Footprint::new(0, name.len()) + v.footprint()
+                }),
+            Object::Struct { fields, .. } | Object::Variant { fields, .. } => fields
+                .iter()
+                .fold(Footprint::new(1, shallow), |total, (f, v)| {
+                    total + Footprint::new(0, f.len()) + v.footprint()
+                }),
+            Object::Result { value: Ok(value) } | Object::Result { value: Err(value) } => {
+                Footprint::new(1, shallow) + value.footprint()
+            }
+            Object::ReturnValue { value } => Footprint::new(1, shallow) + value.footprint(),
+            Object::Error { value } => Footprint::new(
+                1,
+                shallow + mem::size_of::<RuntimeError>() + value.message.len(),
+            ),
+            Object::Function { parameters, .. } => {
+                Footprint::new(1, shallow + mem::size_of::<Node>() * (parameters.len() + 1))
+            }
+            _ => Footprint::new(1, shallow),
+        }
+    }
+}
+
+/// The keys which can be used to index a hash. Keys are ordered, so that
+/// hashes are always iterated and displayed in the same order.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HashKey {
+    Integer { value: i64 },
+    Boolean { value: bool },
+    String { value: String },
+}
+
+impl HashKey {
+    /// Returns the Object a HashKey was created from.
+    pub fn to_object(&self) -> Object {
+        match self {
+            HashKey::Integer { value } => Object::Integer { value: *value },
+            HashKey::Boolean { value } => Object::Boolean { value: *value },
+            HashKey::String { value } => Object::String {
+                value: value.clone(),
+            },
         }
     }
 }
@@ -0,0 +1,151 @@
+use super::object::Object;
+use super::{eval, Environment};
+use crate::ast::{Node, Pattern};
+
+/// Matches a value against a pattern. Returns true if the value matches, in
+/// which case the variables bound by the pattern are appended to `bindings`.
+pub fn matches(pattern: &Pattern, value: &Object, bindings: &mut Vec<(String, Object)>) -> bool {
+    destructure(pattern, value, bindings).is_ok()
+}
+
+/// Matches a value against a pattern, like `matches`, but explains why the
+/// value doesn't fit the pattern when it doesn't match.
+pub fn destructure(
+    pattern: &Pattern,
+    value: &Object,
+    bindings: &mut Vec<(String, Object)>,
+) -> Result<(), String> {
+    match pattern {
+        Pattern::Wildcard => Ok(()),
+        Pattern::Binding { name } => {
+            bindings.push((name.clone(), value.clone()));
+            Ok(())
+        }
+        Pattern::Literal { value: literal } => {
+            if literal_value(literal) == *value {
+                return Ok(());
+            }
+
+            Err(format!(
+                "expected {}, got {}",
+                literal.as_string(),
+                value.inspect()
+            ))
+        }
+        Pattern::Rest { .. } => Err("unexpected rest pattern".to_string()),
+        Pattern::Array { elements: patterns } => match value {
+            Object::Array { elements, .. } => destructure_array(patterns, elements, bindings),
+            other => Err(format!("expected ARRAY, got {}", other.name())),
+        },
+        Pattern::Hash { pairs: patterns } => match value {
+            Object::Hash { pairs, .. } => patterns.iter().try_for_each(|(key, pattern)| {
+                match literal_value(key).hash_key().and_then(|k| pairs.get(&k)) {
+                    Some(v) => destructure(pattern, v, bindings),
+                    None => Err(format!("missing key {}", key.as_string())),
+                }
+            }),
+            other => Err(format!("expected HASH, got {}", other.name())),
+        },
+        Pattern::Variant {
+            enum_name,
+            variant,
+            fields: patterns,
+        } => match value {
+            Object::Variant {
+                enum_name: e,
+                variant: v,
+                fields,
+            } if enum_name.as_ref().is_none_or(|n| n == e) && variant == v => match patterns {
+                Some(patterns) if patterns.len() != fields.len() => Err(format!(
+                    "expected {} fields, got {}",
+                    patterns.len(),
+                    fields.len()
+                )),
+                Some(patterns) => patterns
+                    .iter()
+                    .zip(fields)
+                    .try_for_each(|(pattern, (_, field))| destructure(pattern, field, bindings)),
+                None => Ok(()),
+            },
+            other => Err(format!(
+                "expected {}, got {}",
+                pattern.as_string(),
+                other.inspect()
+            )),
+        },
+        // Only the bindings of the alternative which matched are kept.
+        Pattern::Alternative { patterns } => {
+            let mut reason = String::new();
+            for pattern in patterns {
+                let mut alternative = vec![];
+                match destructure(pattern, value, &mut alternative) {
+                    Ok(()) => {
+                        bindings.append(&mut alternative);
+                        return Ok(());
+                    }
+                    Err(e) => reason = e,
+                }
+            }
+
+            Err(reason)
+        }
+    }
+}
+
+/// Matches the elements of an array against the patterns of an array pattern.
+/// A rest pattern can take up any number of elements, in which case the
+/// patterns before and after it are matched against both ends of the array.
+fn destructure_array(
+    patterns: &[Pattern],
+    elements: &[Object],
+    bindings: &mut Vec<(String, Object)>,
+) -> Result<(), String> {
+    let rest = patterns
+        .iter()
+        .position(|p| matches!(p, Pattern::Rest { .. }));
+
+    let (before, after) = match rest {
+        Some(i) => (&patterns[..i], &patterns[i + 1..]),
+        None => (patterns, &patterns[..0]),
+    };
+
+    let want = before.len() + after.len();
+    if rest.is_none() && want != elements.len() {
+        return Err(format!(
+            "expected {} elements, got {}",
+            want,
+            elements.len()
+        ));
+    }
+
+    if want > elements.len() {
+        return Err(format!(
+            "expected at least {} elements, got {}",
+            want,
+            elements.len()
+        ));
+    }
+
+    let tail = elements.len() - after.len();
+    before
+        .iter()
+        .zip(&elements[..before.len()])
+        .chain(after.iter().zip(&elements[tail..]))
+        .try_for_each(|(pattern, element)| destructure(pattern, element, bindings))?;
+
+    if let Some(Pattern::Rest { name: Some(name) }) = rest.map(|i| &patterns[i]) {
+        bindings.push((
+            name.clone(),
+            Object::Array {
+                elements: elements[before.len()..tail].to_vec(),
+                frozen: false,
+            },
+        ));
+    }
+
+    Ok(())
+}
+
+fn literal_value(literal: &Node) -> Object {
+    eval(literal.clone(), &mut Environment::new())
+}
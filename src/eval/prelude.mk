@@ -0,0 +1,62 @@
+let reduce = fn(items, f, initial) {
+    let acc = initial;
+    for (item in items) {
+        acc = f(acc, item);
+    }
+    acc
+};
+
+let sum = fn(items) {
+    reduce(items, |acc, item| acc + item, 0)
+};
+
+let count = fn(items, f) {
+    reduce(items, |acc, item| if (f(item)) { acc + 1 } else { acc }, 0)
+};
+
+let any = fn(items, f) {
+    for (item in items) {
+        if (f(item)) {
+            return true;
+        }
+    }
+    false
+};
+
+let all = fn(items, f) {
+    for (item in items) {
+        if (!f(item)) {
+            return false;
+        }
+    }
+    true
+};
+
+let reverse = fn(items) {
+    reduce(items, |acc, item| [item, ...acc], [])
+};
+
+let flatten = fn(items) {
+    reduce(items, |acc, item| match (item) { [..inner] => [...acc, ...inner], _ => [...acc, item] }, [])
+};
+
+let flat_map = fn(items, f) {
+    flatten(map(items, f))
+};
+
+let unique = fn(items) {
+    reduce(items, |acc, item| if (contains(acc, item)) { acc } else { [...acc, item] }, [])
+};
+
+let group_by = fn(items, key) {
+    let groups = {};
+    for (item in items) {
+        let k = key(item);
+        let group = groups[k];
+        if (group == null) {
+            group = [];
+        }
+        groups[k] = [...group, item];
+    }
+    groups
+};
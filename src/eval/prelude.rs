@@ -0,0 +1,259 @@
+use super::object::Object;
+use super::{eval, is_unwinding, Environment};
+use crate::ast::Node;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use std::collections::HashSet;
+
+/// The standard library shared by all scripts, written in the language
+/// itself. It builds upon builtins such as `map`, `filter`, `find` and
+/// `contains`, which it doesn't redefine.
+const PRELUDE: &str = include_str!("prelude.mk");
+
+/// Evaluates the prelude into an Environment, before the script it belongs to
+/// runs. Returns the error raised by the prelude if it fails, e.g. because it
+/// doesn't fit in the memory limit.
+///
+/// Functions capture a copy of the whole environment they are created in, so
+/// every definition of the prelude is evaluated in an environment holding
+/// only the earlier definitions it refers to. Otherwise each function would
+/// carry copies of all the functions defined before it.
+pub fn load(environment: &mut Environment) -> Object {
+    let statements = match Parser::new(Lexer::new(PRELUDE)).parse_program() {
+        Node::Program { statements } => statements,
+        _ => vec![],
+    };
+
+    for statement in statements {
+        let mut scope = Environment::new();
+        for name in identifiers(&statement) {
+            if let Some(value) = environment.get(&name) {
+                if let Err(e) = scope.set(name, value) {
+                    return e.into();
+                }
+            }
+        }
+
+        let evaluated = eval(statement, &mut scope);
+        if is_unwinding(&evaluated) {
+            return evaluated;
+        }
+
+        for (name, value) in scope.storage.drain() {
+            if let Err(e) = environment.set(name, value) {
+                return e.into();
+            }
+        }
+    }
+
+    Object::Null
+}
+
+/// Returns the names a statement mentions.
+fn identifiers(statement: &Node) -> HashSet<String> {
+    let mut names = HashSet::new();
+    collect_identifiers(statement, &mut names);
+    names
+}
+
+fn collect_identifiers(node: &Node, names: &mut HashSet<String>) {
+    let mut visit = |node: &Node| collect_identifiers(node, names);
+
+    match node {
+        Node::Identifier { value } => {
+            names.insert(value.v.clone());
+        }
+        Node::Program { statements } | Node::BlockStatement { statements } => {
+            statements.iter().for_each(visit)
+        }
+        Node::ArrayLiteral { elements }
+        | Node::InterpolatedString { parts: elements }
+        | Node::HashLiteral { entries: elements }
+        | Node::StructStatement {
+            fields: elements, ..
+        } => elements.iter().for_each(visit),
+        Node::FunctionLiteral { parameters, body } => {
+            parameters.iter().for_each(&mut visit);
+            visit(body);
+        }
+        Node::HashPair { key: a, value: b }
+        | Node::DefaultParameter {
+            target: a,
+            default: b,
+        }
+        | Node::NamedArgument { name: a, value: b }
+        | Node::InfixExpression {
+            left: a, right: b, ..
+        }
+        | Node::IndexExpression {
+            left: a, index: b, ..
+        }
+        | Node::MemberExpression {
+            object: a,
+            property: b,
+            ..
+        }
+        | Node::AssignStatement {
+            target: a,
+            value: b,
+            ..
+        }
+        | Node::ConstStatement { name: a, value: b } => {
+            visit(a);
+            visit(b);
+        }
+        Node::SpreadExpression { value, .. }
+        | Node::RestParameter { name: value }
+        | Node::PrefixExpression { right: value, .. }
+        | Node::PostfixExpression { left: value, .. }
+        | Node::ThrowStatement { value, .. }
+        | Node::YieldStatement { value, .. }
+        | Node::ExportStatement { statement: value }
+        | Node::ImportStatement { alias: value, .. } => visit(value),
+        Node::LetStatement { name, value } => {
+            visit(name);
+            value.iter().for_each(|v| visit(v));
+        }
+        Node::ReturnStatement { value } | Node::ExpressionStatement { expression: value } => {
+            value.iter().for_each(|v| visit(v))
+        }
+        Node::IfExpression {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            visit(condition);
+            visit(consequence);
+            alternative.iter().for_each(|a| visit(a));
+        }
+        Node::CallExpression {
+            function,
+            arguments,
+            ..
+        } => {
+            visit(function);
+            arguments.iter().for_each(visit);
+        }
+        Node::StructLiteral { name, fields, .. } => {
+            visit(name);
+            fields.iter().for_each(|(_, value)| visit(value));
+        }
+        Node::EnumStatement { name, .. } => visit(name),
+        Node::ImplStatement { name, methods } => {
+            visit(name);
+            methods.iter().for_each(|(_, method)| visit(method));
+        }
+        Node::TryExpression {
+            body,
+            parameter,
+            handler,
+            finalizer,
+        } => {
+            visit(body);
+            for node in [parameter, handler, finalizer].iter().copied().flatten() {
+                visit(node);
+            }
+        }
+        Node::MatchExpression { subject, arms, .. } => {
+            visit(subject);
+            for arm in arms {
+                arm.guard.iter().for_each(|g| visit(g));
+                visit(&arm.body);
+            }
+        }
+        Node::ForStatement {
+            variables,
+            iterable,
+            body,
+        } => {
+            variables.iter().for_each(&mut visit);
+            visit(iterable);
+            visit(body);
+        }
+        Node::DestructuringPattern { .. }
+        | Node::IntegerLiteral { .. }
+        | Node::FloatLiteral { .. }
+        | Node::Boolean { .. }
+        | Node::NullLiteral
+        | Node::StringLiteral { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_eval(input: &str) -> Object {
+        let mut environment = Environment::new();
+        let loaded = load(&mut environment);
+        assert!(!matches!(loaded, Object::Error { .. }), "{:?}", loaded);
+
+        let program = Parser::new(Lexer::new(input)).parse_program();
+        eval(program, &mut environment)
+    }
+
+    #[test]
+    fn test_prelude_parses() {
+        let mut parser = Parser::new(Lexer::new(PRELUDE));
+        parser.parse_program();
+        assert!(parser.errors.is_empty(), "{:?}", parser.errors);
+        assert!(parser.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_prelude() {
+        let table = [
+            ("reduce([1, 2, 3], |acc, x| acc * x, 1);", "6"),
+            ("reduce([], |acc, x| acc + x, 5);", "5"),
+            ("sum(1..=4);", "10"),
+            ("count([1, 5, 2, 7], |x| x > 3);", "2"),
+            (
+                "[any([1, 2], |x| x > 1), any([], |x| true)];",
+                "[true, false]",
+            ),
+            (
+                "[all([1, 2], |x| x > 1), all([], |x| false)];",
+                "[false, true]",
+            ),
+            ("find([1, 4, 6], |x| x > 3);", "4"),
+            ("find([1], |x| x > 3);", "null"),
+            (
+                "[contains([1, [2]], [2]), contains(\"abc\", \"d\")];",
+                "[true, false]",
+            ),
+            ("reverse(0..3);", "[2, 1, 0]"),
+            ("flatten([[1, 2], 3, [], [[4]]]);", "[1, 2, 3, [4]]"),
+            ("flat_map([1, 2], |x| [x, x * 10]);", "[1, 10, 2, 20]"),
+            ("unique([3, 1, 3, 2, 1]);", "[3, 1, 2]"),
+            (
+                "group_by([1, 2, 3, 4, 5], |x| x - x / 2 * 2);",
+                "{0: [2, 4], 1: [1, 3, 5]}",
+            ),
+            // any and all stop at the first item which settles the result.
+            (
+                "[any([1, 2], |x| x == 1 || undefined), all([1, 2], |x| x > 1 && undefined)];",
+                "[true, false]",
+            ),
+            ("[1, 2, 3] |> reverse |> sum;", "6"),
+            ("sum(map(filter(1..=6, |x| x > 3), |x| x * 2));", "30"),
+            // Scripts may shadow the functions of the prelude.
+            ("let sum = |xs| 0; sum([1]);", "0"),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            assert_eq!(test_eval(input).inspect(), *output, "{}", input);
+        });
+
+        let errors = [
+            ("sum(5);", "cannot iterate over INTEGER"),
+            ("reduce([1]);", "wrong number of arguments: want=3, got=1"),
+        ];
+
+        errors
+            .iter()
+            .for_each(|(input, message)| match test_eval(input) {
+                Object::Error { value } => assert_eq!(value.message, *message),
+                object => panic!("Unexpected object, {:?}", object),
+            });
+    }
+}
@@ -0,0 +1,232 @@
+use super::builtins::{check_arity, invalid_argument};
+use super::error::{ErrorKind, RuntimeError};
+use super::object::Object;
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// A xoshiro256** pseudorandom number generator. It is fast and small, but
+/// not suitable for cryptography.
+struct Generator {
+    state: [u64; 4],
+}
+
+impl Generator {
+    /// Creates a Generator from a seed, spreading it over the whole state
+    /// with splitmix64, so that similar seeds give unrelated sequences.
+    fn new(mut seed: u64) -> Self {
+        let mut state = [0; 4];
+        for word in state.iter_mut() {
+            seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            *word = z ^ (z >> 31);
+        }
+
+        Self { state }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+
+        result
+    }
+
+    /// Returns a number from 0 up to, but not including, `bound`, which must
+    /// not be 0. Numbers from the uneven end of the generator's range are
+    /// rejected, so that every number is equally likely.
+    fn below(&mut self, bound: u64) -> u64 {
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let x = self.next_u64();
+            if x >= threshold {
+                return x % bound;
+            }
+        }
+    }
+}
+
+thread_local! {
+    static GENERATOR: RefCell<Option<Generator>> = const { RefCell::new(None) };
+}
+
+/// Seeds the random number generator used by scripts on the current thread,
+/// so that they draw the same numbers every time they run. Without a seed,
+/// the generator is seeded from the operating system's entropy.
+pub fn seed(seed: u64) {
+    GENERATOR.with(|g| *g.borrow_mut() = Some(Generator::new(seed)));
+}
+
+fn with_generator<T>(f: impl FnOnce(&mut Generator) -> T) -> T {
+    GENERATOR.with(|g| {
+        let mut generator = g.borrow_mut();
+        // RandomState is keyed with random numbers from the operating system.
+        let generator = generator
+            .get_or_insert_with(|| Generator::new(RandomState::new().build_hasher().finish()));
+        f(generator)
+    })
+}
+
+/// Returns a float from 0.0 up to, but not including, 1.0.
+pub fn random(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 0)?;
+    let bits = with_generator(Generator::next_u64) >> 11;
+    Ok(Object::Float {
+        value: bits as f64 / (1u64 << 53) as f64,
+    })
+}
+
+/// Returns an integer from `lo` up to and including `hi`.
+pub fn random_int(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 2)?;
+    let bound = |object: &Object| match object {
+        Object::Integer { value } => Ok(*value),
+        other => Err(invalid_argument("random_int", "INTEGER", other)),
+    };
+
+    let (lo, hi) = (bound(&args[0])?, bound(&args[1])?);
+    if lo > hi {
+        return Err(RuntimeError::new(
+            ErrorKind::InvalidArgument,
+            format!("empty range for `random_int`: {}..={}", lo, hi),
+        ));
+    }
+
+    // The width of the range wraps around to 0 when it spans every integer.
+    let width = hi.wrapping_sub(lo) as u64;
+    let offset = match width.checked_add(1) {
+        Some(count) => with_generator(|g| g.below(count)),
+        None => with_generator(Generator::next_u64),
+    };
+
+    Ok(Object::Integer {
+        value: lo.wrapping_add(offset as i64),
+    })
+}
+
+/// Returns a copy of an array with its elements in random order.
+pub fn shuffle(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    let mut elements = match &args[0] {
+        Object::Array { elements, .. } => elements.clone(),
+        other => return Err(invalid_argument("shuffle", "ARRAY", other)),
+    };
+
+    with_generator(|g| {
+        for i in (1..elements.len()).rev() {
+            let j = g.below(i as u64 + 1) as usize;
+            elements.swap(i, j);
+        }
+    });
+
+    Ok(Object::Array {
+        elements,
+        frozen: false,
+    })
+}
+
+/// Returns a random element of an array.
+pub fn choice(args: Vec<Object>) -> Result<Object, RuntimeError> {
+    check_arity(&args, 1)?;
+    let elements = match &args[0] {
+        Object::Array { elements, .. } => elements,
+        other => return Err(invalid_argument("choice", "ARRAY", other)),
+    };
+
+    if elements.is_empty() {
+        return Err(RuntimeError::new(
+            ErrorKind::IndexOutOfRange,
+            "cannot choose from an empty ARRAY".to_string(),
+        ));
+    }
+
+    let index = with_generator(|g| g.below(elements.len() as u64)) as usize;
+    Ok(elements[index].clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::{eval, Environment};
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn test_eval(input: &str) -> Object {
+        let program = Parser::new(Lexer::new(input)).parse_program();
+        eval(program, &mut Environment::new())
+    }
+
+    #[test]
+    fn test_seeded_random() {
+        let script = "[random(), random_int(1, 6), shuffle([1, 2, 3, 4]), choice([\"a\", \"b\"])];";
+
+        seed(42);
+        let first = test_eval(script);
+        seed(42);
+        assert_eq!(test_eval(script), first);
+        seed(43);
+        assert_ne!(test_eval(script), first);
+
+        let table = [
+            ("let r = random(); [!(r < 0), r < 1];", "[true, true]"),
+            (
+                "let i = random_int(-2, 2); [i > -3, i < 3];",
+                "[true, true]",
+            ),
+            ("random_int(5, 5);", "5"),
+            // The range may span every integer.
+            (
+                "random_int(-9223372036854775807 - 1, 9223372036854775807) * 0;",
+                "0",
+            ),
+            (
+                "let s = shuffle([1, 2, 3]); [len(s), contains(s, 1), contains(s, 2), contains(s, 3)];",
+                "[3, true, true, true]",
+            ),
+            ("shuffle([]);", "[]"),
+            ("[7].choice();", "7"),
+        ];
+
+        // Without a seed, the generator is seeded from entropy.
+        GENERATOR.with(|g| *g.borrow_mut() = None);
+        table.iter().for_each(|(input, output)| {
+            for _ in 0..20 {
+                assert_eq!(test_eval(input).inspect(), *output, "{}", input);
+            }
+        });
+    }
+
+    #[test]
+    fn test_random_errors() {
+        let errors = [
+            ("random(1);", "wrong number of arguments: want=0, got=1"),
+            ("random_int(1, 0);", "empty range for `random_int`: 1..=0"),
+            (
+                "random_int(1, 2.5);",
+                "argument to `random_int` must be INTEGER, got FLOAT",
+            ),
+            (
+                "shuffle(\"ab\");",
+                "argument to `shuffle` must be ARRAY, got STRING",
+            ),
+            ("choice([]);", "cannot choose from an empty ARRAY"),
+        ];
+
+        errors
+            .iter()
+            .for_each(|(input, message)| match test_eval(input) {
+                Object::Error { value } => assert_eq!(value.message, *message),
+                object => panic!("Unexpected object, {:?}", object),
+            });
+    }
+}
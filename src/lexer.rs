@@ -1,32 +1,55 @@
-use crate::tokens::{Token, TokenType};
+use crate::tokens::{Span, Token, TokenType};
 use std::iter::Peekable;
 use std::str::Chars;
 
 /// The lexer for our programming language. This struct basically transforms
 /// text input into a series of tokens which make it easier to parse whatever
 /// is being interpreted.
+#[derive(Clone)]
 pub struct Lexer<'a> {
     pub input: Peekable<Chars<'a>>,
     pub ch: char,
+    line: usize,
+    column: usize,
+    /// The number of braces left open in each expression interpolated into a
+    /// string, innermost last. The string resumes after the `}` which closes
+    /// the expression.
+    templates: Vec<usize>,
 }
 
 impl<'a> Lexer<'a> {
     /// Creates a new lexer and initialize it with an input string.
     pub fn new(input_string: &'a str) -> Self {
         let mut input = input_string.chars().peekable();
-        let initial = input.next().unwrap();
-        Self { input, ch: initial }
+        let initial = input.next().unwrap_or('\0');
+        Self {
+            input,
+            ch: initial,
+            line: 1,
+            column: 1,
+            templates: vec![],
+        }
     }
 
     /// Consumes characters from the input string to create the next token.
     pub fn next_token(&mut self) -> Token {
         self.eat_whitespace();
 
+        let span = Span::new(self.line, self.column);
+        let mut token = self.read_token();
+        token.span = span;
+        token
+    }
+
+    fn read_token(&mut self) -> Token {
         let token = match self.ch {
             '=' => {
                 if self.peek_char() == '=' {
                     self.read_char();
                     Token::new(TokenType::Equal, "==".to_string())
+                } else if self.peek_char() == '>' {
+                    self.read_char();
+                    Token::new(TokenType::FatArrow, "=>".to_string())
                 } else {
                     Token::new(TokenType::Assign, self.ch.into())
                 }
@@ -41,24 +64,102 @@ impl<'a> Lexer<'a> {
                     Token::new(TokenType::Bang, self.ch.into())
                 }
             }
+            '?' => match self.peek_char() {
+                '?' => {
+                    self.read_char();
+                    Token::new(TokenType::NullCoalesce, "??".to_string())
+                }
+                '.' => {
+                    self.read_char();
+                    Token::new(TokenType::OptionalChain, "?.".to_string())
+                }
+                '[' => {
+                    self.read_char();
+                    Token::new(TokenType::OptionalIndex, "?[".to_string())
+                }
+                _ => Token::new(TokenType::Question, self.ch.into()),
+            },
+            '&' => {
+                if self.peek_char() == '&' {
+                    self.read_char();
+                    Token::new(TokenType::And, "&&".to_string())
+                } else {
+                    Token::new(TokenType::Illegal, self.ch.into())
+                }
+            }
             '*' => Token::new(TokenType::Asterisk, self.ch.into()),
             '/' => Token::new(TokenType::Slash, self.ch.into()),
             '<' => Token::new(TokenType::LessThan, self.ch.into()),
             '>' => Token::new(TokenType::GreaterThan, self.ch.into()),
+            '.' => {
+                if self.peek_char() == '.' {
+                    self.read_char();
+                    if self.peek_char() == '=' {
+                        self.read_char();
+                        Token::new(TokenType::DotDotEqual, "..=".to_string())
+                    } else if self.peek_char() == '.' {
+                        self.read_char();
+                        Token::new(TokenType::DotDotDot, "...".to_string())
+                    } else {
+                        Token::new(TokenType::DotDot, "..".to_string())
+                    }
+                } else {
+                    Token::new(TokenType::Dot, self.ch.into())
+                }
+            }
             ',' => Token::new(TokenType::Comma, self.ch.into()),
             ';' => Token::new(TokenType::Semicolon, self.ch.into()),
             '(' => Token::new(TokenType::LParen, self.ch.into()),
             ')' => Token::new(TokenType::RParen, self.ch.into()),
-            '{' => Token::new(TokenType::LBrace, self.ch.into()),
-            '}' => Token::new(TokenType::RBrace, self.ch.into()),
-            '0' => Token::new(TokenType::EOF, "".into()),
+            '{' => {
+                if let Some(open) = self.templates.last_mut() {
+                    *open += 1;
+                }
+
+                Token::new(TokenType::LBrace, self.ch.into())
+            }
+            '}' => match self.templates.last_mut() {
+                Some(0) => {
+                    self.templates.pop();
+                    match self.read_string() {
+                        Some((v, true)) => Token::new(TokenType::TemplateMiddle, v),
+                        Some((v, false)) => Token::new(TokenType::TemplateTail, v),
+                        None => Token::new(TokenType::Illegal, "}".into()),
+                    }
+                }
+                Some(open) => {
+                    *open -= 1;
+                    Token::new(TokenType::RBrace, self.ch.into())
+                }
+                None => Token::new(TokenType::RBrace, self.ch.into()),
+            },
+            '[' => Token::new(TokenType::LBracket, self.ch.into()),
+            ']' => Token::new(TokenType::RBracket, self.ch.into()),
+            ':' => Token::new(TokenType::Colon, self.ch.into()),
+            '|' => {
+                if self.peek_char() == '>' {
+                    self.read_char();
+                    Token::new(TokenType::PipeForward, "|>".to_string())
+                } else if self.peek_char() == '|' {
+                    self.read_char();
+                    Token::new(TokenType::Or, "||".to_string())
+                } else {
+                    Token::new(TokenType::Pipe, self.ch.into())
+                }
+            }
+            '"' => match self.read_string() {
+                Some((v, true)) => Token::new(TokenType::TemplateHead, v),
+                Some((v, false)) => Token::new(TokenType::Str, v),
+                None => Token::new(TokenType::Illegal, "\"".into()),
+            },
+            '\0' => Token::new(TokenType::EOF, "".into()),
             _ => {
                 if is_letter(self.ch) {
-                    let v = self.read_ident(is_letter);
+                    // Identifiers may contain digits after their first letter.
+                    let v = self.read_ident(|ch| is_letter(ch) || is_digit(ch));
                     return Token::from(v.as_str());
                 } else if is_digit(self.ch) {
-                    let v = self.read_ident(is_digit);
-                    return Token::new(TokenType::Int, v);
+                    return self.read_number();
                 }
 
                 Token::new(TokenType::Illegal, self.ch.into())
@@ -70,16 +171,23 @@ impl<'a> Lexer<'a> {
     }
 
     fn read_char(&mut self) {
+        if self.ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
         match self.input.next() {
             Some(ch) => self.ch = ch,
-            None => self.ch = '0',
+            None => self.ch = '\0',
         }
     }
 
     fn peek_char(&mut self) -> char {
         match self.input.peek() {
             Some(ch) => *ch,
-            None => '0',
+            None => '\0',
         }
     }
 
@@ -89,6 +197,57 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// Reads a string literal, starting on its opening quote and stopping on
+    /// its closing quote. Returns None if the input ends before the string is
+    /// terminated.
+    ///
+    /// Reading stops early on the `{` of an interpolated `${`, in which case
+    /// the returned flag is true. Reading resumes from the `}` closing the
+    /// interpolated expression. `\$` stands for a literal `$`.
+    fn read_string(&mut self) -> Option<(String, bool)> {
+        let mut value = String::new();
+
+        loop {
+            self.read_char();
+            let next = self.peek_char();
+            match self.ch {
+                '"' => return Some((value, false)),
+                '$' if next == '{' => {
+                    self.read_char();
+                    self.templates.push(0);
+                    return Some((value, true));
+                }
+                '\0' => return None,
+                '\\' => {
+                    self.read_char();
+                    match self.ch {
+                        'n' => value.push('\n'),
+                        't' => value.push('\t'),
+                        'r' => value.push('\r'),
+                        '\0' => return None,
+                        ch => value.push(ch),
+                    }
+                }
+                ch => value.push(ch),
+            }
+        }
+    }
+
+    /// Reads an integer, or a float if its digits are followed by a `.` and
+    /// more digits. A `.` followed by anything else is left alone, so that
+    /// e.g. `1..5` is a range.
+    fn read_number(&mut self) -> Token {
+        let mut number = self.read_ident(is_digit);
+        if self.ch != '.' || !is_digit(self.peek_char()) {
+            return Token::new(TokenType::Int, number);
+        }
+
+        self.read_char();
+        number.push('.');
+        number.push_str(&self.read_ident(is_digit));
+        Token::new(TokenType::Float, number)
+    }
+
     fn read_ident(&mut self, conditional: fn(char) -> bool) -> String {
         let mut ident = String::new();
 
@@ -102,11 +261,11 @@ impl<'a> Lexer<'a> {
 }
 
 fn is_letter(ch: char) -> bool {
-    ('a'..='z').contains(&ch) || ('A'..='Z').contains(&ch) || ch == '_'
+    ch.is_ascii_alphabetic() || ch == '_'
 }
 
 fn is_digit(ch: char) -> bool {
-    ('0'..='9').contains(&ch)
+    ch.is_ascii_digit()
 }
 
 #[cfg(test)]
@@ -133,7 +292,28 @@ mod tests {
         }
 
         10 == 10;
-        10 != 9;";
+        10 != 9;
+        \"foobar\"
+        \"foo bar\"
+        [1, 2];
+        {\"foo\": \"bar\"}
+        0;
+        f()?;
+        a ?? null;
+        a?[1];
+        f?.(1);
+        for (i in 0..10) {}
+        1..=n;
+        match (x) { 1 | 2 => x }
+        fn(...r) {}
+        x |> f;
+        a && b || c;
+        const x = 1;
+        struct P { x } P { x: 1 }.x;
+        impl P {}
+        enum E { A(x) }
+        import \"lib.mk\" as lib;
+        export let y = 1;";
 
         let mut lexer = Lexer::new(input);
 
@@ -226,11 +406,228 @@ mod tests {
             Token::new(TokenType::NotEqual, String::from("!=")),
             Token::new(TokenType::Int, String::from("9")),
             Token::new(TokenType::Semicolon, String::from(";")),
+            Token::new(TokenType::Str, String::from("foobar")),
+            Token::new(TokenType::Str, String::from("foo bar")),
+            Token::new(TokenType::LBracket, String::from("[")),
+            Token::new(TokenType::Int, String::from("1")),
+            Token::new(TokenType::Comma, String::from(",")),
+            Token::new(TokenType::Int, String::from("2")),
+            Token::new(TokenType::RBracket, String::from("]")),
+            Token::new(TokenType::Semicolon, String::from(";")),
+            Token::new(TokenType::LBrace, String::from("{")),
+            Token::new(TokenType::Str, String::from("foo")),
+            Token::new(TokenType::Colon, String::from(":")),
+            Token::new(TokenType::Str, String::from("bar")),
+            Token::new(TokenType::RBrace, String::from("}")),
+            Token::new(TokenType::Int, String::from("0")),
+            Token::new(TokenType::Semicolon, String::from(";")),
+            Token::new(TokenType::Ident, String::from("f")),
+            Token::new(TokenType::LParen, String::from("(")),
+            Token::new(TokenType::RParen, String::from(")")),
+            Token::new(TokenType::Question, String::from("?")),
+            Token::new(TokenType::Semicolon, String::from(";")),
+            Token::new(TokenType::Ident, String::from("a")),
+            Token::new(TokenType::NullCoalesce, String::from("??")),
+            Token::new(TokenType::Null, String::from("null")),
+            Token::new(TokenType::Semicolon, String::from(";")),
+            Token::new(TokenType::Ident, String::from("a")),
+            Token::new(TokenType::OptionalIndex, String::from("?[")),
+            Token::new(TokenType::Int, String::from("1")),
+            Token::new(TokenType::RBracket, String::from("]")),
+            Token::new(TokenType::Semicolon, String::from(";")),
+            Token::new(TokenType::Ident, String::from("f")),
+            Token::new(TokenType::OptionalChain, String::from("?.")),
+            Token::new(TokenType::LParen, String::from("(")),
+            Token::new(TokenType::Int, String::from("1")),
+            Token::new(TokenType::RParen, String::from(")")),
+            Token::new(TokenType::Semicolon, String::from(";")),
+            Token::new(TokenType::For, String::from("for")),
+            Token::new(TokenType::LParen, String::from("(")),
+            Token::new(TokenType::Ident, String::from("i")),
+            Token::new(TokenType::In, String::from("in")),
+            Token::new(TokenType::Int, String::from("0")),
+            Token::new(TokenType::DotDot, String::from("..")),
+            Token::new(TokenType::Int, String::from("10")),
+            Token::new(TokenType::RParen, String::from(")")),
+            Token::new(TokenType::LBrace, String::from("{")),
+            Token::new(TokenType::RBrace, String::from("}")),
+            Token::new(TokenType::Int, String::from("1")),
+            Token::new(TokenType::DotDotEqual, String::from("..=")),
+            Token::new(TokenType::Ident, String::from("n")),
+            Token::new(TokenType::Semicolon, String::from(";")),
+            Token::new(TokenType::Match, String::from("match")),
+            Token::new(TokenType::LParen, String::from("(")),
+            Token::new(TokenType::Ident, String::from("x")),
+            Token::new(TokenType::RParen, String::from(")")),
+            Token::new(TokenType::LBrace, String::from("{")),
+            Token::new(TokenType::Int, String::from("1")),
+            Token::new(TokenType::Pipe, String::from("|")),
+            Token::new(TokenType::Int, String::from("2")),
+            Token::new(TokenType::FatArrow, String::from("=>")),
+            Token::new(TokenType::Ident, String::from("x")),
+            Token::new(TokenType::RBrace, String::from("}")),
+            Token::new(TokenType::Function, String::from("fn")),
+            Token::new(TokenType::LParen, String::from("(")),
+            Token::new(TokenType::DotDotDot, String::from("...")),
+            Token::new(TokenType::Ident, String::from("r")),
+            Token::new(TokenType::RParen, String::from(")")),
+            Token::new(TokenType::LBrace, String::from("{")),
+            Token::new(TokenType::RBrace, String::from("}")),
+            Token::new(TokenType::Ident, String::from("x")),
+            Token::new(TokenType::PipeForward, String::from("|>")),
+            Token::new(TokenType::Ident, String::from("f")),
+            Token::new(TokenType::Semicolon, String::from(";")),
+            Token::new(TokenType::Ident, String::from("a")),
+            Token::new(TokenType::And, String::from("&&")),
+            Token::new(TokenType::Ident, String::from("b")),
+            Token::new(TokenType::Or, String::from("||")),
+            Token::new(TokenType::Ident, String::from("c")),
+            Token::new(TokenType::Semicolon, String::from(";")),
+            Token::new(TokenType::Const, String::from("const")),
+            Token::new(TokenType::Ident, String::from("x")),
+            Token::new(TokenType::Assign, String::from("=")),
+            Token::new(TokenType::Int, String::from("1")),
+            Token::new(TokenType::Semicolon, String::from(";")),
+            Token::new(TokenType::Struct, String::from("struct")),
+            Token::new(TokenType::Ident, String::from("P")),
+            Token::new(TokenType::LBrace, String::from("{")),
+            Token::new(TokenType::Ident, String::from("x")),
+            Token::new(TokenType::RBrace, String::from("}")),
+            Token::new(TokenType::Ident, String::from("P")),
+            Token::new(TokenType::LBrace, String::from("{")),
+            Token::new(TokenType::Ident, String::from("x")),
+            Token::new(TokenType::Colon, String::from(":")),
+            Token::new(TokenType::Int, String::from("1")),
+            Token::new(TokenType::RBrace, String::from("}")),
+            Token::new(TokenType::Dot, String::from(".")),
+            Token::new(TokenType::Ident, String::from("x")),
+            Token::new(TokenType::Semicolon, String::from(";")),
+            Token::new(TokenType::Impl, String::from("impl")),
+            Token::new(TokenType::Ident, String::from("P")),
+            Token::new(TokenType::LBrace, String::from("{")),
+            Token::new(TokenType::RBrace, String::from("}")),
+            Token::new(TokenType::Enum, String::from("enum")),
+            Token::new(TokenType::Ident, String::from("E")),
+            Token::new(TokenType::LBrace, String::from("{")),
+            Token::new(TokenType::Ident, String::from("A")),
+            Token::new(TokenType::LParen, String::from("(")),
+            Token::new(TokenType::Ident, String::from("x")),
+            Token::new(TokenType::RParen, String::from(")")),
+            Token::new(TokenType::RBrace, String::from("}")),
+            Token::new(TokenType::Import, String::from("import")),
+            Token::new(TokenType::Str, String::from("lib.mk")),
+            Token::new(TokenType::As, String::from("as")),
+            Token::new(TokenType::Ident, String::from("lib")),
+            Token::new(TokenType::Semicolon, String::from(";")),
+            Token::new(TokenType::Export, String::from("export")),
+            Token::new(TokenType::Let, String::from("let")),
+            Token::new(TokenType::Ident, String::from("y")),
+            Token::new(TokenType::Assign, String::from("=")),
+            Token::new(TokenType::Int, String::from("1")),
+            Token::new(TokenType::Semicolon, String::from(";")),
         ];
 
+        assert_eq!(tokens.len(), expected_tokens.len());
         assert!(tokens
             .iter()
             .zip(expected_tokens.iter())
-            .all(|(t, e)| *t == *e));
+            .all(|(t, e)| (t.t, &t.v) == (e.t, &e.v)));
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let mut lexer = Lexer::new(r#""a\"b\\c\nd" "unterminated"#);
+
+        let token = lexer.next_token();
+        assert_eq!((token.t, token.v.as_str()), (TokenType::Str, "a\"b\\c\nd"));
+        assert_eq!(lexer.next_token().t, TokenType::Illegal);
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        let mut lexer = Lexer::new(r#""a ${x + {"k": "${y}"}["k"]} b \${c} ${z}!" }"#);
+
+        let expected = [
+            (TokenType::TemplateHead, "a "),
+            (TokenType::Ident, "x"),
+            (TokenType::Plus, "+"),
+            (TokenType::LBrace, "{"),
+            (TokenType::Str, "k"),
+            (TokenType::Colon, ":"),
+            (TokenType::TemplateHead, ""),
+            (TokenType::Ident, "y"),
+            (TokenType::TemplateTail, ""),
+            (TokenType::RBrace, "}"),
+            (TokenType::LBracket, "["),
+            (TokenType::Str, "k"),
+            (TokenType::RBracket, "]"),
+            (TokenType::TemplateMiddle, " b ${c} "),
+            (TokenType::Ident, "z"),
+            (TokenType::TemplateTail, "!"),
+            (TokenType::RBrace, "}"),
+            (TokenType::EOF, ""),
+        ];
+
+        expected.iter().for_each(|(t, v)| {
+            let token = lexer.next_token();
+            assert_eq!((token.t, token.v.as_str()), (*t, *v));
+        });
+
+        let mut lexer = Lexer::new(r#""a ${b"#);
+        lexer.next_token();
+        lexer.next_token();
+        assert_eq!(lexer.next_token().t, TokenType::EOF);
+
+        let mut lexer = Lexer::new(r#""a ${b} c"#);
+        lexer.next_token();
+        lexer.next_token();
+        assert_eq!(lexer.next_token().t, TokenType::Illegal);
+    }
+
+    #[test]
+    fn test_numbers() {
+        let mut lexer = Lexer::new("3.25 1..2 7.x2 0.5..=1.0");
+
+        let expected = [
+            (TokenType::Float, "3.25"),
+            (TokenType::Int, "1"),
+            (TokenType::DotDot, ".."),
+            (TokenType::Int, "2"),
+            (TokenType::Int, "7"),
+            (TokenType::Dot, "."),
+            (TokenType::Ident, "x2"),
+            (TokenType::Float, "0.5"),
+            (TokenType::DotDotEqual, "..="),
+            (TokenType::Float, "1.0"),
+            (TokenType::EOF, ""),
+        ];
+
+        expected.iter().for_each(|(t, v)| {
+            let token = lexer.next_token();
+            assert_eq!((token.t, token.v.as_str()), (*t, *v));
+        });
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let input = "let five = 5;
+  five + 10;";
+
+        let mut lexer = Lexer::new(input);
+
+        let expected = [
+            (1, 1),
+            (1, 5),
+            (1, 10),
+            (1, 12),
+            (1, 13),
+            (2, 3),
+            (2, 8),
+            (2, 10),
+        ];
+        expected.iter().for_each(|(line, column)| {
+            let token = lexer.next_token();
+            assert_eq!((token.span.line, token.span.column), (*line, *column));
+        });
     }
 }
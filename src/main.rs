@@ -1,4 +1,5 @@
 pub mod ast;
+pub mod cli;
 pub mod eval;
 pub mod lexer;
 pub mod parser;
@@ -6,5 +7,5 @@ pub mod repl;
 pub mod tokens;
 
 fn main() {
-    repl::start();
+    cli::run();
 }
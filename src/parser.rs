@@ -1,18 +1,31 @@
-use crate::ast::Node;
+use crate::ast::{MatchArm, Node, Pattern};
 use crate::lexer::Lexer;
-use crate::tokens::{Token, TokenType};
+use crate::tokens::{Span, Token, TokenType};
+use std::collections::HashMap;
+use std::fmt;
+
+/// How many tokens are read ahead of a `?` to find the `:` of a ternary
+/// expression.
+const TERNARY_LOOKAHEAD: usize = 256;
 
 /// The different types of operator precedence that can be encountered while
 /// parsing multi-layered expressions.
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub enum Precedence {
     Lowest,
+    Ternary,
+    Coalesce,
+    Pipeline,
+    LogicalOr,
+    LogicalAnd,
     Equals,
     LessGreater,
+    Range,
     Sum,
     Product,
     Prefix,
     Call,
+    Index,
 }
 
 /// All the possible errors that can be encountered by the parser.
@@ -22,10 +35,59 @@ pub enum ParserError {
     IdentExpected,
     AssignExpected,
     IntegerParsingFailed,
+    FloatParsingFailed,
     BooleanParsingFailed,
     GroupExpressionParsingFailed,
     IncorrectIfStatement,
     IncorrectFunctionDeclaration,
+    IncorrectHashLiteral,
+    IncorrectIndexExpression,
+    IncorrectTryExpression,
+    IncorrectOptionalChain,
+    IncorrectArrayLiteral,
+    IncorrectForStatement,
+    IncorrectMatchExpression,
+    IncorrectPattern,
+    IncorrectTernaryExpression,
+    InvalidAssignmentTarget,
+    IncorrectStructDeclaration,
+    IncorrectStructLiteral,
+    IncorrectMemberExpression,
+    IncorrectImplBlock,
+    IncorrectEnumDeclaration,
+    IncorrectInterpolation,
+    IncorrectImportStatement,
+    IncorrectExportStatement,
+    ExportOutsideTopLevel,
+    ConstantReassigned { name: String, span: Span },
+}
+
+/// Problems the parser can detect in code which is valid, but most likely
+/// not what was meant.
+#[derive(Debug)]
+pub enum ParserWarning {
+    UnreachableMatchArm { span: Span },
+    NonExhaustiveMatch { span: Span },
+    MissingVariants { span: Span, variants: Vec<String> },
+}
+
+impl fmt::Display for ParserWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParserWarning::UnreachableMatchArm { span } => {
+                write!(f, "warning: unreachable match arm at {}", span)
+            }
+            ParserWarning::NonExhaustiveMatch { span } => {
+                write!(f, "warning: match at {} is not exhaustive", span)
+            }
+            ParserWarning::MissingVariants { span, variants } => write!(
+                f,
+                "warning: match at {} doesn't handle {}",
+                span,
+                variants.join(", ")
+            ),
+        }
+    }
 }
 
 /// The parser for our programming language. The parser uses a lexer to create
@@ -39,7 +101,24 @@ pub struct Parser<'a> {
     lexer: Lexer<'a>,
     curr_token: Token,
     peek_token: Token,
+    /// The token after the peek token, which tells the ternary `?` apart
+    /// from the postfix `?` operator.
+    next_peek_token: Token,
     pub errors: Vec<ParserError>,
+    pub warnings: Vec<ParserWarning>,
+    /// Set while parsing the guard of a match arm, where `x =>` ends the
+    /// guard rather than starting a lambda.
+    parsing_guard: bool,
+    /// Set while parsing the condition of an if expression, where `x {`
+    /// starts the consequence rather than a struct literal.
+    parsing_condition: bool,
+    /// The postfix `?` operators found in the first branch of a ternary
+    /// expression while looking for its `:`. Cleared before every top-level
+    /// statement.
+    postfix_questions: Vec<Span>,
+    /// The variants of the enums declared so far, which tell whether a match
+    /// handles every variant of an enum.
+    enums: HashMap<String, Vec<String>>,
 }
 
 impl<'a> Parser<'a> {
@@ -50,9 +129,16 @@ impl<'a> Parser<'a> {
             lexer,
             curr_token: Token::default(),
             peek_token: Token::default(),
+            next_peek_token: Token::default(),
             errors: vec![],
+            warnings: vec![],
+            parsing_guard: false,
+            parsing_condition: false,
+            postfix_questions: vec![],
+            enums: HashMap::new(),
         };
 
+        parser.next_token();
         parser.next_token();
         parser.next_token();
         parser
@@ -64,7 +150,15 @@ impl<'a> Parser<'a> {
         let mut statements = vec![];
 
         while !self.finished() {
-            match self.parse_statement() {
+            self.postfix_questions.clear();
+
+            // Only the top level of a file can export declarations.
+            let parsed = match self.curr_token.t {
+                TokenType::Export => self.parse_export_statement(),
+                _ => self.parse_statement(),
+            };
+
+            match parsed {
                 Ok(stmt) => statements.push(stmt),
                 Err(e) => self.errors.push(e),
             };
@@ -72,7 +166,9 @@ impl<'a> Parser<'a> {
             self.next_token();
         }
 
-        Node::Program { statements }
+        let program = Node::Program { statements };
+        self.check_constants(&program, &mut vec![HashMap::new()]);
+        program
     }
 
     fn finished(&self) -> bool {
@@ -82,20 +178,241 @@ impl<'a> Parser<'a> {
     fn parse_statement(&mut self) -> Result<Node, ParserError> {
         match self.curr_token.t {
             TokenType::Let => self.parse_let_statement(),
+            TokenType::Const => self.parse_const_statement(),
+            TokenType::Struct => self.parse_struct_statement(),
+            TokenType::Impl => self.parse_impl_statement(),
+            TokenType::Enum => self.parse_enum_statement(),
+            TokenType::Import => self.parse_import_statement(),
+            TokenType::Export => Err(ParserError::ExportOutsideTopLevel),
             TokenType::Return => self.parse_return_statement(),
+            TokenType::Throw => self.parse_throw_statement(),
+            TokenType::Yield => self.parse_yield_statement(),
+            TokenType::For => self.parse_for_statement(),
             _ => self.parse_expression_statement(),
         }
     }
 
     fn parse_let_statement(&mut self) -> Result<Node, ParserError> {
+        let (name, value) = self.parse_binding()?;
+
+        Ok(Node::LetStatement {
+            name: Box::new(name),
+            value: Some(Box::new(value)),
+        })
+    }
+
+    fn parse_const_statement(&mut self) -> Result<Node, ParserError> {
+        let (name, value) = self.parse_binding()?;
+
+        Ok(Node::ConstStatement {
+            name: Box::new(name),
+            value: Box::new(value),
+        })
+    }
+
+    fn parse_struct_statement(&mut self) -> Result<Node, ParserError> {
         if !self.expect_peek(TokenType::Ident) {
-            return Err(ParserError::IdentExpected);
+            return Err(ParserError::IncorrectStructDeclaration);
         }
 
-        let ident = Node::Identifier {
+        let name = Node::Identifier {
             value: self.curr_token.clone(),
         };
 
+        if !self.expect_peek(TokenType::LBrace) {
+            return Err(ParserError::IncorrectStructDeclaration);
+        }
+
+        let mut fields = vec![];
+        while self.peek_token.t != TokenType::RBrace {
+            if !self.expect_peek(TokenType::Ident) {
+                return Err(ParserError::IncorrectStructDeclaration);
+            }
+
+            fields.push(Node::Identifier {
+                value: self.curr_token.clone(),
+            });
+
+            if self.peek_token.t != TokenType::RBrace && !self.expect_peek(TokenType::Comma) {
+                return Err(ParserError::IncorrectStructDeclaration);
+            }
+        }
+
+        self.next_token();
+        if self.peek_token.t == TokenType::Semicolon {
+            self.next_token();
+        }
+
+        Ok(Node::StructStatement {
+            name: Box::new(name),
+            fields,
+        })
+    }
+
+    fn parse_import_statement(&mut self) -> Result<Node, ParserError> {
+        let span = self.curr_token.span;
+
+        if !self.expect_peek(TokenType::Str) {
+            return Err(ParserError::IncorrectImportStatement);
+        }
+
+        let path = self.curr_token.v.clone();
+        if !self.expect_peek(TokenType::As) || !self.expect_peek(TokenType::Ident) {
+            return Err(ParserError::IncorrectImportStatement);
+        }
+
+        let alias = Node::Identifier {
+            value: self.curr_token.clone(),
+        };
+
+        if self.peek_token.t == TokenType::Semicolon {
+            self.next_token();
+        }
+
+        Ok(Node::ImportStatement {
+            path,
+            alias: Box::new(alias),
+            span,
+        })
+    }
+
+    fn parse_export_statement(&mut self) -> Result<Node, ParserError> {
+        self.next_token();
+        let statement = match self.curr_token.t {
+            TokenType::Let | TokenType::Const | TokenType::Struct | TokenType::Enum => {
+                self.parse_statement()?
+            }
+            _ => return Err(ParserError::IncorrectExportStatement),
+        };
+
+        Ok(Node::ExportStatement {
+            statement: Box::new(statement),
+        })
+    }
+
+    fn parse_enum_statement(&mut self) -> Result<Node, ParserError> {
+        if !self.expect_peek(TokenType::Ident) {
+            return Err(ParserError::IncorrectEnumDeclaration);
+        }
+
+        let name = Node::Identifier {
+            value: self.curr_token.clone(),
+        };
+
+        if !self.expect_peek(TokenType::LBrace) {
+            return Err(ParserError::IncorrectEnumDeclaration);
+        }
+
+        let mut variants = vec![];
+        while self.peek_token.t != TokenType::RBrace {
+            if !self.expect_peek(TokenType::Ident) {
+                return Err(ParserError::IncorrectEnumDeclaration);
+            }
+
+            let variant = Node::Identifier {
+                value: self.curr_token.clone(),
+            };
+
+            let mut fields = None;
+            if self.peek_token.t == TokenType::LParen {
+                self.next_token();
+                self.next_token();
+                fields = Some(
+                    self.parse_parameters(TokenType::RParen)
+                        .map_err(|_| ParserError::IncorrectEnumDeclaration)?,
+                );
+
+                if !fields
+                    .iter()
+                    .flatten()
+                    .all(|f| matches!(f, Node::Identifier { .. }))
+                {
+                    return Err(ParserError::IncorrectEnumDeclaration);
+                }
+            }
+
+            variants.push((variant, fields));
+            if self.peek_token.t != TokenType::RBrace && !self.expect_peek(TokenType::Comma) {
+                return Err(ParserError::IncorrectEnumDeclaration);
+            }
+        }
+
+        self.next_token();
+        if self.peek_token.t == TokenType::Semicolon {
+            self.next_token();
+        }
+
+        self.enums.insert(
+            name.as_string(),
+            variants.iter().map(|(v, _)| v.as_string()).collect(),
+        );
+
+        Ok(Node::EnumStatement {
+            name: Box::new(name),
+            variants,
+        })
+    }
+
+    fn parse_impl_statement(&mut self) -> Result<Node, ParserError> {
+        if !self.expect_peek(TokenType::Ident) {
+            return Err(ParserError::IncorrectImplBlock);
+        }
+
+        let name = Node::Identifier {
+            value: self.curr_token.clone(),
+        };
+
+        if !self.expect_peek(TokenType::LBrace) {
+            return Err(ParserError::IncorrectImplBlock);
+        }
+
+        let mut methods = vec![];
+        while self.peek_token.t != TokenType::RBrace {
+            if self.peek_token.t == TokenType::Semicolon {
+                self.next_token();
+                continue;
+            }
+
+            if !self.expect_peek(TokenType::Function) || !self.expect_peek(TokenType::Ident) {
+                return Err(ParserError::IncorrectImplBlock);
+            }
+
+            let method = Node::Identifier {
+                value: self.curr_token.clone(),
+            };
+            methods.push((method, self.parse_function_literal()?));
+        }
+
+        self.next_token();
+        if self.peek_token.t == TokenType::Semicolon {
+            self.next_token();
+        }
+
+        Ok(Node::ImplStatement {
+            name: Box::new(name),
+            methods,
+        })
+    }
+
+    /// Parses the name or pattern of a let or const statement, and the value
+    /// bound to it.
+    fn parse_binding(&mut self) -> Result<(Node, Node), ParserError> {
+        let ident = match self.peek_token.t {
+            TokenType::LBracket | TokenType::LBrace => {
+                self.next_token();
+                self.parse_destructuring_pattern()?
+            }
+            _ => {
+                if !self.expect_peek(TokenType::Ident) {
+                    return Err(ParserError::IdentExpected);
+                }
+
+                Node::Identifier {
+                    value: self.curr_token.clone(),
+                }
+            }
+        };
+
         if !self.expect_peek(TokenType::Assign) {
             return Err(ParserError::AssignExpected);
         }
@@ -107,10 +424,7 @@ impl<'a> Parser<'a> {
             self.next_token();
         }
 
-        Ok(Node::LetStatement {
-            name: Box::new(ident),
-            value: Some(Box::new(value)),
-        })
+        Ok((ident, value))
     }
 
     fn parse_return_statement(&mut self) -> Result<Node, ParserError> {
@@ -125,9 +439,87 @@ impl<'a> Parser<'a> {
         })
     }
 
+    fn parse_throw_statement(&mut self) -> Result<Node, ParserError> {
+        let span = self.curr_token.span;
+
+        self.next_token();
+        let value = self.parse_expression(Precedence::Lowest)?;
+        if self.peek_token.t == TokenType::Semicolon {
+            self.next_token();
+        }
+
+        Ok(Node::ThrowStatement {
+            value: Box::new(value),
+            span,
+        })
+    }
+
+    fn parse_yield_statement(&mut self) -> Result<Node, ParserError> {
+        let span = self.curr_token.span;
+
+        self.next_token();
+        let value = self.parse_expression(Precedence::Lowest)?;
+        if self.peek_token.t == TokenType::Semicolon {
+            self.next_token();
+        }
+
+        Ok(Node::YieldStatement {
+            value: Box::new(value),
+            span,
+        })
+    }
+
+    fn parse_for_statement(&mut self) -> Result<Node, ParserError> {
+        if !self.expect_peek(TokenType::LParen) || !self.expect_peek(TokenType::Ident) {
+            return Err(ParserError::IncorrectForStatement);
+        }
+
+        let mut variables = vec![Node::Identifier {
+            value: self.curr_token.clone(),
+        }];
+
+        if self.peek_token.t == TokenType::Comma {
+            self.next_token();
+            if !self.expect_peek(TokenType::Ident) {
+                return Err(ParserError::IncorrectForStatement);
+            }
+
+            variables.push(Node::Identifier {
+                value: self.curr_token.clone(),
+            });
+        }
+
+        if !self.expect_peek(TokenType::In) {
+            return Err(ParserError::IncorrectForStatement);
+        }
+
+        self.next_token();
+        let iterable = self.parse_expression(Precedence::Lowest)?;
+
+        if !self.expect_peek(TokenType::RParen) || !self.expect_peek(TokenType::LBrace) {
+            return Err(ParserError::IncorrectForStatement);
+        }
+
+        let body = self.parse_block_statement()?;
+        if self.peek_token.t == TokenType::Semicolon {
+            self.next_token();
+        }
+
+        Ok(Node::ForStatement {
+            variables,
+            iterable: Box::new(iterable),
+            body: Box::new(body),
+        })
+    }
+
     fn parse_expression_statement(&mut self) -> Result<Node, ParserError> {
+        let expression = self.parse_expression(Precedence::Lowest)?;
+        if self.peek_token.t == TokenType::Assign {
+            return self.parse_assign_statement(expression);
+        }
+
         let expr = Node::ExpressionStatement {
-            expression: Some(Box::new(self.parse_expression(Precedence::Lowest)?)),
+            expression: Some(Box::new(expression)),
         };
 
         if self.peek_token.t == TokenType::Semicolon {
@@ -136,17 +528,81 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
+    /// Parses the value assigned to a variable, or to an element of the array
+    /// or hash it holds.
+    fn parse_assign_statement(&mut self, target: Node) -> Result<Node, ParserError> {
+        let mut root = &target;
+        loop {
+            root = match root {
+                Node::IndexExpression {
+                    left,
+                    optional: false,
+                    ..
+                } => left,
+                Node::MemberExpression {
+                    object,
+                    optional: false,
+                    ..
+                } => object,
+                _ => break,
+            };
+        }
+
+        if !matches!(root, Node::Identifier { .. }) {
+            return Err(ParserError::InvalidAssignmentTarget);
+        }
+
+        self.next_token();
+        let span = self.curr_token.span;
+
+        self.next_token();
+        let value = self.parse_expression(Precedence::Lowest)?;
+        if self.peek_token.t == TokenType::Semicolon {
+            self.next_token();
+        }
+
+        Ok(Node::AssignStatement {
+            target: Box::new(target),
+            value: Box::new(value),
+            span,
+        })
+    }
+
     fn parse_expression(&mut self, precedence: Precedence) -> Result<Node, ParserError> {
         let mut left_exp = match self.curr_token.t {
+            TokenType::Ident if self.peek_token.t == TokenType::FatArrow && !self.parsing_guard => {
+                self.parse_arrow_lambda()
+            }
+            TokenType::Ident
+                if self.peek_token.t == TokenType::LBrace && !self.parsing_condition =>
+            {
+                self.parse_struct_literal()
+            }
             TokenType::Ident => Ok(Node::Identifier {
                 value: self.curr_token.clone(),
             }),
+            TokenType::Pipe => self.parse_pipe_lambda(),
+            TokenType::Or => {
+                // `||` is a lambda without parameters.
+                self.next_token();
+                self.parse_lambda_body(vec![])
+            }
             TokenType::Int => self.parse_integer_literal(),
+            TokenType::Float => self.parse_float_literal(),
+            TokenType::Str => Ok(Node::StringLiteral {
+                value: self.curr_token.v.clone(),
+            }),
+            TokenType::TemplateHead => self.parse_interpolated_string(),
+            TokenType::LBrace => self.parse_hash_literal(),
+            TokenType::LBracket => self.parse_array_literal(),
             TokenType::Minus | TokenType::Bang => self.parse_prefix_expression(),
             TokenType::True | TokenType::False => self.parse_boolean_expression(),
+            TokenType::Null => Ok(Node::NullLiteral),
             TokenType::LParen => self.parse_grouped_expression(),
             TokenType::If => self.parse_if_expression(),
             TokenType::Function => self.parse_function_literal(),
+            TokenType::Try => self.parse_try_expression(),
+            TokenType::Match => self.parse_match_expression(),
             _ => Err(ParserError::TokenUnrecognized),
         }?;
 
@@ -173,6 +629,16 @@ impl<'a> Parser<'a> {
         })
     }
 
+    fn parse_float_literal(&mut self) -> Result<Node, ParserError> {
+        Ok(Node::FloatLiteral {
+            value: self
+                .curr_token
+                .v
+                .parse()
+                .map_err(|_| ParserError::FloatParsingFailed)?,
+        })
+    }
+
     fn parse_prefix_expression(&mut self) -> Result<Node, ParserError> {
         let prefix_token = self.curr_token.clone();
 
@@ -180,6 +646,7 @@ impl<'a> Parser<'a> {
         Ok(Node::PrefixExpression {
             operator: prefix_token.v,
             right: Box::new(self.parse_expression(Precedence::Prefix)?),
+            span: prefix_token.span,
         })
     }
 
@@ -188,8 +655,41 @@ impl<'a> Parser<'a> {
         // with grouped expressions.
         // TODO: This is obviously poorly designed and needs to be revamped.
         if self.curr_token.t == TokenType::LParen {
+            let span = self.curr_token.span;
             self.next_token();
-            return self.parse_call_expression(left);
+            return self.parse_call_expression(left, span, false);
+        }
+
+        if self.curr_token.t == TokenType::LBracket {
+            return self.parse_index_expression(left, false);
+        }
+
+        if self.curr_token.t == TokenType::OptionalIndex {
+            return self.parse_index_expression(left, true);
+        }
+
+        if self.curr_token.t == TokenType::OptionalChain {
+            return self.parse_optional_chain(left);
+        }
+
+        if self.curr_token.t == TokenType::Dot {
+            return self.parse_member_expression(left, false);
+        }
+
+        if self.curr_token.t == TokenType::PipeForward {
+            return self.parse_pipeline(left);
+        }
+
+        if self.is_ternary(0) {
+            return self.parse_ternary_expression(left);
+        }
+
+        if self.curr_token.t == TokenType::Question {
+            return Ok(Node::PostfixExpression {
+                left: Box::new(left),
+                operator: self.curr_token.v.clone(),
+                span: self.curr_token.span,
+            });
         }
 
         let operator = self.curr_token.clone();
@@ -200,19 +700,21 @@ impl<'a> Parser<'a> {
             left: Box::new(left),
             operator: operator.v,
             right: Box::new(self.parse_expression(precedence)?),
+            span: operator.span,
         })
     }
 
     fn parse_boolean_expression(&mut self) -> Result<Node, ParserError> {
         Ok(Node::Boolean {
-            value: self.curr_token == Token::new(TokenType::True, String::from("true")),
+            value: self.curr_token.t == TokenType::True,
         })
     }
 
     fn parse_grouped_expression(&mut self) -> Result<Node, ParserError> {
         self.next_token();
 
-        let exp = self.parse_expression(Precedence::Lowest)?;
+        // Struct literals are allowed in parentheses, even in a condition.
+        let exp = self.within_condition(false, |p| p.parse_expression(Precedence::Lowest))?;
         if !self.expect_peek(TokenType::RParen) {
             return Err(ParserError::GroupExpressionParsingFailed);
         }
@@ -222,7 +724,7 @@ impl<'a> Parser<'a> {
 
     fn parse_if_expression(&mut self) -> Result<Node, ParserError> {
         self.next_token();
-        let condition = self.parse_expression(Precedence::Lowest)?;
+        let condition = self.within_condition(true, |p| p.parse_expression(Precedence::Lowest))?;
 
         if !self.expect_peek(TokenType::LBrace) {
             return Err(ParserError::IncorrectIfStatement);
@@ -233,11 +735,22 @@ impl<'a> Parser<'a> {
         if self.peek_token.t == TokenType::Else {
             self.next_token();
 
-            if !self.expect_peek(TokenType::LBrace) {
-                return Err(ParserError::IncorrectIfStatement);
-            }
+            // `else if` chains nest the following if expression in the
+            // alternative block.
+            let alternative = if self.peek_token.t == TokenType::If {
+                self.next_token();
+                Node::BlockStatement {
+                    statements: vec![Node::ExpressionStatement {
+                        expression: Some(Box::new(self.parse_if_expression()?)),
+                    }],
+                }
+            } else {
+                if !self.expect_peek(TokenType::LBrace) {
+                    return Err(ParserError::IncorrectIfStatement);
+                }
 
-            let alternative = self.parse_block_statement()?;
+                self.parse_block_statement()?
+            };
 
             Ok(Node::IfExpression {
                 condition: Box::new(condition),
@@ -253,12 +766,710 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Parses the branches of a `cond ? a : b` expression, which is shorthand
+    /// for an if expression.
+    fn parse_ternary_expression(&mut self, condition: Node) -> Result<Node, ParserError> {
+        let branch = |expression| Node::BlockStatement {
+            statements: vec![Node::ExpressionStatement {
+                expression: Some(Box::new(expression)),
+            }],
+        };
+
+        self.next_token();
+        let consequence = self.parse_expression(Precedence::Lowest)?;
+        if !self.expect_peek(TokenType::Colon) {
+            return Err(ParserError::IncorrectTernaryExpression);
+        }
+
+        self.next_token();
+        let alternative = self.parse_expression(Precedence::Lowest)?;
+
+        Ok(Node::IfExpression {
+            condition: Box::new(condition),
+            consequence: Box::new(branch(consequence)),
+            alternative: Some(Box::new(branch(alternative))),
+        })
+    }
+
+    fn parse_try_expression(&mut self) -> Result<Node, ParserError> {
+        if !self.expect_peek(TokenType::LBrace) {
+            return Err(ParserError::IncorrectTryExpression);
+        }
+
+        let body = self.parse_block_statement()?;
+
+        let mut parameter = None;
+        let mut handler = None;
+        if self.peek_token.t == TokenType::Catch {
+            self.next_token();
+
+            if !self.expect_peek(TokenType::LParen) || !self.expect_peek(TokenType::Ident) {
+                return Err(ParserError::IncorrectTryExpression);
+            }
+
+            parameter = Some(Box::new(Node::Identifier {
+                value: self.curr_token.clone(),
+            }));
+
+            if !self.expect_peek(TokenType::RParen) || !self.expect_peek(TokenType::LBrace) {
+                return Err(ParserError::IncorrectTryExpression);
+            }
+
+            handler = Some(Box::new(self.parse_block_statement()?));
+        }
+
+        let mut finalizer = None;
+        if self.peek_token.t == TokenType::Finally {
+            self.next_token();
+
+            if !self.expect_peek(TokenType::LBrace) {
+                return Err(ParserError::IncorrectTryExpression);
+            }
+
+            finalizer = Some(Box::new(self.parse_block_statement()?));
+        }
+
+        if handler.is_none() && finalizer.is_none() {
+            return Err(ParserError::IncorrectTryExpression);
+        }
+
+        Ok(Node::TryExpression {
+            body: Box::new(body),
+            parameter,
+            handler,
+            finalizer,
+        })
+    }
+
+    fn parse_match_expression(&mut self) -> Result<Node, ParserError> {
+        let span = self.curr_token.span;
+
+        if !self.expect_peek(TokenType::LParen) {
+            return Err(ParserError::IncorrectMatchExpression);
+        }
+
+        self.next_token();
+        let subject = self.parse_expression(Precedence::Lowest)?;
+        if !self.expect_peek(TokenType::RParen) || !self.expect_peek(TokenType::LBrace) {
+            return Err(ParserError::IncorrectMatchExpression);
+        }
+
+        let mut arms = vec![];
+        let mut spans = vec![];
+        while self.peek_token.t != TokenType::RBrace {
+            self.next_token();
+            spans.push(self.curr_token.span);
+            let pattern = self.parse_pattern()?;
+
+            let mut guard = None;
+            if self.peek_token.t == TokenType::If {
+                self.next_token();
+                self.next_token();
+                self.parsing_guard = true;
+                let parsed = self.parse_expression(Precedence::Lowest);
+                self.parsing_guard = false;
+                guard = Some(Box::new(parsed?));
+            }
+
+            if !self.expect_peek(TokenType::FatArrow) {
+                return Err(ParserError::IncorrectMatchExpression);
+            }
+
+            // Arms which need more than an expression use a block, which
+            // doesn't need to be followed by a comma.
+            self.next_token();
+            let body = if self.curr_token.t == TokenType::LBrace {
+                let body = self.parse_block_statement()?;
+                if self.peek_token.t == TokenType::Comma {
+                    self.next_token();
+                }
+
+                body
+            } else {
+                let body = self.parse_expression(Precedence::Lowest)?;
+                if self.peek_token.t != TokenType::RBrace && !self.expect_peek(TokenType::Comma) {
+                    return Err(ParserError::IncorrectMatchExpression);
+                }
+
+                body
+            };
+
+            arms.push(MatchArm {
+                pattern,
+                guard,
+                body: Box::new(body),
+            });
+        }
+
+        self.next_token();
+        self.check_match_arms(&arms, &spans, span);
+
+        Ok(Node::MatchExpression {
+            subject: Box::new(subject),
+            arms,
+            span,
+        })
+    }
+
+    /// Warns about arms which can never be reached, because an earlier arm
+    /// always matches first, and about matches which may not match at all.
+    /// A match on an enum declared earlier is exhaustive once every variant
+    /// of the enum is handled. Matches on enums this parser hasn't seen, such
+    /// as ones declared on an earlier REPL line or imported from a module,
+    /// aren't warned about.
+    fn check_match_arms(&mut self, arms: &[MatchArm], spans: &[Span], span: Span) {
+        let mut exhaustive = false;
+        let mut booleans = (false, false);
+        let mut variants: Vec<(String, String)> = vec![];
+
+        for (i, arm) in arms.iter().enumerate() {
+            let shadowed = arms[..i]
+                .iter()
+                .any(|a| a.guard.is_none() && a.pattern == arm.pattern);
+            if exhaustive || shadowed {
+                self.warnings
+                    .push(ParserWarning::UnreachableMatchArm { span: spans[i] });
+            }
+
+            if arm.guard.is_some() {
+                continue;
+            }
+
+            match &arm.pattern {
+                Pattern::Literal {
+                    value: Node::Boolean { value: true },
+                } => booleans.0 = true,
+                Pattern::Literal {
+                    value: Node::Boolean { value: false },
+                } => booleans.1 = true,
+                pattern => exhaustive |= pattern.is_irrefutable(),
+            }
+
+            self.handled_variants(&arm.pattern, &mut variants);
+            exhaustive |=
+                booleans == (true, true) || self.missing_variants(&variants) == Some(vec![]);
+        }
+
+        if exhaustive
+            || arms
+                .iter()
+                .any(|arm| self.matches_unknown_enum(&arm.pattern))
+        {
+            return;
+        }
+
+        match self.missing_variants(&variants) {
+            Some(missing) => self.warnings.push(ParserWarning::MissingVariants {
+                span,
+                variants: missing,
+            }),
+            None => self
+                .warnings
+                .push(ParserWarning::NonExhaustiveMatch { span }),
+        }
+    }
+
+    /// Collects the enum variants a pattern always matches, along with the
+    /// enum they belong to. Variants given without the name of their enum
+    /// are looked up in the enums declared so far.
+    fn handled_variants(&self, pattern: &Pattern, handled: &mut Vec<(String, String)>) {
+        match pattern {
+            Pattern::Alternative { patterns } => patterns
+                .iter()
+                .for_each(|p| self.handled_variants(p, handled)),
+            Pattern::Variant {
+                enum_name,
+                variant,
+                fields,
+            } => {
+                if !fields.iter().flatten().all(|f| f.is_irrefutable()) {
+                    return;
+                }
+
+                let enum_name = enum_name.clone().or_else(|| {
+                    self.enums
+                        .iter()
+                        .find(|(_, variants)| variants.contains(variant))
+                        .map(|(name, _)| name.clone())
+                });
+
+                if let Some(enum_name) = enum_name {
+                    handled.push((enum_name, variant.clone()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns true if a pattern matches a variant of an enum which wasn't
+    /// declared to this parser.
+    fn matches_unknown_enum(&self, pattern: &Pattern) -> bool {
+        match pattern {
+            Pattern::Alternative { patterns } => {
+                patterns.iter().any(|p| self.matches_unknown_enum(p))
+            }
+            Pattern::Variant {
+                enum_name: Some(enum_name),
+                ..
+            } => !self.enums.contains_key(enum_name),
+            Pattern::Variant { variant, .. } => {
+                !self.enums.values().flatten().any(|v| v == variant)
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the variants of the enum a match is on which aren't handled
+    /// yet, or None if the match isn't on a known enum.
+    fn missing_variants(&self, handled: &[(String, String)]) -> Option<Vec<String>> {
+        let (enum_name, _) = handled.first()?;
+        let variants = self.enums.get(enum_name)?;
+
+        Some(
+            variants
+                .iter()
+                .filter(|v| !handled.iter().any(|(e, h)| e == enum_name && h == *v))
+                .map(|v| format!("{}.{}", enum_name, v))
+                .collect(),
+        )
+    }
+
+    /// Reports constants which are reassigned or shadowed in the scope that
+    /// declares them, before the program runs. Each scope maps the names it
+    /// binds to whether they are constants. The evaluator catches the cases
+    /// this can't see, such as constants declared on an earlier REPL line.
+    fn check_constants(&mut self, node: &Node, scopes: &mut Vec<HashMap<String, bool>>) {
+        match node {
+            Node::Program { statements }
+            | Node::BlockStatement { statements }
+            | Node::ArrayLiteral {
+                elements: statements,
+            }
+            | Node::InterpolatedString { parts: statements }
+            | Node::HashLiteral {
+                entries: statements,
+            } => statements
+                .iter()
+                .for_each(|s| self.check_constants(s, scopes)),
+            Node::LetStatement { name, value } => {
+                if let Some(value) = value {
+                    self.check_constants(value, scopes);
+                }
+
+                self.declare(name, false, scopes);
+            }
+            Node::ConstStatement { name, value } => {
+                self.check_constants(value, scopes);
+                self.declare(name, true, scopes);
+            }
+            Node::StructStatement { name, .. }
+            | Node::EnumStatement { name, .. }
+            | Node::ImportStatement { alias: name, .. } => self.declare(name, false, scopes),
+            Node::ExportStatement { statement } => self.check_constants(statement, scopes),
+            Node::ImplStatement { methods, .. } => methods
+                .iter()
+                .for_each(|(_, function)| self.check_constants(function, scopes)),
+            Node::StructLiteral { fields, .. } => fields
+                .iter()
+                .for_each(|(_, value)| self.check_constants(value, scopes)),
+            Node::AssignStatement {
+                target,
+                value,
+                span,
+            } => {
+                self.check_constants(value, scopes);
+
+                let mut root = target.as_ref();
+                loop {
+                    root = match root {
+                        Node::IndexExpression { left, index, .. } => {
+                            self.check_constants(index, scopes);
+                            left
+                        }
+                        Node::MemberExpression { object, .. } => object,
+                        _ => break,
+                    };
+                }
+
+                let name = root.as_string();
+                if scopes.iter().rev().find_map(|s| s.get(&name)) == Some(&true) {
+                    self.errors
+                        .push(ParserError::ConstantReassigned { name, span: *span });
+                }
+            }
+            // Loop variables go away once the loop is done, so they are only
+            // checked against the constants of the scope.
+            Node::ForStatement {
+                variables,
+                iterable,
+                body,
+            } => {
+                self.check_constants(iterable, scopes);
+                for variable in variables {
+                    if let Node::Identifier { value } = variable {
+                        if scopes.last().and_then(|s| s.get(&value.v)) == Some(&true) {
+                            self.errors.push(ParserError::ConstantReassigned {
+                                name: value.v.clone(),
+                                span: value.span,
+                            });
+                        }
+                    }
+                }
+
+                self.check_constants(body, scopes);
+            }
+            Node::FunctionLiteral { parameters, body } => {
+                scopes.push(HashMap::new());
+                for parameter in parameters {
+                    let target = match parameter {
+                        Node::DefaultParameter { target, default } => {
+                            self.check_constants(default, scopes);
+                            target
+                        }
+                        Node::RestParameter { name } => name,
+                        other => other,
+                    };
+
+                    self.declare(target, false, scopes);
+                }
+
+                self.check_constants(body, scopes);
+                scopes.pop();
+            }
+            Node::MatchExpression { subject, arms, .. } => {
+                self.check_constants(subject, scopes);
+                for arm in arms {
+                    scopes.push(
+                        arm.pattern
+                            .bindings()
+                            .into_iter()
+                            .map(|name| (name, false))
+                            .collect(),
+                    );
+
+                    if let Some(guard) = &arm.guard {
+                        self.check_constants(guard, scopes);
+                    }
+
+                    self.check_constants(&arm.body, scopes);
+                    scopes.pop();
+                }
+            }
+            Node::TryExpression {
+                body,
+                parameter,
+                handler,
+                finalizer,
+            } => {
+                self.check_constants(body, scopes);
+                // Like loop variables, the parameter only lives as long as the
+                // handler.
+                if let Some(Node::Identifier { value }) = parameter.as_deref() {
+                    if scopes.last().and_then(|s| s.get(&value.v)) == Some(&true) {
+                        self.errors.push(ParserError::ConstantReassigned {
+                            name: value.v.clone(),
+                            span: value.span,
+                        });
+                    }
+                }
+
+                for block in handler.iter().chain(finalizer) {
+                    self.check_constants(block, scopes);
+                }
+            }
+            Node::IfExpression {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                self.check_constants(condition, scopes);
+                self.check_constants(consequence, scopes);
+                if let Some(alternative) = alternative {
+                    self.check_constants(alternative, scopes);
+                }
+            }
+            Node::CallExpression {
+                function,
+                arguments,
+                ..
+            } => {
+                self.check_constants(function, scopes);
+                arguments
+                    .iter()
+                    .for_each(|a| self.check_constants(a, scopes));
+            }
+            Node::InfixExpression { left, right, .. }
+            | Node::IndexExpression {
+                left, index: right, ..
+            }
+            | Node::HashPair {
+                key: left,
+                value: right,
+            } => {
+                self.check_constants(left, scopes);
+                self.check_constants(right, scopes);
+            }
+            Node::PrefixExpression { right: value, .. }
+            | Node::PostfixExpression { left: value, .. }
+            | Node::MemberExpression { object: value, .. }
+            | Node::SpreadExpression { value, .. }
+            | Node::NamedArgument { value, .. }
+            | Node::ThrowStatement { value, .. }
+            | Node::YieldStatement { value, .. } => self.check_constants(value, scopes),
+            Node::ReturnStatement { value } | Node::ExpressionStatement { expression: value } => {
+                if let Some(value) = value {
+                    self.check_constants(value, scopes);
+                }
+            }
+            Node::Identifier { .. }
+            | Node::IntegerLiteral { .. }
+            | Node::FloatLiteral { .. }
+            | Node::Boolean { .. }
+            | Node::NullLiteral
+            | Node::StringLiteral { .. }
+            | Node::DestructuringPattern { .. }
+            | Node::DefaultParameter { .. }
+            | Node::RestParameter { .. } => {}
+        }
+    }
+
+    /// Records the names bound by a let or const statement, function parameter
+    /// or catch parameter in the innermost scope. Constants can't be bound
+    /// again, and a constant can't take over a name already bound in the scope.
+    fn declare(&mut self, target: &Node, constant: bool, scopes: &mut [HashMap<String, bool>]) {
+        let (names, span) = match target {
+            Node::Identifier { value } => (vec![value.v.clone()], value.span),
+            Node::DestructuringPattern { pattern, span } => (pattern.bindings(), *span),
+            _ => return,
+        };
+
+        let scope = match scopes.last_mut() {
+            Some(scope) => scope,
+            None => return,
+        };
+
+        for name in names {
+            let previous = scope.get(&name).copied();
+            if previous == Some(true) || (constant && previous.is_some()) {
+                self.errors.push(ParserError::ConstantReassigned {
+                    name: name.clone(),
+                    span,
+                });
+            }
+
+            scope.insert(name, constant || previous == Some(true));
+        }
+    }
+
+    /// Parses an array or hash pattern used in place of a variable name.
+    fn parse_destructuring_pattern(&mut self) -> Result<Node, ParserError> {
+        let span = self.curr_token.span;
+        let pattern = match self.curr_token.t {
+            TokenType::LBracket => self.parse_array_pattern()?,
+            TokenType::LBrace => self.parse_hash_pattern()?,
+            _ => return Err(ParserError::IncorrectPattern),
+        };
+
+        Ok(Node::DestructuringPattern {
+            pattern: Box::new(pattern),
+            span,
+        })
+    }
+
+    /// Parses a pattern, along with any alternatives to it.
+    fn parse_pattern(&mut self) -> Result<Pattern, ParserError> {
+        let pattern = self.parse_single_pattern()?;
+        if self.peek_token.t != TokenType::Pipe {
+            return Ok(pattern);
+        }
+
+        let mut patterns = vec![pattern];
+        while self.peek_token.t == TokenType::Pipe {
+            self.next_token();
+            self.next_token();
+            patterns.push(self.parse_single_pattern()?);
+        }
+
+        Ok(Pattern::Alternative { patterns })
+    }
+
+    fn parse_single_pattern(&mut self) -> Result<Pattern, ParserError> {
+        match self.curr_token.t {
+            TokenType::Ident if self.curr_token.v == "_" => Ok(Pattern::Wildcard),
+            // A name which is a variant of an enum declared earlier matches
+            // that variant instead of binding the value.
+            TokenType::Ident
+                if matches!(self.peek_token.t, TokenType::LParen | TokenType::Dot)
+                    || self
+                        .enums
+                        .values()
+                        .flatten()
+                        .any(|v| *v == self.curr_token.v) =>
+            {
+                self.parse_variant_pattern()
+            }
+            TokenType::Ident => Ok(Pattern::Binding {
+                name: self.curr_token.v.clone(),
+            }),
+            TokenType::Int => Ok(Pattern::Literal {
+                value: self.parse_integer_literal()?,
+            }),
+            TokenType::Minus => {
+                if !self.expect_peek(TokenType::Int) {
+                    return Err(ParserError::IncorrectPattern);
+                }
+
+                match self.parse_integer_literal()? {
+                    Node::IntegerLiteral { value } => Ok(Pattern::Literal {
+                        value: Node::IntegerLiteral { value: -value },
+                    }),
+                    _ => Err(ParserError::IncorrectPattern),
+                }
+            }
+            TokenType::Str => Ok(Pattern::Literal {
+                value: Node::StringLiteral {
+                    value: self.curr_token.v.clone(),
+                },
+            }),
+            TokenType::True | TokenType::False => Ok(Pattern::Literal {
+                value: self.parse_boolean_expression()?,
+            }),
+            TokenType::Null => Ok(Pattern::Literal {
+                value: Node::NullLiteral,
+            }),
+            TokenType::LBracket => self.parse_array_pattern(),
+            TokenType::LBrace => self.parse_hash_pattern(),
+            _ => Err(ParserError::IncorrectPattern),
+        }
+    }
+
+    /// Parses a pattern for a variant of an enum, which is either qualified
+    /// with the name of its enum, or followed by patterns for its fields.
+    fn parse_variant_pattern(&mut self) -> Result<Pattern, ParserError> {
+        let mut enum_name = None;
+        let mut variant = self.curr_token.v.clone();
+        if self.peek_token.t == TokenType::Dot {
+            self.next_token();
+            if !self.expect_peek(TokenType::Ident) {
+                return Err(ParserError::IncorrectPattern);
+            }
+
+            enum_name = Some(variant);
+            variant = self.curr_token.v.clone();
+        }
+
+        if self.peek_token.t != TokenType::LParen {
+            return Ok(Pattern::Variant {
+                enum_name,
+                variant,
+                fields: None,
+            });
+        }
+
+        self.next_token();
+        let mut fields = vec![];
+        while self.peek_token.t != TokenType::RParen {
+            self.next_token();
+            fields.push(self.parse_pattern()?);
+
+            if self.peek_token.t != TokenType::RParen && !self.expect_peek(TokenType::Comma) {
+                return Err(ParserError::IncorrectPattern);
+            }
+        }
+
+        self.next_token();
+        Ok(Pattern::Variant {
+            enum_name,
+            variant,
+            fields: Some(fields),
+        })
+    }
+
+    fn parse_array_pattern(&mut self) -> Result<Pattern, ParserError> {
+        let mut elements = vec![];
+
+        while self.peek_token.t != TokenType::RBracket {
+            self.next_token();
+            if self.curr_token.t == TokenType::DotDot {
+                if elements.iter().any(|e| matches!(e, Pattern::Rest { .. })) {
+                    return Err(ParserError::IncorrectPattern);
+                }
+
+                let mut name = None;
+                if self.peek_token.t == TokenType::Ident {
+                    self.next_token();
+                    name = Some(self.curr_token.v.clone());
+                }
+
+                elements.push(Pattern::Rest { name });
+            } else {
+                elements.push(self.parse_pattern()?);
+            }
+
+            if self.peek_token.t != TokenType::RBracket && !self.expect_peek(TokenType::Comma) {
+                return Err(ParserError::IncorrectPattern);
+            }
+        }
+
+        self.next_token();
+        Ok(Pattern::Array { elements })
+    }
+
+    /// Parses a hash pattern. Keys which are names don't need to be quoted,
+    /// and can be given on their own, as a shorthand for binding the value of
+    /// that key to a variable of the same name.
+    fn parse_hash_pattern(&mut self) -> Result<Pattern, ParserError> {
+        let mut pairs = vec![];
+
+        while self.peek_token.t != TokenType::RBrace {
+            self.next_token();
+            let pair = match self.curr_token.t {
+                TokenType::Ident => {
+                    let name = self.curr_token.v.clone();
+                    let key = Node::StringLiteral {
+                        value: name.clone(),
+                    };
+
+                    if self.peek_token.t == TokenType::Colon {
+                        self.next_token();
+                        self.next_token();
+                        (key, self.parse_pattern()?)
+                    } else {
+                        (key, Pattern::Binding { name })
+                    }
+                }
+                TokenType::Int | TokenType::Str | TokenType::True | TokenType::False => {
+                    let key = match self.parse_single_pattern()? {
+                        Pattern::Literal { value } => value,
+                        _ => return Err(ParserError::IncorrectPattern),
+                    };
+
+                    if !self.expect_peek(TokenType::Colon) {
+                        return Err(ParserError::IncorrectPattern);
+                    }
+
+                    self.next_token();
+                    (key, self.parse_pattern()?)
+                }
+                _ => return Err(ParserError::IncorrectPattern),
+            };
+
+            pairs.push(pair);
+            if self.peek_token.t != TokenType::RBrace && !self.expect_peek(TokenType::Comma) {
+                return Err(ParserError::IncorrectPattern);
+            }
+        }
+
+        self.next_token();
+        Ok(Pattern::Hash { pairs })
+    }
+
     fn parse_block_statement(&mut self) -> Result<Node, ParserError> {
         let mut statements = vec![];
 
         self.next_token();
         while self.curr_token.t != TokenType::RBrace && self.curr_token.t != TokenType::EOF {
-            let stmt = self.parse_statement()?;
+            let stmt = self.within_condition(false, Self::parse_statement)?;
             statements.push(stmt);
             self.next_token();
         }
@@ -266,42 +1477,224 @@ impl<'a> Parser<'a> {
         Ok(Node::BlockStatement { statements })
     }
 
+    /// Runs a parsing function with struct literals disallowed or allowed,
+    /// and restores the previous setting afterwards.
+    fn within_condition(
+        &mut self,
+        parsing_condition: bool,
+        parse: impl FnOnce(&mut Self) -> Result<Node, ParserError>,
+    ) -> Result<Node, ParserError> {
+        let previous = std::mem::replace(&mut self.parsing_condition, parsing_condition);
+        let parsed = parse(self);
+        self.parsing_condition = previous;
+        parsed
+    }
+
     fn parse_function_literal(&mut self) -> Result<Node, ParserError> {
         if !self.expect_peek(TokenType::LParen) {
             return Err(ParserError::IncorrectFunctionDeclaration);
         }
 
         self.next_token();
+        let parameters = self.parse_parameters(TokenType::RParen)?;
+
+        self.next_token();
+        let body = self.parse_block_statement()?;
+        Ok(Node::FunctionLiteral {
+            parameters,
+            body: Box::new(body),
+        })
+    }
+
+    /// Parses a parameter list, up to the given closing token.
+    fn parse_parameters(&mut self, closing: TokenType) -> Result<Vec<Node>, ParserError> {
         let mut parameters = vec![];
-        while self.curr_token.t != TokenType::RParen {
+        while self.curr_token.t != closing {
             if self.curr_token.t == TokenType::Comma {
                 self.next_token();
             }
 
-            let parameter = Node::Identifier {
-                value: self.curr_token.clone(),
+            // A rest parameter collects the remaining arguments, so it has to
+            // come last.
+            if self.curr_token.t == TokenType::DotDotDot {
+                if !self.expect_peek(TokenType::Ident) {
+                    return Err(ParserError::IncorrectFunctionDeclaration);
+                }
+
+                parameters.push(Node::RestParameter {
+                    name: Box::new(Node::Identifier {
+                        value: self.curr_token.clone(),
+                    }),
+                });
+
+                if !self.expect_peek(closing) {
+                    return Err(ParserError::IncorrectFunctionDeclaration);
+                }
+
+                break;
+            }
+
+            let mut parameter = match self.curr_token.t {
+                TokenType::LBracket | TokenType::LBrace => self.parse_destructuring_pattern()?,
+                TokenType::Ident => Node::Identifier {
+                    value: self.curr_token.clone(),
+                },
+                _ => return Err(ParserError::IncorrectFunctionDeclaration),
             };
+
+            if self.peek_token.t == TokenType::Assign {
+                self.next_token();
+                self.next_token();
+                parameter = Node::DefaultParameter {
+                    target: Box::new(parameter),
+                    default: Box::new(self.parse_expression(Precedence::Lowest)?),
+                };
+            }
+
             parameters.push(parameter);
 
             self.next_token();
         }
 
+        Ok(parameters)
+    }
+
+    /// Parses a `|x, y| body` lambda, which is shorthand for a function
+    /// literal.
+    fn parse_pipe_lambda(&mut self) -> Result<Node, ParserError> {
         self.next_token();
-        let body = self.parse_block_statement()?;
+        let parameters = self.parse_parameters(TokenType::Pipe)?;
+
+        self.next_token();
+        self.parse_lambda_body(parameters)
+    }
+
+    /// Parses a `x => body` lambda, which is shorthand for a function literal
+    /// with a single parameter.
+    fn parse_arrow_lambda(&mut self) -> Result<Node, ParserError> {
+        let parameters = vec![Node::Identifier {
+            value: self.curr_token.clone(),
+        }];
+
+        self.next_token();
+        self.next_token();
+        self.parse_lambda_body(parameters)
+    }
+
+    /// Parses the body of a lambda, which is either a block or a single
+    /// expression.
+    fn parse_lambda_body(&mut self, parameters: Vec<Node>) -> Result<Node, ParserError> {
+        let body = if self.curr_token.t == TokenType::LBrace {
+            self.parse_block_statement()?
+        } else {
+            Node::BlockStatement {
+                statements: vec![Node::ExpressionStatement {
+                    expression: Some(Box::new(self.parse_expression(Precedence::Lowest)?)),
+                }],
+            }
+        };
+
         Ok(Node::FunctionLiteral {
             parameters,
             body: Box::new(body),
         })
     }
 
-    fn parse_call_expression(&mut self, function: Node) -> Result<Node, ParserError> {
+    /// Parses the right-hand side of a pipeline. `value |> f(a)` is rewritten
+    /// to `f(value, a)`, and `value |> f` to `f(value)`. The right-hand side
+    /// binds as tightly as a call, so `value |> f + 1` is `f(value) + 1`.
+    fn parse_pipeline(&mut self, left: Node) -> Result<Node, ParserError> {
+        let span = self.curr_token.span;
+
+        self.next_token();
+        match self.parse_expression(Precedence::Prefix)? {
+            Node::CallExpression {
+                function,
+                mut arguments,
+                optional,
+                span,
+            } => {
+                arguments.insert(0, left);
+                Ok(Node::CallExpression {
+                    function,
+                    arguments,
+                    optional,
+                    span,
+                })
+            }
+            function => Ok(Node::CallExpression {
+                function: Box::new(function),
+                arguments: vec![left],
+                optional: false,
+                span,
+            }),
+        }
+    }
+
+    fn parse_optional_chain(&mut self, left: Node) -> Result<Node, ParserError> {
+        match self.peek_token.t {
+            TokenType::LParen => {
+                self.next_token();
+                let span = self.curr_token.span;
+                self.next_token();
+                self.parse_call_expression(left, span, true)
+            }
+            TokenType::LBracket => {
+                self.next_token();
+                self.parse_index_expression(left, true)
+            }
+            TokenType::Ident => self.parse_member_expression(left, true),
+            _ => Err(ParserError::IncorrectOptionalChain),
+        }
+    }
+
+    fn parse_member_expression(
+        &mut self,
+        object: Node,
+        optional: bool,
+    ) -> Result<Node, ParserError> {
+        let span = self.curr_token.span;
+        if !self.expect_peek(TokenType::Ident) {
+            return Err(ParserError::IncorrectMemberExpression);
+        }
+
+        Ok(Node::MemberExpression {
+            object: Box::new(object),
+            property: Box::new(Node::Identifier {
+                value: self.curr_token.clone(),
+            }),
+            optional,
+            span,
+        })
+    }
+
+    fn parse_call_expression(
+        &mut self,
+        function: Node,
+        span: Span,
+        optional: bool,
+    ) -> Result<Node, ParserError> {
         let mut arguments = vec![];
         while self.curr_token.t != TokenType::RParen {
             if self.curr_token.t == TokenType::Comma {
                 self.next_token();
             }
 
-            let argument = self.parse_expression(Precedence::Lowest)?;
+            let argument =
+                if self.curr_token.t == TokenType::Ident && self.peek_token.t == TokenType::Colon {
+                    let name = Node::Identifier {
+                        value: self.curr_token.clone(),
+                    };
+
+                    self.next_token();
+                    self.next_token();
+                    Node::NamedArgument {
+                        name: Box::new(name),
+                        value: Box::new(self.parse_expression(Precedence::Lowest)?),
+                    }
+                } else {
+                    self.parse_element()?
+                };
             arguments.push(argument);
 
             self.next_token();
@@ -310,27 +1703,194 @@ impl<'a> Parser<'a> {
         Ok(Node::CallExpression {
             function: Box::new(function),
             arguments,
+            optional,
+            span,
+        })
+    }
+
+    fn parse_index_expression(&mut self, left: Node, optional: bool) -> Result<Node, ParserError> {
+        let span = self.curr_token.span;
+
+        self.next_token();
+        let index = self.parse_expression(Precedence::Lowest)?;
+        if !self.expect_peek(TokenType::RBracket) {
+            return Err(ParserError::IncorrectIndexExpression);
+        }
+
+        Ok(Node::IndexExpression {
+            left: Box::new(left),
+            index: Box::new(index),
+            optional,
+            span,
+        })
+    }
+
+    fn parse_array_literal(&mut self) -> Result<Node, ParserError> {
+        let mut elements = vec![];
+
+        while self.peek_token.t != TokenType::RBracket {
+            self.next_token();
+            elements.push(self.parse_element()?);
+
+            if self.peek_token.t != TokenType::RBracket && !self.expect_peek(TokenType::Comma) {
+                return Err(ParserError::IncorrectArrayLiteral);
+            }
+        }
+
+        self.next_token();
+        Ok(Node::ArrayLiteral { elements })
+    }
+
+    /// Parses a string literal with interpolated expressions, from its head up
+    /// to its tail. Empty strings between the expressions are left out.
+    fn parse_interpolated_string(&mut self) -> Result<Node, ParserError> {
+        let mut parts = vec![];
+        loop {
+            if !self.curr_token.v.is_empty() {
+                parts.push(Node::StringLiteral {
+                    value: self.curr_token.v.clone(),
+                });
+            }
+
+            if self.curr_token.t == TokenType::TemplateTail {
+                return Ok(Node::InterpolatedString { parts });
+            }
+
+            self.next_token();
+            parts.push(self.within_condition(false, |p| p.parse_expression(Precedence::Lowest))?);
+
+            self.next_token();
+            if !matches!(
+                self.curr_token.t,
+                TokenType::TemplateMiddle | TokenType::TemplateTail
+            ) {
+                return Err(ParserError::IncorrectInterpolation);
+            }
+        }
+    }
+
+    fn parse_hash_literal(&mut self) -> Result<Node, ParserError> {
+        let mut entries = vec![];
+
+        while self.peek_token.t != TokenType::RBrace {
+            self.next_token();
+            let entry = self.parse_element()?;
+            if let Node::SpreadExpression { .. } = entry {
+                entries.push(entry);
+            } else {
+                if !self.expect_peek(TokenType::Colon) {
+                    return Err(ParserError::IncorrectHashLiteral);
+                }
+
+                self.next_token();
+                entries.push(Node::HashPair {
+                    key: Box::new(entry),
+                    value: Box::new(self.parse_expression(Precedence::Lowest)?),
+                });
+            }
+
+            if self.peek_token.t != TokenType::RBrace && !self.expect_peek(TokenType::Comma) {
+                return Err(ParserError::IncorrectHashLiteral);
+            }
+        }
+
+        self.next_token();
+        Ok(Node::HashLiteral { entries })
+    }
+
+    /// Parses the fields given to a struct, e.g. `Point { x: 1, y }`.
+    fn parse_struct_literal(&mut self) -> Result<Node, ParserError> {
+        let name = Node::Identifier {
+            value: self.curr_token.clone(),
+        };
+        let span = self.curr_token.span;
+        self.next_token();
+
+        let mut fields = vec![];
+        while self.peek_token.t != TokenType::RBrace {
+            if !self.expect_peek(TokenType::Ident) {
+                return Err(ParserError::IncorrectStructLiteral);
+            }
+
+            let field = Node::Identifier {
+                value: self.curr_token.clone(),
+            };
+
+            let value = if self.peek_token.t == TokenType::Colon {
+                self.next_token();
+                self.next_token();
+                self.parse_expression(Precedence::Lowest)?
+            } else {
+                field.clone()
+            };
+
+            fields.push((field, value));
+            if self.peek_token.t != TokenType::RBrace && !self.expect_peek(TokenType::Comma) {
+                return Err(ParserError::IncorrectStructLiteral);
+            }
+        }
+
+        self.next_token();
+        Ok(Node::StructLiteral {
+            name: Box::new(name),
+            fields,
+            span,
+        })
+    }
+
+    /// Parses an element of an array or hash literal, or an argument of a
+    /// call, any of which can spread the items of a value with `...`.
+    fn parse_element(&mut self) -> Result<Node, ParserError> {
+        if self.curr_token.t != TokenType::DotDotDot {
+            return self.parse_expression(Precedence::Lowest);
+        }
+
+        let span = self.curr_token.span;
+        self.next_token();
+        Ok(Node::SpreadExpression {
+            value: Box::new(self.parse_expression(Precedence::Lowest)?),
+            span,
         })
     }
 
     fn check_curr_precedence(&mut self) -> Precedence {
+        if self.is_ternary(0) {
+            return Precedence::Ternary;
+        }
+
         match self.curr_token.t {
+            TokenType::NullCoalesce => Precedence::Coalesce,
+            TokenType::PipeForward => Precedence::Pipeline,
+            TokenType::Or => Precedence::LogicalOr,
+            TokenType::And => Precedence::LogicalAnd,
             TokenType::Equal | TokenType::NotEqual => Precedence::Equals,
             TokenType::LessThan | TokenType::GreaterThan => Precedence::LessGreater,
+            TokenType::DotDot | TokenType::DotDotEqual => Precedence::Range,
             TokenType::Plus | TokenType::Minus => Precedence::Sum,
             TokenType::Slash | TokenType::Asterisk => Precedence::Product,
-            TokenType::LParen => Precedence::Call,
+            TokenType::LParen | TokenType::Question | TokenType::OptionalChain => Precedence::Call,
+            TokenType::LBracket | TokenType::OptionalIndex | TokenType::Dot => Precedence::Index,
             _ => Precedence::Lowest,
         }
     }
 
     fn check_peek_precedence(&mut self) -> Precedence {
+        if self.is_ternary(1) {
+            return Precedence::Ternary;
+        }
+
         match self.peek_token.t {
+            TokenType::NullCoalesce => Precedence::Coalesce,
+            TokenType::PipeForward => Precedence::Pipeline,
+            TokenType::Or => Precedence::LogicalOr,
+            TokenType::And => Precedence::LogicalAnd,
             TokenType::Equal | TokenType::NotEqual => Precedence::Equals,
             TokenType::LessThan | TokenType::GreaterThan => Precedence::LessGreater,
+            TokenType::DotDot | TokenType::DotDotEqual => Precedence::Range,
             TokenType::Plus | TokenType::Minus => Precedence::Sum,
             TokenType::Slash | TokenType::Asterisk => Precedence::Product,
-            TokenType::LParen => Precedence::Call,
+            TokenType::LParen | TokenType::Question | TokenType::OptionalChain => Precedence::Call,
+            TokenType::LBracket | TokenType::OptionalIndex | TokenType::Dot => Precedence::Index,
             _ => Precedence::Lowest,
         }
     }
@@ -346,7 +1906,18 @@ impl<'a> Parser<'a> {
                 | TokenType::NotEqual
                 | TokenType::LessThan
                 | TokenType::GreaterThan
+                | TokenType::DotDot
+                | TokenType::DotDotEqual
                 | TokenType::LParen
+                | TokenType::LBracket
+                | TokenType::Question
+                | TokenType::NullCoalesce
+                | TokenType::PipeForward
+                | TokenType::And
+                | TokenType::Or
+                | TokenType::OptionalChain
+                | TokenType::OptionalIndex
+                | TokenType::Dot
         )
     }
 
@@ -361,7 +1932,131 @@ impl<'a> Parser<'a> {
 
     fn next_token(&mut self) {
         self.curr_token = self.peek_token.clone();
-        self.peek_token = self.lexer.next_token();
+        self.peek_token = self.next_peek_token.clone();
+        self.next_peek_token = self.lexer.next_token();
+    }
+
+    /// Returns true if a `?` token starts the branches of a ternary expression,
+    /// rather than being the postfix `?` operator. That is the case if an
+    /// expression starts right after the `?`, on the same line, and a matching
+    /// `:` follows before the expression ends. The `?` is the current token,
+    /// or the peek token if `offset` is 1. Tokens past the ones already read
+    /// are looked up with a copy of the lexer, up to `TERNARY_LOOKAHEAD` of
+    /// them. An expression which is still going by then is taken to be the
+    /// first branch of a ternary expression.
+    fn is_ternary(&mut self, offset: usize) -> bool {
+        let read = [&self.curr_token, &self.peek_token, &self.next_peek_token];
+        let (question, after) = (read[offset], &read[offset + 1..]);
+        let next = after[0];
+
+        let starts_expression = Self::starts_branch(next.t)
+            || matches!(
+                next.t,
+                TokenType::Minus | TokenType::LParen | TokenType::LBracket | TokenType::Or
+            );
+        if question.t != TokenType::Question
+            || next.span.line != question.span.line
+            || !starts_expression
+            || self.postfix_questions.contains(&question.span)
+        {
+            return false;
+        }
+
+        let mut lexer = self.lexer.clone();
+        let mut tokens = after
+            .iter()
+            .map(|t| (*t).clone())
+            .chain(std::iter::from_fn(move || Some(lexer.next_token())))
+            .take(TERNARY_LOOKAHEAD)
+            .peekable();
+
+        let mut depth = 0;
+        let mut branches = 1;
+        let mut postfix = vec![];
+        while let Some(token) = tokens.next() {
+            match token.t {
+                // In the condition of an if expression, the consequence
+                // starts with a brace, and in the guard of a match arm, the
+                // body starts after `=>`.
+                TokenType::LBrace if depth == 0 && self.parsing_condition => return false,
+                TokenType::FatArrow if depth == 0 && self.parsing_guard => return false,
+                TokenType::LParen
+                | TokenType::LBracket
+                | TokenType::LBrace
+                | TokenType::TemplateHead => depth += 1,
+                TokenType::RParen
+                | TokenType::RBracket
+                | TokenType::RBrace
+                | TokenType::TemplateTail => {
+                    if depth == 0 {
+                        return false;
+                    }
+
+                    depth -= 1;
+                }
+                // A nested ternary expression needs a `:` of its own. Tokens
+                // which may also follow the postfix `?` operator, such as `-`
+                // or `(`, are taken to follow it.
+                TokenType::Question if depth == 0 => {
+                    if tokens.peek().is_some_and(|t| Self::starts_branch(t.t)) {
+                        branches += 1;
+                    } else {
+                        postfix.push(token.span);
+                    }
+                }
+                TokenType::Colon if depth == 0 => {
+                    branches -= 1;
+                    if branches == 0 {
+                        self.postfix_questions.extend(postfix);
+                        return true;
+                    }
+                }
+                TokenType::Semicolon
+                | TokenType::Comma
+                | TokenType::Let
+                | TokenType::Const
+                | TokenType::Return
+                | TokenType::For
+                | TokenType::Throw
+                | TokenType::Yield
+                | TokenType::Struct
+                | TokenType::Impl
+                | TokenType::Enum
+                | TokenType::Import
+                | TokenType::Export
+                    if depth == 0 =>
+                {
+                    return false
+                }
+                TokenType::EOF => return false,
+                _ => {}
+            }
+        }
+
+        true
+    }
+
+    /// Returns true if a token can only start an expression, and can't carry
+    /// on the one before it.
+    fn starts_branch(t: TokenType) -> bool {
+        matches!(
+            t,
+            TokenType::Ident
+                | TokenType::Int
+                | TokenType::Float
+                | TokenType::Str
+                | TokenType::TemplateHead
+                | TokenType::True
+                | TokenType::False
+                | TokenType::Null
+                | TokenType::LBrace
+                | TokenType::Bang
+                | TokenType::Function
+                | TokenType::If
+                | TokenType::Match
+                | TokenType::Try
+                | TokenType::Pipe
+        )
     }
 }
 
@@ -470,7 +2165,7 @@ mod tests {
                         value: Token::new(TokenType::Ident, "foobar".to_string()),
                     })),
                 };
-                assert_eq!(stmt, ident);
+                assert_eq!(without_spans(&stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "foobar".to_string());
             }
             _ => panic!("Unsupported node type"),
@@ -494,7 +2189,7 @@ mod tests {
                 let ident = Node::ExpressionStatement {
                     expression: Some(Box::new(Node::IntegerLiteral { value: 5 })),
                 };
-                assert_eq!(stmt, ident);
+                assert_eq!(without_spans(&stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "5".to_string());
             }
             _ => panic!("Unsupported node type"),
@@ -524,9 +2219,10 @@ mod tests {
                     expression: Some(Box::new(Node::PrefixExpression {
                         operator: "!".to_string(),
                         right: Box::new(Node::IntegerLiteral { value: 5 }),
+                        span: Span::default(),
                     })),
                 };
-                assert_eq!(stmt, ident);
+                assert_eq!(without_spans(&stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "!".to_string());
 
                 let stmt = iter.next().unwrap();
@@ -534,9 +2230,10 @@ mod tests {
                     expression: Some(Box::new(Node::PrefixExpression {
                         operator: "-".to_string(),
                         right: Box::new(Node::IntegerLiteral { value: 15 }),
+                        span: Span::default(),
                     })),
                 };
-                assert_eq!(stmt, ident);
+                assert_eq!(without_spans(&stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "-".to_string());
 
                 let stmt = iter.next().unwrap();
@@ -544,9 +2241,10 @@ mod tests {
                     expression: Some(Box::new(Node::PrefixExpression {
                         operator: "!".to_string(),
                         right: Box::new(Node::Boolean { value: true }),
+                        span: Span::default(),
                     })),
                 };
-                assert_eq!(stmt, ident);
+                assert_eq!(without_spans(&stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "!".to_string());
 
                 let stmt = iter.next().unwrap();
@@ -554,9 +2252,10 @@ mod tests {
                     expression: Some(Box::new(Node::PrefixExpression {
                         operator: "!".to_string(),
                         right: Box::new(Node::Boolean { value: false }),
+                        span: Span::default(),
                     })),
                 };
-                assert_eq!(stmt, ident);
+                assert_eq!(without_spans(&stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "!".to_string());
             }
             _ => panic!("Unsupported node type"),
@@ -594,9 +2293,10 @@ mod tests {
                         left: Box::new(Node::IntegerLiteral { value: 5 }),
                         operator: "+".to_string(),
                         right: Box::new(Node::IntegerLiteral { value: 5 }),
+                        span: Span::default(),
                     })),
                 };
-                assert_eq!(stmt, ident);
+                assert_eq!(without_spans(&stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "+".to_string());
 
                 let stmt = iter.next().unwrap();
@@ -605,9 +2305,10 @@ mod tests {
                         left: Box::new(Node::IntegerLiteral { value: 5 }),
                         operator: "-".to_string(),
                         right: Box::new(Node::IntegerLiteral { value: 5 }),
+                        span: Span::default(),
                     })),
                 };
-                assert_eq!(stmt, ident);
+                assert_eq!(without_spans(&stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "-".to_string());
 
                 let stmt = iter.next().unwrap();
@@ -616,9 +2317,10 @@ mod tests {
                         left: Box::new(Node::IntegerLiteral { value: 5 }),
                         operator: "*".to_string(),
                         right: Box::new(Node::IntegerLiteral { value: 5 }),
+                        span: Span::default(),
                     })),
                 };
-                assert_eq!(stmt, ident);
+                assert_eq!(without_spans(&stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "*".to_string());
 
                 let stmt = iter.next().unwrap();
@@ -627,9 +2329,10 @@ mod tests {
                         left: Box::new(Node::IntegerLiteral { value: 5 }),
                         operator: "/".to_string(),
                         right: Box::new(Node::IntegerLiteral { value: 5 }),
+                        span: Span::default(),
                     })),
                 };
-                assert_eq!(stmt, ident);
+                assert_eq!(without_spans(&stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "/".to_string());
 
                 let stmt = iter.next().unwrap();
@@ -638,9 +2341,10 @@ mod tests {
                         left: Box::new(Node::IntegerLiteral { value: 5 }),
                         operator: ">".to_string(),
                         right: Box::new(Node::IntegerLiteral { value: 5 }),
+                        span: Span::default(),
                     })),
                 };
-                assert_eq!(stmt, ident);
+                assert_eq!(without_spans(&stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), ">".to_string());
 
                 let stmt = iter.next().unwrap();
@@ -649,9 +2353,10 @@ mod tests {
                         left: Box::new(Node::IntegerLiteral { value: 5 }),
                         operator: "<".to_string(),
                         right: Box::new(Node::IntegerLiteral { value: 5 }),
+                        span: Span::default(),
                     })),
                 };
-                assert_eq!(stmt, ident);
+                assert_eq!(without_spans(&stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "<".to_string());
 
                 let stmt = iter.next().unwrap();
@@ -660,9 +2365,10 @@ mod tests {
                         left: Box::new(Node::IntegerLiteral { value: 5 }),
                         operator: "==".to_string(),
                         right: Box::new(Node::IntegerLiteral { value: 5 }),
+                        span: Span::default(),
                     })),
                 };
-                assert_eq!(stmt, ident);
+                assert_eq!(without_spans(&stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "==".to_string());
 
                 let stmt = iter.next().unwrap();
@@ -671,9 +2377,10 @@ mod tests {
                         left: Box::new(Node::IntegerLiteral { value: 5 }),
                         operator: "!=".to_string(),
                         right: Box::new(Node::IntegerLiteral { value: 5 }),
+                        span: Span::default(),
                     })),
                 };
-                assert_eq!(stmt, ident);
+                assert_eq!(without_spans(&stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "!=".to_string());
 
                 let stmt = iter.next().unwrap();
@@ -682,9 +2389,10 @@ mod tests {
                         left: Box::new(Node::Boolean { value: true }),
                         operator: "==".to_string(),
                         right: Box::new(Node::Boolean { value: true }),
+                        span: Span::default(),
                     })),
                 };
-                assert_eq!(stmt, ident);
+                assert_eq!(without_spans(&stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "==".to_string());
 
                 let stmt = iter.next().unwrap();
@@ -693,9 +2401,10 @@ mod tests {
                         left: Box::new(Node::Boolean { value: true }),
                         operator: "!=".to_string(),
                         right: Box::new(Node::Boolean { value: false }),
+                        span: Span::default(),
                     })),
                 };
-                assert_eq!(stmt, ident);
+                assert_eq!(without_spans(&stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "!=".to_string());
 
                 let stmt = iter.next().unwrap();
@@ -704,9 +2413,10 @@ mod tests {
                         left: Box::new(Node::Boolean { value: false }),
                         operator: "==".to_string(),
                         right: Box::new(Node::Boolean { value: false }),
+                        span: Span::default(),
                     })),
                 };
-                assert_eq!(stmt, ident);
+                assert_eq!(without_spans(&stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "==".to_string());
             }
             _ => panic!("Unsupported node type"),
@@ -732,14 +2442,14 @@ mod tests {
                 let ident = Node::ExpressionStatement {
                     expression: Some(Box::new(Node::Boolean { value: true })),
                 };
-                assert_eq!(*stmt, ident);
+                assert_eq!(without_spans(stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "true".to_string());
 
                 let stmt = iter.next().unwrap();
                 let ident = Node::ExpressionStatement {
                     expression: Some(Box::new(Node::Boolean { value: false })),
                 };
-                assert_eq!(*stmt, ident);
+                assert_eq!(without_spans(stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "false".to_string());
             }
             _ => panic!("Unsupported node type"),
@@ -771,6 +2481,7 @@ mod tests {
                             right: Box::new(Node::Identifier {
                                 value: Token::new(TokenType::Ident, "y".to_string()),
                             }),
+                            span: Span::default(),
                         }),
                         consequence: Box::new(Node::BlockStatement {
                             statements: vec![Node::ExpressionStatement {
@@ -782,7 +2493,7 @@ mod tests {
                         alternative: None,
                     })),
                 };
-                assert_eq!(*stmt, ident);
+                assert_eq!(without_spans(stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "if".to_string());
             }
             _ => panic!("Unsupported node type"),
@@ -814,6 +2525,7 @@ mod tests {
                             right: Box::new(Node::Identifier {
                                 value: Token::new(TokenType::Ident, "y".to_string()),
                             }),
+                            span: Span::default(),
                         }),
                         consequence: Box::new(Node::BlockStatement {
                             statements: vec![Node::ExpressionStatement {
@@ -831,7 +2543,7 @@ mod tests {
                         })),
                     })),
                 };
-                assert_eq!(*stmt, ident);
+                assert_eq!(without_spans(stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "if".to_string());
             }
             _ => panic!("Unsupported node type"),
@@ -873,12 +2585,13 @@ mod tests {
                                     right: Box::new(Node::Identifier {
                                         value: Token::new(TokenType::Ident, "y".to_string()),
                                     }),
+                                    span: Span::default(),
                                 })),
                             }],
                         }),
                     })),
                 };
-                assert_eq!(*stmt, ident);
+                assert_eq!(without_spans(stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "fn".to_string());
             }
             _ => panic!("Unexpected node type"),
@@ -887,7 +2600,7 @@ mod tests {
 
     #[test]
     fn test_function_parameter_parsing() {
-        let table = vec![
+        let table = [
             ("fn() {};", vec![]),
             ("fn(x) {};", vec!["x"]),
             ("fn(x, y) {};", vec!["x", "y"]),
@@ -948,16 +2661,20 @@ mod tests {
                                 left: Box::new(Node::IntegerLiteral { value: 2 }),
                                 operator: "*".to_string(),
                                 right: Box::new(Node::IntegerLiteral { value: 3 }),
+                                span: Span::default(),
                             },
                             Node::InfixExpression {
                                 left: Box::new(Node::IntegerLiteral { value: 4 }),
                                 operator: "+".to_string(),
                                 right: Box::new(Node::IntegerLiteral { value: 5 }),
+                                span: Span::default(),
                             },
                         ],
+                        optional: false,
+                        span: Span::default(),
                     })),
                 };
-                assert_eq!(*stmt, ident);
+                assert_eq!(without_spans(stmt), without_spans(&ident));
                 assert_eq!(stmt.token_literal(), "add".to_string());
             }
             _ => panic!("Unexpected node type"),
@@ -966,7 +2683,7 @@ mod tests {
 
     #[test]
     fn test_call_expression_parameter_parsing() {
-        let table = vec![
+        let table = [
             ("add();", vec![]),
             ("add(x);", vec!["x"]),
             ("add(x, y);", vec!["x", "y"]),
@@ -989,6 +2706,7 @@ mod tests {
                             Node::CallExpression {
                                 function: _,
                                 arguments,
+                                ..
                             } => {
                                 assert_eq!(arguments.len(), output.len());
                                 arguments.iter().zip(output.iter()).for_each(|(a, o)| {
@@ -1007,10 +2725,11 @@ mod tests {
 
     #[test]
     fn test_operator_precedence_parsing() {
-        let table = vec![
+        let table = [
             ("-a * b;", "((-a) * b);"),
             ("!-a;", "(!(-a));"),
             ("a + b + c;", "((a + b) + c);"),
+            ("-1.5 * 2.0;", "((-1.5) * 2.0);"),
             ("a + b - c;", "((a + b) - c);"),
             ("a * b * c;", "((a * b) * c);"),
             ("a * b / c;", "((a * b) / c);"),
@@ -1045,17 +2764,300 @@ mod tests {
                 "add(a + b + c * d / f + g);",
                 "add((((a + b) + ((c * d) / f)) + g));",
             ),
+            ("a * b[c] + d;", "((a * (b[c])) + d);"),
+            ("add(a[\"b\"], c[d][e]);", "add((a[\"b\"]), ((c[d])[e]));"),
+            ("{\"a\": 1 + 2, b: c};", "{\"a\": (1 + 2), b: c};"),
+            ("{};", "{};"),
+            ("a + f(x)? * 2;", "(a + ((f(x)?) * 2));"),
+            ("-a?;", "(-(a?));"),
+            ("a[0]?;", "((a[0])?);"),
+            ("a ?? b == c;", "(a ?? (b == c));"),
+            ("a ?? b ?? null;", "((a ?? b) ?? null);"),
+            ("a?[b]?[c] ?? d;", "(((a?[b])?[c]) ?? d);"),
+            ("a?.[b] + f?.(1, 2);", "((a?[b]) + f?.(1, 2));"),
+            ("[1, 2 * 3, a[0]];", "[1, (2 * 3), (a[0])];"),
+            ("[];", "[];"),
+            ("0..n - 1 == r;", "((0 .. (n - 1)) == r);"),
+            ("1..=n * 2;", "(1 ..= (n * 2));"),
+            ("for (x in 0..10) { f(x); }", "for (x in (0 .. 10)) f(x);"),
+            ("for (k, v in h) { v; }", "for (k, v in h) v;"),
+            ("fn() { yield x + 1; }", "fn() yield (x + 1);;"),
+            ("let [a, ..rest] = x;", "let [a, ..rest] = x;"),
+            (
+                "let {name, age: years} = p;",
+                "let {\"name\": name, \"age\": years} = p;",
+            ),
+            ("fn([a, _], {c}) { a; }", "fn([a, _], {\"c\": c}) a;;"),
+            (
+                "fn(x, y = x * 2, ...r) { r; }",
+                "fn(x, y = (x * 2), ...r) r;;",
+            ),
+            ("f(1, y: 2 + 3);", "f(1, y: (2 + 3));"),
+            ("[...a, 1, ...b + c];", "[...a, 1, ...(b + c)];"),
+            ("{...d, \"k\": v};", "{...d, \"k\": v};"),
+            ("f(...args, x: 1);", "f(...args, x: 1);"),
+            ("|x, y = 2| x + y;", "fn(x, y = 2) (x + y);;"),
+            ("x => x * 2;", "fn(x) (x * 2);;"),
+            ("|| { a; };", "fn() a;;"),
+            ("x |> f(1) |> g;", "g(f(x, 1));"),
+            ("x + 1 |> f;", "f((x + 1));"),
+            ("3 |> f + 1;", "(f(3) + 1);"),
+            ("x |> obj.f(1) * 2;", "((obj.f)(x, 1) * 2);"),
+            ("a ?? b |> f;", "(a ?? f(b));"),
+            ("xs |> map(|x| x * 2);", "map(xs, fn(x) (x * 2););"),
+            ("a || b && c;", "(a || (b && c));"),
+            ("a == b && c != d;", "((a == b) && (c != d));"),
+            ("a ?? b || c;", "(a ?? (b || c));"),
+            ("a || b ? c : d;", "if (a || b) c;else d;;"),
+            ("a ? b : c ? d : e;", "if a b;else if c d;else e;;;"),
+            ("f()? + 1;", "((f()?) + 1);"),
+            ("f()? - 1;", "((f()?) - 1);"),
+            ("c ? -x : y;", "if c (-x);else y;;"),
+            ("c ? !x : y;", "if c (!x);else y;;"),
+            ("c ? f()? : y;", "if c (f()?);else y;;"),
+            ("c ? f()? - 1 : y;", "if c ((f()?) - 1);else y;;"),
+            ("a ? b ? c : d : e;", "if a if b c;else d;;else e;;"),
+            ("c ? x => x : x => 0;", "if c fn(x) x;;else fn(x) 0;;;"),
+            (
+                "match (v) { n if c ? n : 0 => n };",
+                "match (v) { n if if c n;else 0; => n };",
+            ),
+            ("let a = f()?\nx;", "let a = (f()?);x;"),
+            (
+                "if (a) { b } else if (c) { d } else { e };",
+                "if a b;else if c d;else e;;;",
+            ),
+            ("struct Point { x, y }", "struct Point { x, y }"),
+            (
+                "P { x: 1 + 2, y }.x * 2;",
+                "((P { x: (1 + 2), y: y }.x) * 2);",
+            ),
+            ("a.b[c].d?.e;", "((((a.b)[c]).d)?.e);"),
+            ("p.x = p.y + 1;", "(p.x) = ((p.y) + 1);"),
+            ("if x == y { z };", "if (x == y) z;;"),
+            ("if (P { x }).x { z };", "if (P { x: x }.x) z;;"),
+            (
+                "\"a ${b + c * d}${e} f\" + g;",
+                "(\"a ${(b + (c * d))}${e} f\" + g);",
+            ),
+            ("x ? \"${y}\" : z;", "if x \"${y}\";else z;;"),
+            (
+                "impl P { fn len(self) { self.x } fn new() { P {} } }",
+                "impl P { fn len(self) (self.x); fn new() P {  }; }",
+            ),
+            ("p.f(1).g;", "((p.f)(1).g);"),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let lexer = Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+
+            let program = parser.parse_program();
+            assert!(!did_parser_fail(parser.errors));
+
+            assert_eq!(&program.as_string(), *output);
+        });
+
+        // Branches longer than the lookahead are still read as ternaries.
+        let long = vec!["1"; TERNARY_LOOKAHEAD].join(", ");
+        let input = format!("c ? [{}] : y;", long);
+        let mut parser = Parser::new(Lexer::new(&input));
+        let program = parser.parse_program();
+        assert!(!did_parser_fail(parser.errors));
+        assert_eq!(program.as_string(), format!("if c [{}];else y;;", long));
+    }
+
+    #[test]
+    fn test_try_expression_parsing() {
+        let table = [
+            ("try { a; } catch (e) { b; };", "try a; catch (e) b;;"),
+            ("try { a; } finally { c; };", "try a; finally c;;"),
+            (
+                "try { throw \"x\"; } catch (e) { b; } finally { c; };",
+                "try throw \"x\"; catch (e) b; finally c;;",
+            ),
+        ];
+
+        table.iter().for_each(|(input, output)| {
+            let lexer = Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+
+            let program = parser.parse_program();
+            assert!(!did_parser_fail(parser.errors));
+
+            assert_eq!(&program.as_string(), *output);
+        });
+
+        let lexer = Lexer::new("try { a; };");
+        let mut parser = Parser::new(lexer);
+        parser.parse_program();
+        assert!(matches!(
+            parser.errors[0],
+            ParserError::IncorrectTryExpression
+        ));
+    }
+
+    #[test]
+    fn test_match_expression_parsing() {
+        let table = [
+            (
+                "match (x) { 1 | -2 => a, [h, ..t] if h > 0 => { b; }, {\"k\": v, n} => v, _ => c };",
+                "match (x) { 1 | -2 => a, [h, ..t] if (h > 0) => b;, {\"k\": v, \"n\": n} => v, _ => c };",
+            ),
+            ("match (b) { true => 1, false => 0 };", "match (b) { true => 1, false => 0 };"),
         ];
 
         table.iter().for_each(|(input, output)| {
-            let lexer = Lexer::new(*input);
+            let lexer = Lexer::new(input);
             let mut parser = Parser::new(lexer);
 
             let program = parser.parse_program();
             assert!(!did_parser_fail(parser.errors));
+            assert!(parser.warnings.is_empty());
 
             assert_eq!(&program.as_string(), *output);
         });
+
+        let lexer =
+            Lexer::new("match (x) { 1 => a, x => b, 2 => c };\nmatch (x) { 1 => a, 1 => b };");
+        let mut parser = Parser::new(lexer);
+        parser.parse_program();
+        assert!(parser.errors.is_empty());
+        assert!(matches!(
+            parser.warnings.as_slice(),
+            [
+                ParserWarning::UnreachableMatchArm { .. },
+                ParserWarning::UnreachableMatchArm { .. },
+                ParserWarning::NonExhaustiveMatch { .. }
+            ]
+        ));
+
+        let lexer = Lexer::new("match (x) { [..a, ..b] => a };");
+        let mut parser = Parser::new(lexer);
+        parser.parse_program();
+        assert!(matches!(parser.errors[0], ParserError::IncorrectPattern));
+    }
+
+    #[test]
+    fn test_enum_parsing() {
+        let input = "enum Shape { Circle(r), Rect(w, h), Empty }
+            match (s) { Circle(r) => r, Shape.Rect(w, _) => w, Empty => 0 };";
+
+        let mut parser = Parser::new(Lexer::new(input));
+        let program = parser.parse_program();
+        assert!(!did_parser_fail(parser.errors));
+        assert!(parser.warnings.is_empty());
+        assert_eq!(
+            program.as_string(),
+            "enum Shape { Circle(r), Rect(w, h), Empty }match (s) { Circle(r) => r, Shape.Rect(w, _) => w, Empty => 0 };"
+        );
+
+        let input = "enum Shape { Circle(r), Rect(w, h), Empty }
+            match (s) { Circle(1) => 1, Rect(w, h) => w };
+            match (s) { Circle(_) | Rect(_, _) => 1, Empty => 0, x => x };";
+
+        let mut parser = Parser::new(Lexer::new(input));
+        parser.parse_program();
+        assert!(parser.errors.is_empty());
+        match parser.warnings.as_slice() {
+            [ParserWarning::MissingVariants { variants, .. }, ParserWarning::UnreachableMatchArm { .. }] =>
+            {
+                assert_eq!(variants, &["Shape.Circle", "Shape.Empty"])
+            }
+            warnings => panic!("Unexpected warnings, {:?}", warnings),
+        }
+
+        // Enums declared elsewhere, e.g. on an earlier REPL line, can't be
+        // checked.
+        let input = "match (s) { Shape.Circle(r) => r, Shape.Empty => 0 };
+            match (s) { Circle(r) => r };";
+        let mut parser = Parser::new(Lexer::new(input));
+        parser.parse_program();
+        assert!(parser.errors.is_empty());
+        assert!(parser.warnings.is_empty());
+
+        let input = "enum A { X }; impl A { fn f() { 1 }; }; struct B { y }; 1;";
+        let mut parser = Parser::new(Lexer::new(input));
+        let program = parser.parse_program();
+        assert!(!did_parser_fail(parser.errors));
+        match program {
+            Node::Program { statements } => assert_eq!(statements.len(), 4),
+            node => panic!("Unexpected node, {:?}", node),
+        }
+
+        let mut parser = Parser::new(Lexer::new("enum E { A(1) }"));
+        parser.parse_program();
+        assert!(matches!(
+            parser.errors[0],
+            ParserError::IncorrectEnumDeclaration
+        ));
+    }
+
+    #[test]
+    fn test_import_and_export_statements() {
+        let input = "import \"lib/a.mk\" as a; export let x = a.f(1); export struct P { x }";
+        let mut parser = Parser::new(Lexer::new(input));
+        let program = parser.parse_program();
+        assert!(!did_parser_fail(parser.errors));
+        assert_eq!(
+            program.as_string(),
+            "import \"lib/a.mk\" as a;export let x = (a.f)(1);export struct P { x }"
+        );
+
+        let errors = [
+            ("import a as b;", ParserError::IncorrectImportStatement),
+            ("import \"a.mk\";", ParserError::IncorrectImportStatement),
+            ("export 1;", ParserError::IncorrectExportStatement),
+            (
+                "let f = fn() { export let x = 1; };",
+                ParserError::ExportOutsideTopLevel,
+            ),
+        ];
+
+        errors.iter().for_each(|(input, error)| {
+            let mut parser = Parser::new(Lexer::new(input));
+            parser.parse_program();
+            assert_eq!(format!("{:?}", parser.errors[0]), format!("{:?}", error));
+        });
+    }
+
+    #[test]
+    fn test_constant_checks() {
+        let table = [
+            ("const a = 1; let a = 2;", vec!["a"]),
+            ("const a = 1; if (true) { a = 2 }", vec!["a"]),
+            ("let a = 1; const a = 2;", vec!["a"]),
+            ("const a = 1; let f = fn(b) { b = a; a = 2 };", vec!["a"]),
+            ("const [a, b] = [1, 2]; let {b} = {b: 3};", vec!["b"]),
+            ("const a = 1; for (a in 0..2) {}", vec!["a"]),
+            ("const a = 1; try { 1 } catch (a) { a };", vec!["a"]),
+            ("try { 1 } catch (a) { a }; const a = 2;", vec![]),
+            ("const a = 1; let f = fn(a) { let a = 2 };", vec![]),
+            ("const a = 1; match (a) { a => a };", vec![]),
+            ("let a = 1; let a = 2; a = 3;", vec![]),
+        ];
+
+        table.iter().for_each(|(input, names)| {
+            let mut parser = Parser::new(Lexer::new(input));
+            parser.parse_program();
+
+            let reported: Vec<&str> = parser
+                .errors
+                .iter()
+                .map(|e| match e {
+                    ParserError::ConstantReassigned { name, .. } => name.as_str(),
+                    other => panic!("Unexpected error, {:?}", other),
+                })
+                .collect();
+            assert_eq!(reported, *names, "{}", input);
+        });
+
+        let mut parser = Parser::new(Lexer::new("f() = 1;"));
+        parser.parse_program();
+        assert!(matches!(
+            parser.errors[0],
+            ParserError::InvalidAssignmentTarget
+        ));
     }
 
     #[test]
@@ -1072,8 +3074,24 @@ mod tests {
         println!("{}", program.as_string());
     }
 
+    /// Returns the debug representation of a node with its spans left out, so
+    /// that nodes can be compared without spelling out where they appear.
+    fn without_spans(node: &Node) -> String {
+        let debug = format!("{:?}", node);
+        let mut output = String::new();
+        let mut rest = debug.as_str();
+        while let Some(start) = rest.find("Span {") {
+            output.push_str(&rest[..start]);
+            let end = start + rest[start..].find('}').unwrap();
+            rest = &rest[end + 1..];
+        }
+
+        output.push_str(rest);
+        output
+    }
+
     fn did_parser_fail(errors: Vec<ParserError>) -> bool {
-        if errors.len() == 0 {
+        if errors.is_empty() {
             false
         } else {
             errors.iter().for_each(|e| {
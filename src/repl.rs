@@ -1,5 +1,6 @@
 use crate::eval::eval;
-use crate::eval::Environment;
+use crate::eval::object::Object;
+use crate::eval::{prelude, Environment};
 use crate::lexer::Lexer;
 use crate::parser::Parser;
 use std::io::{self, Write};
@@ -7,27 +8,39 @@ use std::io::{self, Write};
 /// Start the REPL.
 pub fn start() {
     let mut environment = Environment::new();
+    if let Object::Error { value } = prelude::load(&mut environment) {
+        println!("{}", value.traceback());
+    }
 
     loop {
         print!(">> ");
         io::stdout().flush().unwrap();
 
         let mut input = String::new();
-        io::stdin()
+        let read = io::stdin()
             .read_line(&mut input)
             .expect("could not read input");
+        if read == 0 {
+            break;
+        }
 
         let lexer = Lexer::new(&input);
         let mut parser = Parser::new(lexer);
 
         let program = parser.parse_program();
+        parser.warnings.iter().for_each(|w| {
+            println!("{}", w);
+        });
+
         if !parser.errors.is_empty() {
             parser.errors.iter().for_each(|e| {
                 println!("{:?}", e);
             })
         }
 
-        let evaluated = eval(program, &mut environment);
-        println!("{}", evaluated.inspect());
+        match eval(program, &mut environment) {
+            Object::Error { value } => println!("{}", value.traceback()),
+            evaluated => println!("{}", evaluated.inspect()),
+        }
     }
 }
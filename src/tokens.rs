@@ -1,45 +1,103 @@
+use std::fmt;
+
 /// All the possible tokens that can be created by the lexer.
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
 pub enum TokenType {
+    #[default]
     Illegal,
     EOF,
     Ident,
     Int,
+    Float,
+    Str,
 
     // Operators
     Assign,
     Plus,
     Minus,
     Bang,
+    Question,
+    NullCoalesce,
+    OptionalChain,
+    OptionalIndex,
     Asterisk,
     Slash,
 
     LessThan,
     GreaterThan,
 
+    Dot,
+    DotDot,
+    DotDotEqual,
+    DotDotDot,
+
     Equal,
     NotEqual,
 
     Comma,
+    Colon,
+    FatArrow,
+    Pipe,
+    PipeForward,
+    And,
+    Or,
     Semicolon,
+    /// The parts of a string literal around the expressions interpolated into
+    /// it, e.g. `"a ${`, `} b ${` and `} c"`.
+    TemplateHead,
+    TemplateMiddle,
+    TemplateTail,
     LParen,
     RParen,
     LBrace,
     RBrace,
+    LBracket,
+    RBracket,
 
     // Keywords
     Function,
     Let,
+    Const,
     True,
     False,
+    Null,
     If,
     Else,
     Return,
+    For,
+    In,
+    Try,
+    Catch,
+    Finally,
+    Throw,
+    Yield,
+    Match,
+    Struct,
+    Impl,
+    Enum,
+    Import,
+    Export,
+    As,
+}
+
+/// A position in the source input, used to point runtime errors and stack
+/// frames back at the code that produced them.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
 }
 
-impl Default for TokenType {
-    fn default() -> Self {
-        TokenType::Illegal
+impl Span {
+    /// Creates a new Span pointing at the given line and column.
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
     }
 }
 
@@ -47,50 +105,47 @@ impl Default for TokenType {
 pub struct Token {
     pub t: TokenType,
     pub v: String,
+    pub span: Span,
 }
 
 impl Token {
     /// Creates a new Token with the given type and string value.
     pub fn new(t: TokenType, v: String) -> Self {
-        Self { t, v }
+        Self {
+            t,
+            v,
+            span: Span::default(),
+        }
     }
 }
 
 impl From<&str> for Token {
     fn from(value: &str) -> Self {
         match value {
-            "fn" => Token {
-                t: TokenType::Function,
-                v: value.into(),
-            },
-            "let" => Token {
-                t: TokenType::Let,
-                v: value.into(),
-            },
-            "true" => Token {
-                t: TokenType::True,
-                v: value.into(),
-            },
-            "false" => Token {
-                t: TokenType::False,
-                v: value.into(),
-            },
-            "if" => Token {
-                t: TokenType::If,
-                v: value.into(),
-            },
-            "else" => Token {
-                t: TokenType::Else,
-                v: value.into(),
-            },
-            "return" => Token {
-                t: TokenType::Return,
-                v: value.into(),
-            },
-            _ => Token {
-                t: TokenType::Ident,
-                v: value.into(),
-            },
+            "fn" => Token::new(TokenType::Function, value.into()),
+            "let" => Token::new(TokenType::Let, value.into()),
+            "const" => Token::new(TokenType::Const, value.into()),
+            "true" => Token::new(TokenType::True, value.into()),
+            "false" => Token::new(TokenType::False, value.into()),
+            "null" => Token::new(TokenType::Null, value.into()),
+            "if" => Token::new(TokenType::If, value.into()),
+            "else" => Token::new(TokenType::Else, value.into()),
+            "return" => Token::new(TokenType::Return, value.into()),
+            "for" => Token::new(TokenType::For, value.into()),
+            "in" => Token::new(TokenType::In, value.into()),
+            "try" => Token::new(TokenType::Try, value.into()),
+            "catch" => Token::new(TokenType::Catch, value.into()),
+            "finally" => Token::new(TokenType::Finally, value.into()),
+            "throw" => Token::new(TokenType::Throw, value.into()),
+            "yield" => Token::new(TokenType::Yield, value.into()),
+            "match" => Token::new(TokenType::Match, value.into()),
+            "struct" => Token::new(TokenType::Struct, value.into()),
+            "impl" => Token::new(TokenType::Impl, value.into()),
+            "enum" => Token::new(TokenType::Enum, value.into()),
+            "import" => Token::new(TokenType::Import, value.into()),
+            "export" => Token::new(TokenType::Export, value.into()),
+            "as" => Token::new(TokenType::As, value.into()),
+            _ => Token::new(TokenType::Ident, value.into()),
         }
     }
 }